@@ -3,18 +3,45 @@ use std::process::Command;
 fn main() {
     build_shader("lightmap_texture.vert");
     build_shader("lightmap_texture.frag");
+    build_shader_variant("lightmap_texture.frag", "lightmap_only.frag", &["LIGHTMAP_MODE=1"]);
+    build_shader_variant("lightmap_texture.frag", "diffuse_only.frag", &["LIGHTMAP_MODE=2"]);
+    build_shader_variant("lightmap_texture.vert", "lightmap_vertex_color.vert", &["VERTEX_COLOR"]);
+    build_shader_variant("lightmap_texture.frag", "lightmap_vertex_color.frag", &["VERTEX_COLOR"]);
+    build_shader("simple_texture.vert");
+    build_shader("simple_texture.frag");
+    build_shader("lit_texture.vert");
+    build_shader("lit_texture.frag");
+    build_shader("vertex_color.vert");
+    build_shader("vertex_color.frag");
+    build_shader("water.vert");
+    build_shader("water.frag");
+    build_shader("sky.vert");
+    build_shader("sky.frag");
+    build_shader("billboard.vert");
+    build_shader("billboard.frag");
+    build_shader("particle.vert");
+    build_shader("particle.frag");
+    build_shader("screen_texture.vert");
+    // Screen quads are tinted like particles, so they share the shader
+    build_shader_variant("particle.frag", "screen_texture.frag", &[]);
 }
 
 fn build_shader(shader_name: &str) {
+    build_shader_variant(shader_name, shader_name, &[]);
+}
+
+fn build_shader_variant(shader_name: &str, output_name: &str, defines: &[&str]) {
     let out_dir = std::env::var("OUT_DIR").unwrap();
     println!("{}", out_dir);
     let path = format!("src/shaders/{}", shader_name);
     println!("cargo:rerun-if-changed={}", path);
-    let shader_out_dir = format!("{}/{}.spv", out_dir, shader_name);
+    let shader_out_dir = format!("{}/{}.spv", out_dir, output_name);
+    let mut args: Vec<String> = defines.iter().map(|d| format!("-D{}", d)).collect();
+    args.extend_from_slice(&[path, "-o".to_string(), shader_out_dir]);
     let output = Command::new("glslc")
-        .args(&[path, "-o".to_string(), shader_out_dir])
+        .args(&args)
         .output()
-        .expect(&format!("Failed to compile shader {}", shader_name));
+        .expect(&format!("Failed to compile shader {}", output_name));
 
     println!("{}", std::str::from_utf8(&output.stdout).unwrap());
 }
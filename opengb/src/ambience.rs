@@ -0,0 +1,291 @@
+use crate::config::read_key_values;
+use crate::particles::EmitterDesc;
+use radiance::math::Vec3;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmbientSound {
+    pub file: PathBuf,
+    pub position: [f32; 3],
+    // Distance at which the sound fades out completely
+    pub radius: f32,
+    pub volume: f32,
+}
+
+impl AmbientSound {
+    pub fn new<P: AsRef<Path>>(file: P) -> Self {
+        AmbientSound {
+            file: file.as_ref().to_path_buf(),
+            position: [0., 0., 0.],
+            radius: 500.,
+            volume: 1.,
+        }
+    }
+
+    pub fn volume_at(&self, listener: [f32; 3]) -> f32 {
+        let d = distance(self.position, listener);
+        if d >= self.radius {
+            0.
+        } else {
+            self.volume * (1. - d / self.radius)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AmbientEmitter {
+    pub texture: PathBuf,
+    pub position: [f32; 3],
+    pub desc: EmitterDesc,
+}
+
+impl AmbientEmitter {
+    pub fn new<P: AsRef<Path>>(texture: P) -> Self {
+        AmbientEmitter {
+            texture: texture.as_ref().to_path_buf(),
+            position: [0., 0., 0.],
+            desc: EmitterDesc::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlickerLight {
+    pub name: String,
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub min_intensity: f32,
+    pub max_intensity: f32,
+    // Rough number of flickers per second
+    pub frequency: f32,
+}
+
+impl FlickerLight {
+    pub fn new(name: &str) -> Self {
+        FlickerLight {
+            name: name.to_string(),
+            position: [0., 0., 0.],
+            color: [1., 1., 1.],
+            min_intensity: 1.,
+            max_intensity: 1.,
+            frequency: 0.,
+        }
+    }
+
+    // Deterministic so that the same scene flickers the same way on every
+    // run. A few incommensurate sines read as noise and never repeat visibly.
+    pub fn intensity(&self, time: f32) -> f32 {
+        let t = time * self.frequency * std::f32::consts::PI * 2.;
+        let noise = (t.sin() + (t * 2.31 + 1.7).sin() * 0.5 + (t * 4.73 + 0.3).sin() * 0.25)
+            / 1.75;
+        let k = noise * 0.5 + 0.5;
+        self.min_intensity + (self.max_intensity - self.min_intensity) * k
+    }
+}
+
+// Per-scene ambience: looping positional sounds, particle emitters and
+// flickering lights. It lives next to the scene as `<scene>.ambience` and
+// uses the same layout as scene descriptions: `sound = <file>`,
+// `emitter = <texture>` and `light = <name>` each start a new item, and the
+// lines after it describe that item. Relative paths are resolved against
+// the directory of the file. Blank lines and lines starting with '#' are
+// ignored.
+#[derive(Debug, Clone)]
+pub struct SceneAmbience {
+    pub sounds: Vec<AmbientSound>,
+    pub emitters: Vec<AmbientEmitter>,
+    pub lights: Vec<FlickerLight>,
+}
+
+#[derive(Clone, Copy)]
+enum Item {
+    None,
+    Sound,
+    Emitter,
+    Light,
+}
+
+impl SceneAmbience {
+    pub fn new() -> Self {
+        SceneAmbience {
+            sounds: vec![],
+            emitters: vec![],
+            lights: vec![],
+        }
+    }
+
+    pub fn path_for_scene<P: AsRef<Path>>(scene_path: P) -> PathBuf {
+        scene_path.as_ref().with_extension("ambience")
+    }
+
+    // Scenes without ambience are common, so a missing file is not an error.
+    pub fn load_for_scene<P: AsRef<Path>>(scene_path: P) -> Result<Self, Box<dyn Error>> {
+        let path = SceneAmbience::path_for_scene(scene_path);
+        if path.exists() {
+            SceneAmbience::load_from_file(path)
+        } else {
+            Ok(SceneAmbience::new())
+        }
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut base_dir = path.as_ref().to_path_buf();
+        base_dir.pop();
+
+        let mut ambience = SceneAmbience::new();
+        let mut item = Item::None;
+        read_key_values(path, |key, value| {
+            let floats: Vec<f32> = value
+                .split_whitespace()
+                .filter_map(|c| c.parse().ok())
+                .collect();
+            match (key, item) {
+                ("sound", _) if !value.is_empty() => {
+                    ambience.sounds.push(AmbientSound::new(base_dir.join(value)));
+                    item = Item::Sound;
+                    true
+                }
+                ("emitter", _) if !value.is_empty() => {
+                    ambience
+                        .emitters
+                        .push(AmbientEmitter::new(base_dir.join(value)));
+                    item = Item::Emitter;
+                    true
+                }
+                ("light", _) if !value.is_empty() => {
+                    ambience.lights.push(FlickerLight::new(value));
+                    item = Item::Light;
+                    true
+                }
+                (_, Item::Sound) => {
+                    parse_sound_line(ambience.sounds.last_mut().unwrap(), key, &floats)
+                }
+                (_, Item::Emitter) => {
+                    parse_emitter_line(ambience.emitters.last_mut().unwrap(), key, &floats)
+                }
+                (_, Item::Light) => {
+                    parse_light_line(ambience.lights.last_mut().unwrap(), key, &floats)
+                }
+                _ => false,
+            }
+        })?;
+
+        Ok(ambience)
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+fn parse_sound_line(sound: &mut AmbientSound, key: &str, floats: &[f32]) -> bool {
+    match (key, floats) {
+        ("position", &[x, y, z]) => sound.position = [x, y, z],
+        ("radius", &[r]) => sound.radius = r,
+        ("volume", &[v]) => sound.volume = v,
+        _ => return false,
+    }
+
+    true
+}
+
+fn parse_emitter_line(emitter: &mut AmbientEmitter, key: &str, floats: &[f32]) -> bool {
+    let desc = &mut emitter.desc;
+    match (key, floats) {
+        ("position", &[x, y, z]) => emitter.position = [x, y, z],
+        ("spawn_rate", &[r]) => desc.spawn_rate = r,
+        ("max_particles", &[n]) if n >= 1. => desc.max_particles = n as usize,
+        ("lifetime", &[min, max]) => desc.lifetime = (min, max),
+        ("velocity", &[x, y, z]) => desc.velocity = Vec3::new(x, y, z),
+        ("velocity_spread", &[x, y, z]) => desc.velocity_spread = Vec3::new(x, y, z),
+        ("acceleration", &[x, y, z]) => desc.acceleration = Vec3::new(x, y, z),
+        ("size", &[start, end]) => {
+            desc.start_size = start;
+            desc.end_size = end;
+        }
+        ("start_color", &[r, g, b]) => desc.start_color = Vec3::new(r, g, b),
+        ("end_color", &[r, g, b]) => desc.end_color = Vec3::new(r, g, b),
+        _ => return false,
+    }
+
+    true
+}
+
+fn parse_light_line(light: &mut FlickerLight, key: &str, floats: &[f32]) -> bool {
+    match (key, floats) {
+        ("position", &[x, y, z]) => light.position = [x, y, z],
+        ("color", &[r, g, b]) => light.color = [r, g, b],
+        ("intensity", &[min, max]) => {
+            light.min_intensity = min;
+            light.max_intensity = max;
+        }
+        ("frequency", &[f]) => light.frequency = f,
+        _ => return false,
+    }
+
+    true
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let d = [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt()
+}
+
+fn vec3_str(v: &Vec3) -> String {
+    format!("{} {} {}", v.x, v.y, v.z)
+}
+
+impl std::fmt::Display for SceneAmbience {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut first = true;
+        let mut separate = |f: &mut std::fmt::Formatter| {
+            let result = if first { Ok(()) } else { writeln!(f) };
+            first = false;
+            result
+        };
+
+        for sound in &self.sounds {
+            separate(f)?;
+            writeln!(f, "sound = {}", sound.file.display())?;
+            let p = sound.position;
+            writeln!(f, "position = {} {} {}", p[0], p[1], p[2])?;
+            writeln!(f, "radius = {}", sound.radius)?;
+            writeln!(f, "volume = {}", sound.volume)?;
+        }
+
+        for emitter in &self.emitters {
+            separate(f)?;
+            let desc = &emitter.desc;
+            writeln!(f, "emitter = {}", emitter.texture.display())?;
+            let p = emitter.position;
+            writeln!(f, "position = {} {} {}", p[0], p[1], p[2])?;
+            writeln!(f, "spawn_rate = {}", desc.spawn_rate)?;
+            writeln!(f, "max_particles = {}", desc.max_particles)?;
+            writeln!(f, "lifetime = {} {}", desc.lifetime.0, desc.lifetime.1)?;
+            writeln!(f, "velocity = {}", vec3_str(&desc.velocity))?;
+            writeln!(f, "velocity_spread = {}", vec3_str(&desc.velocity_spread))?;
+            writeln!(f, "acceleration = {}", vec3_str(&desc.acceleration))?;
+            writeln!(f, "size = {} {}", desc.start_size, desc.end_size)?;
+            writeln!(f, "start_color = {}", vec3_str(&desc.start_color))?;
+            writeln!(f, "end_color = {}", vec3_str(&desc.end_color))?;
+        }
+
+        for light in &self.lights {
+            separate(f)?;
+            writeln!(f, "light = {}", light.name)?;
+            let p = light.position;
+            writeln!(f, "position = {} {} {}", p[0], p[1], p[2])?;
+            let c = light.color;
+            writeln!(f, "color = {} {} {}", c[0], c[1], c[2])?;
+            writeln!(
+                f,
+                "intensity = {} {}",
+                light.min_intensity, light.max_intensity
+            )?;
+            writeln!(f, "frequency = {}", light.frequency)?;
+        }
+
+        Ok(())
+    }
+}
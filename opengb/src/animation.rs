@@ -0,0 +1,288 @@
+use radiance::math::{Quaternion, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationLoopMode {
+    Once,
+    Loop,
+    PingPong,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keyframe<T> {
+    pub timestamp: f32,
+    pub value: T,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationEvent {
+    pub timestamp: f32,
+    pub name: String,
+}
+
+pub trait Interpolate: Copy {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for Vec3 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Vec3::new(
+            self.x * (1. - t) + other.x * t,
+            self.y * (1. - t) + other.y * t,
+            self.z * (1. - t) + other.z * t,
+        )
+    }
+}
+
+impl Interpolate for Quaternion {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        // Normalized lerp along the shorter arc
+        let dot = self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w;
+        let sign = if dot < 0. { -1. } else { 1. };
+        let x = self.x * (1. - t) + other.x * t * sign;
+        let y = self.y * (1. - t) + other.y * t * sign;
+        let z = self.z * (1. - t) + other.z * t * sign;
+        let w = self.w * (1. - t) + other.w * t * sign;
+        let len = (x * x + y * y + z * z + w * w).sqrt();
+        if len == 0. {
+            *self
+        } else {
+            Quaternion::new(x / len, y / len, z / len, w / len)
+        }
+    }
+}
+
+// Finds the two keyframes surrounding `time` and how far `time` is between
+// them. Times before the first or after the last keyframe clamp to it.
+pub fn find_keyframes(timestamps: &[f32], time: f32) -> (usize, usize, f32) {
+    if timestamps.is_empty() {
+        return (0, 0, 0.);
+    }
+
+    match timestamps.iter().position(|&t| t > time) {
+        None => (timestamps.len() - 1, timestamps.len() - 1, 0.),
+        Some(0) => (0, 0, 0.),
+        Some(next) => {
+            let prev = next - 1;
+            let span = timestamps[next] - timestamps[prev];
+            let percentile = if span > 0. {
+                (time - timestamps[prev]) / span
+            } else {
+                0.
+            };
+
+            (prev, next, percentile)
+        }
+    }
+}
+
+// The keyframe at or before `time`
+pub fn keyframe_index(timestamps: &[f32], time: f32) -> usize {
+    find_keyframes(timestamps, time).0
+}
+
+fn sample_track<T: Interpolate>(track: &[Keyframe<T>], time: f32) -> Option<T> {
+    if track.is_empty() {
+        return None;
+    }
+
+    let timestamps: Vec<f32> = track.iter().map(|k| k.timestamp).collect();
+    let (prev, next, percentile) = find_keyframes(&timestamps, time);
+    Some(track[prev].value.interpolate(&track[next].value, percentile))
+}
+
+// A component driving an entity's timeline. Entities add it when loading
+// and call `update` from `on_updating`, then read the sampled tracks or the
+// current time for their own per-frame data (e.g. morph frames).
+pub struct KeyframeAnimation {
+    translation_track: Vec<Keyframe<Vec3>>,
+    rotation_track: Vec<Keyframe<Quaternion>>,
+    scale_track: Vec<Keyframe<Vec3>>,
+    events: Vec<AnimationEvent>,
+    duration: f32,
+    time: f32,
+    speed: f32,
+    loop_mode: AnimationLoopMode,
+    playing: bool,
+    reversed: bool,
+}
+
+impl KeyframeAnimation {
+    pub fn new(duration: f32, loop_mode: AnimationLoopMode) -> Self {
+        KeyframeAnimation {
+            translation_track: vec![],
+            rotation_track: vec![],
+            scale_track: vec![],
+            events: vec![],
+            duration,
+            time: 0.,
+            speed: 1.,
+            loop_mode,
+            playing: true,
+            reversed: false,
+        }
+    }
+
+    pub fn with_translation_track(mut self, track: Vec<Keyframe<Vec3>>) -> Self {
+        self.translation_track = track;
+        self
+    }
+
+    pub fn with_rotation_track(mut self, track: Vec<Keyframe<Quaternion>>) -> Self {
+        self.rotation_track = track;
+        self
+    }
+
+    pub fn with_scale_track(mut self, track: Vec<Keyframe<Vec3>>) -> Self {
+        self.scale_track = track;
+        self
+    }
+
+    pub fn with_event(mut self, timestamp: f32, name: &str) -> Self {
+        self.events.push(AnimationEvent {
+            timestamp,
+            name: name.to_owned(),
+        });
+        self
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time.min(self.duration).max(0.);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.);
+    }
+
+    pub fn loop_mode(&self) -> AnimationLoopMode {
+        self.loop_mode
+    }
+
+    pub fn set_loop_mode(&mut self, loop_mode: AnimationLoopMode) {
+        self.loop_mode = loop_mode;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn play(&mut self) {
+        if self.loop_mode == AnimationLoopMode::Once && self.time >= self.duration {
+            self.time = 0.;
+        }
+
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    // Jumps `count` keyframes forward or backward from the current one,
+    // wrapping around the ends. Used to single-step morph animations whose
+    // frames don't live in the tracks.
+    pub fn step_keyframes(&mut self, timestamps: &[f32], count: i32) {
+        if timestamps.is_empty() {
+            return;
+        }
+
+        let len = timestamps.len() as i32;
+        let current = keyframe_index(timestamps, self.time) as i32;
+        let index = ((current + count) % len + len) % len;
+        self.set_time(timestamps[index as usize]);
+    }
+
+    pub fn translation(&self) -> Option<Vec3> {
+        sample_track(&self.translation_track, self.time)
+    }
+
+    pub fn rotation(&self) -> Option<Quaternion> {
+        sample_track(&self.rotation_track, self.time)
+    }
+
+    pub fn scale(&self) -> Option<Vec3> {
+        sample_track(&self.scale_track, self.time)
+    }
+
+    // Advances the timeline and returns the names of the events passed.
+    pub fn update(&mut self, delta_sec: f32) -> Vec<String> {
+        let mut fired = vec![];
+        if !self.playing || self.duration <= 0. {
+            return fired;
+        }
+
+        let step = delta_sec * self.speed;
+        let old_time = self.time;
+        match self.loop_mode {
+            AnimationLoopMode::Once => {
+                self.time = (old_time + step).min(self.duration);
+                self.collect_events(old_time, self.time, self.time >= self.duration, &mut fired);
+                if self.time >= self.duration {
+                    self.playing = false;
+                }
+            }
+            AnimationLoopMode::Loop => {
+                let new_time = old_time + step;
+                if new_time >= self.duration {
+                    self.collect_events(old_time, self.duration, false, &mut fired);
+                    self.time = new_time % self.duration;
+                    self.collect_events(0., self.time, false, &mut fired);
+                } else {
+                    self.collect_events(old_time, new_time, false, &mut fired);
+                    self.time = new_time;
+                }
+            }
+            AnimationLoopMode::PingPong => {
+                let mut new_time = if self.reversed {
+                    old_time - step
+                } else {
+                    old_time + step
+                };
+
+                if new_time >= self.duration {
+                    new_time = (2. * self.duration - new_time).max(0.);
+                    self.reversed = true;
+                    self.collect_events(old_time, self.duration, true, &mut fired);
+                    self.collect_events(new_time, self.duration, false, &mut fired);
+                } else if new_time <= 0. {
+                    new_time = (-new_time).min(self.duration);
+                    self.reversed = false;
+                    self.collect_events(0., old_time, false, &mut fired);
+                    self.collect_events(0., new_time, false, &mut fired);
+                } else {
+                    self.collect_events(
+                        old_time.min(new_time),
+                        old_time.max(new_time),
+                        false,
+                        &mut fired,
+                    );
+                }
+
+                self.time = new_time;
+            }
+        }
+
+        fired
+    }
+
+    fn collect_events(&self, from: f32, to: f32, inclusive: bool, fired: &mut Vec<String>) {
+        for event in &self.events {
+            if event.timestamp >= from
+                && (event.timestamp < to || (inclusive && event.timestamp == to))
+            {
+                fired.push(event.name.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::config::read_key_values;
+use std::collections::HashMap;
+use std::error::Error;
+use std::hash::Hash;
+use std::path::Path;
+
+pub const MEGABYTE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EvictionPolicy {
+    LeastRecentlyUsed,
+    LargestFirst,
+}
+
+// Byte budgets for the caches, read from lines like `texture_cache = 256`
+// (in megabytes). Keys that are missing keep their defaults. A budget of 0
+// turns its cache off, which is the default: the decoded textures are kept
+// on top of the copies radiance has uploaded, so the cache trades memory
+// for load time.
+#[derive(Debug, Clone)]
+pub struct MemoryBudgets {
+    pub texture_cache: usize,
+    pub eviction_policy: EvictionPolicy,
+}
+
+impl Default for MemoryBudgets {
+    fn default() -> Self {
+        MemoryBudgets {
+            texture_cache: 0,
+            eviction_policy: EvictionPolicy::LeastRecentlyUsed,
+        }
+    }
+}
+
+impl MemoryBudgets {
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut budgets = MemoryBudgets::default();
+        read_key_values(path, |key, value| {
+            let megabytes = || value.parse::<usize>().ok().map(|mb| mb * MEGABYTE);
+            let parsed = match key {
+                "texture_cache" => megabytes().map(|b| budgets.texture_cache = b),
+                "eviction_policy" => match value {
+                    "lru" => Some(EvictionPolicy::LeastRecentlyUsed),
+                    "largest" => Some(EvictionPolicy::LargestFirst),
+                    _ => None,
+                }
+                .map(|p| budgets.eviction_policy = p),
+                _ => None,
+            };
+
+            parsed.is_some()
+        })?;
+
+        Ok(budgets)
+    }
+}
+
+struct CacheEntry<V> {
+    value: V,
+    size: usize,
+    last_used: u64,
+}
+
+// A cache holding at most `budget` bytes, as measured by the sizes given
+// on insertion. Entries are evicted according to `policy` to make room.
+pub struct BudgetedCache<K: Hash + Eq + Clone, V> {
+    name: String,
+    entries: HashMap<K, CacheEntry<V>>,
+    budget: usize,
+    usage: usize,
+    policy: EvictionPolicy,
+    clock: u64,
+    eviction_count: u64,
+    rejected_count: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> BudgetedCache<K, V> {
+    pub fn new(name: &str, budget: usize, policy: EvictionPolicy) -> Self {
+        BudgetedCache {
+            name: name.to_owned(),
+            entries: HashMap::new(),
+            budget,
+            usage: 0,
+            policy,
+            clock: 0,
+            eviction_count: 0,
+            rejected_count: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|e| {
+            e.last_used = clock;
+            &e.value
+        })
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    // Returns false if the value alone is larger than the whole budget, in
+    // which case it is not cached.
+    pub fn insert(&mut self, key: K, value: V, size: usize) -> bool {
+        self.remove(&key);
+        if size > self.budget {
+            self.rejected_count += 1;
+            return false;
+        }
+
+        while self.usage + size > self.budget {
+            self.evict_one();
+        }
+
+        self.clock += 1;
+        self.usage += size;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                size,
+                last_used: self.clock,
+            },
+        );
+
+        true
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|e| {
+            self.usage -= e.size;
+            e.value
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.usage = 0;
+    }
+
+    // Lowering the budget evicts entries right away.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        while self.usage > self.budget {
+            self.evict_one();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn report(&self) -> CacheReport {
+        CacheReport {
+            name: self.name.clone(),
+            usage: self.usage,
+            budget: self.budget,
+            entry_count: self.entries.len(),
+            eviction_count: self.eviction_count,
+            rejected_count: self.rejected_count,
+        }
+    }
+
+    fn evict_one(&mut self) {
+        let victim = match self.policy {
+            EvictionPolicy::LeastRecentlyUsed => self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone()),
+            EvictionPolicy::LargestFirst => self
+                .entries
+                .iter()
+                .max_by_key(|(_, e)| (e.size, std::u64::MAX - e.last_used))
+                .map(|(k, _)| k.clone()),
+        };
+
+        if let Some(key) = victim {
+            self.remove(&key);
+            self.eviction_count += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheReport {
+    pub name: String,
+    pub usage: usize,
+    pub budget: usize,
+    pub entry_count: usize,
+    pub eviction_count: u64,
+    pub rejected_count: u64,
+}
+
+impl CacheReport {
+    // Set once the cache had to evict entries or turn away an asset larger
+    // than the whole budget, i.e. the budget is too small for the content
+    // being played.
+    pub fn needs_warning(&self) -> bool {
+        self.eviction_count > 0 || self.rejected_count > 0
+    }
+}
+
+impl std::fmt::Display for CacheReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {:.1}/{:.1} MB, {} entries, {} evicted, {} too large",
+            self.name,
+            self.usage as f32 / MEGABYTE as f32,
+            self.budget as f32 / MEGABYTE as f32,
+            self.entry_count,
+            self.eviction_count,
+            self.rejected_count
+        )
+    }
+}
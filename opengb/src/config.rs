@@ -0,0 +1,51 @@
+use std::error::Error;
+use std::path::Path;
+
+// A line of the `key = value` files opengb reads its configuration from,
+// with the key and value trimmed. `value` is None for lines without '='.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyValueLine<'a> {
+    pub number: usize,
+    pub key: &'a str,
+    pub value: Option<&'a str>,
+}
+
+// The lines of `text`, numbered from 1. Blank lines and lines starting with
+// '#' are skipped.
+pub fn key_value_lines(text: &str) -> impl Iterator<Item = KeyValueLine<'_>> {
+    text.lines().enumerate().filter_map(|(i, line)| {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let mut kv = line.splitn(2, '=');
+        Some(KeyValueLine {
+            number: i + 1,
+            key: kv.next().unwrap_or("").trim(),
+            value: kv.next().map(|v| v.trim()),
+        })
+    })
+}
+
+// Passes each `key = value` line of the file at `path` to `parse`, which
+// returns false for lines it doesn't understand. Those, and lines without
+// '=', are reported and skipped.
+pub fn read_key_values<P: AsRef<Path>, F: FnMut(&str, &str) -> bool>(
+    path: P,
+    mut parse: F,
+) -> Result<(), Box<dyn Error>> {
+    let text = std::fs::read_to_string(&path)?;
+    for line in key_value_lines(&text) {
+        let parsed = match line.value {
+            Some(value) => parse(line.key, value),
+            None => false,
+        };
+
+        if !parsed {
+            println!("{:?}:{}: ignoring malformed line", path.as_ref(), line.number);
+        }
+    }
+
+    Ok(())
+}
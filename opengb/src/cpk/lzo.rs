@@ -0,0 +1,252 @@
+use std::error::Error;
+
+const M2_MAX_OFFSET: usize = 0x0800;
+
+// LZO1X decompression as done by miniLZO, which CPK archives are packed
+// with. Corrupt input fails instead of reading or writing out of bounds,
+// and never grows the output past `output_size`. A run of zero bytes adds
+// 255 to a length, which bounds the output by the input size.
+pub fn lzo1x_decompress(input: &[u8], output_size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut d = Decompressor {
+        input,
+        ip: 0,
+        output: Vec::with_capacity(output_size.min(input.len().saturating_mul(255))),
+        output_size,
+    };
+
+    d.run()?;
+    if d.output.len() != output_size {
+        return Err(format!(
+            "lzo: expected {} bytes, got {}",
+            output_size,
+            d.output.len()
+        )
+        .into());
+    }
+
+    Ok(d.output)
+}
+
+struct Decompressor<'a> {
+    input: &'a [u8],
+    ip: usize,
+    output: Vec<u8>,
+    output_size: usize,
+}
+
+impl<'a> Decompressor<'a> {
+    fn next(&mut self) -> Result<usize, Box<dyn Error>> {
+        let b = *self.input.get(self.ip).ok_or("lzo: input overrun")?;
+        self.ip += 1;
+        Ok(b as usize)
+    }
+
+    fn next_u16(&mut self) -> Result<usize, Box<dyn Error>> {
+        let lo = self.next()?;
+        let hi = self.next()?;
+        Ok(lo | (hi << 8))
+    }
+
+    // Runs of zero bytes each add 255 to a length
+    fn long_length(&mut self, base: usize) -> Result<usize, Box<dyn Error>> {
+        let mut t = 0;
+        loop {
+            let b = self.next()?;
+            if b != 0 {
+                return Ok(t + base + b);
+            }
+
+            t += 255;
+        }
+    }
+
+    fn reserve(&self, count: usize) -> Result<(), Box<dyn Error>> {
+        if self.output.len() + count > self.output_size {
+            return Err("lzo: output overrun".into());
+        }
+
+        Ok(())
+    }
+
+    fn copy_literals(&mut self, count: usize) -> Result<(), Box<dyn Error>> {
+        self.reserve(count)?;
+        let end = self.ip + count;
+        let literals = self.input.get(self.ip..end).ok_or("lzo: input overrun")?;
+        self.output.extend_from_slice(literals);
+        self.ip = end;
+        Ok(())
+    }
+
+    // Matches may overlap their own output, so bytes are copied one by one
+    fn copy_match(&mut self, distance: usize, count: usize) -> Result<(), Box<dyn Error>> {
+        if distance == 0 || distance > self.output.len() {
+            return Err("lzo: lookbehind overrun".into());
+        }
+
+        self.reserve(count)?;
+        let start = self.output.len() - distance;
+        for i in 0..count {
+            let b = self.output[start + i];
+            self.output.push(b);
+        }
+
+        Ok(())
+    }
+
+    fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        let first = *self.input.get(0).ok_or("lzo: empty input")? as usize;
+        let mut state = if first > 17 {
+            self.ip = 1;
+            let count = first - 17;
+            self.copy_literals(count)?;
+            let t = self.next()?;
+            if count < 4 {
+                State::Match(t)
+            } else {
+                State::AfterLiterals(t)
+            }
+        } else {
+            State::Start
+        };
+
+        loop {
+            state = match state {
+                State::Start => {
+                    let t = self.next()?;
+                    if t >= 16 {
+                        State::Match(t)
+                    } else {
+                        let count = if t == 0 { self.long_length(15)? } else { t };
+                        self.copy_literals(count + 3)?;
+                        State::AfterLiterals(self.next()?)
+                    }
+                }
+                State::AfterLiterals(t) if t < 16 => {
+                    let distance = 1 + M2_MAX_OFFSET + (t >> 2) + (self.next()? << 2);
+                    self.copy_match(distance, 3)?;
+                    self.after_match()?
+                }
+                State::AfterLiterals(t) | State::Match(t) => {
+                    if !self.decode_match(t)? {
+                        return Ok(());
+                    }
+
+                    self.after_match()?
+                }
+            };
+        }
+    }
+
+    // Returns false at the end of stream marker
+    fn decode_match(&mut self, t: usize) -> Result<bool, Box<dyn Error>> {
+        if t >= 64 {
+            let distance = 1 + ((t >> 2) & 7) + (self.next()? << 3);
+            self.copy_match(distance, (t >> 5) + 1)?;
+        } else if t >= 32 {
+            let length = if t & 31 == 0 {
+                self.long_length(31)?
+            } else {
+                t & 31
+            };
+            let distance = 1 + (self.next_u16()? >> 2);
+            self.copy_match(distance, length + 2)?;
+        } else if t >= 16 {
+            let length = if t & 7 == 0 {
+                self.long_length(7)?
+            } else {
+                t & 7
+            };
+            let distance = ((t & 8) << 11) + (self.next_u16()? >> 2);
+            if distance == 0 {
+                return Ok(false);
+            }
+
+            self.copy_match(distance + 0x4000, length + 2)?;
+        } else {
+            let distance = 1 + (t >> 2) + (self.next()? << 2);
+            self.copy_match(distance, 2)?;
+        }
+
+        Ok(true)
+    }
+
+    // The low 2 bits of the byte before last tell how many literals follow
+    // a match. Without any, the next instruction may start a literal run.
+    fn after_match(&mut self) -> Result<State, Box<dyn Error>> {
+        let count = self.input[self.ip - 2] as usize & 3;
+        if count == 0 {
+            return Ok(State::Start);
+        }
+
+        self.copy_literals(count)?;
+        Ok(State::Match(self.next()?))
+    }
+}
+
+enum State {
+    Start,
+    AfterLiterals(usize),
+    Match(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lzo1x_decompress;
+
+    // Ends every stream below
+    const END: [u8; 3] = [0x11, 0, 0];
+
+    fn stream(parts: &[&[u8]]) -> Vec<u8> {
+        parts.concat()
+    }
+
+    #[test]
+    fn literal_run_after_first_byte() {
+        let input = stream(&[&[17 + 5], b"hello", &END]);
+        assert_eq!(lzo1x_decompress(&input, 5).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn literal_run_instruction() {
+        let input = stream(&[&[1], b"wxyz", &END]);
+        assert_eq!(lzo1x_decompress(&input, 4).unwrap(), b"wxyz");
+
+        // A zero length adds 15 to the next byte, then 3 more
+        let literals: Vec<u8> = (0..21).collect();
+        let input = stream(&[&[0, 3], &literals, &END]);
+        assert_eq!(lzo1x_decompress(&input, 21).unwrap(), literals);
+    }
+
+    #[test]
+    fn overlapping_match() {
+        // Two literals, then 6 bytes from 2 back
+        let input = stream(&[&[17 + 2], b"ab", &[0xa4, 0], &END]);
+        assert_eq!(lzo1x_decompress(&input, 8).unwrap(), b"abababab");
+    }
+
+    #[test]
+    fn end_marker_stops_decoding() {
+        let input = stream(&[&[17 + 2], b"ab", &END, b"trailing"]);
+        assert_eq!(lzo1x_decompress(&input, 2).unwrap(), b"ab");
+    }
+
+    #[test]
+    fn truncated_stream_fails() {
+        let input = stream(&[&[17 + 5], b"hello", &END]);
+        for len in 0..input.len() {
+            assert!(lzo1x_decompress(&input[..len], 5).is_err());
+        }
+    }
+
+    #[test]
+    fn corrupt_stream_fails() {
+        // A match reaching 8 bytes back with only 2 written
+        let input = stream(&[&[17 + 2], b"ab", &[0xbc, 0], &END]);
+        assert!(lzo1x_decompress(&input, 8).is_err());
+
+        // More output than the entry says
+        let input = stream(&[&[17 + 5], b"hello", &END]);
+        assert!(lzo1x_decompress(&input, 4).is_err());
+        assert!(lzo1x_decompress(&input, 6).is_err());
+    }
+}
@@ -0,0 +1,388 @@
+mod lzo;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use encoding::{DecoderTrap, Encoding};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const CPK_LABEL: u32 = 0x1a545352; // "RST\x1a"
+const CPK_HEADER_SIZE: u64 = 0x80;
+
+const CPK_FLAG_VALID: u32 = 0x1;
+const CPK_FLAG_DIR: u32 = 0x2;
+const CPK_FLAG_DELETED: u32 = 0x10;
+const CPK_FLAG_NOT_COMPRESSED: u32 = 0x10000;
+
+#[derive(Debug, Clone)]
+pub struct CpkEntry {
+    pub crc: u32,
+    pub flag: u32,
+    pub father_crc: u32,
+    pub start_pos: u32,
+    pub packed_size: u32,
+    pub origin_size: u32,
+    pub extra_info_size: u32,
+}
+
+impl CpkEntry {
+    pub fn is_valid(&self) -> bool {
+        self.flag & CPK_FLAG_VALID != 0 && self.flag & CPK_FLAG_DELETED == 0
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.flag & CPK_FLAG_DIR != 0
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flag & CPK_FLAG_NOT_COMPRESSED == 0
+    }
+
+    // The data is followed by the name
+    fn end(&self) -> u64 {
+        self.start_pos as u64 + self.packed_size as u64 + self.extra_info_size as u64
+    }
+}
+
+// A CPK archive as shipped with the game and its patches. Only the table
+// is read when opening; file data is read and unpacked on demand. Paths
+// use '/' and are matched ignoring ASCII case, like the rest of the VFS.
+pub struct CpkArchive {
+    path: PathBuf,
+    // Files only, with their full names alongside
+    entries: Vec<CpkEntry>,
+    entry_names: Vec<String>,
+    files: HashMap<String, usize>,
+    names: Vec<String>,
+}
+
+impl CpkArchive {
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let file = fs::File::open(&path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let label = reader.read_u32::<LittleEndian>()?;
+        if label != CPK_LABEL {
+            return Err(format!("{:?}: not a cpk archive", path.as_ref()).into());
+        }
+
+        let _version = reader.read_u32::<LittleEndian>()?;
+        let table_start = reader.read_u32::<LittleEndian>()?;
+        let _data_start = reader.read_u32::<LittleEndian>()?;
+        let max_file_num = reader.read_u32::<LittleEndian>()?;
+
+        reader.seek(SeekFrom::Start((table_start as u64).max(CPK_HEADER_SIZE)))?;
+        let mut entries = vec![];
+        for _ in 0..max_file_num {
+            let entry = CpkEntry {
+                crc: reader.read_u32::<LittleEndian>()?,
+                flag: reader.read_u32::<LittleEndian>()?,
+                father_crc: reader.read_u32::<LittleEndian>()?,
+                start_pos: reader.read_u32::<LittleEndian>()?,
+                packed_size: reader.read_u32::<LittleEndian>()?,
+                origin_size: reader.read_u32::<LittleEndian>()?,
+                extra_info_size: reader.read_u32::<LittleEndian>()?,
+            };
+
+            if entry.is_valid() {
+                entries.push(entry);
+            }
+        }
+
+        // Entry names are stored after each entry's data
+        let mut entry_names = vec![];
+        for entry in &entries {
+            if entry.end() > file_len {
+                return Err(format!("{:?}: entry past the end", path.as_ref()).into());
+            }
+
+            reader.seek(SeekFrom::Start(
+                entry.start_pos as u64 + entry.packed_size as u64,
+            ))?;
+            let mut name = vec![0u8; entry.extra_info_size as usize];
+            reader.read_exact(&mut name)?;
+            let name: Vec<u8> = name.into_iter().take_while(|&c| c != 0).collect();
+            entry_names.push(
+                encoding::all::GBK
+                    .decode(&name, DecoderTrap::Ignore)
+                    .map_err(|e| e.to_string())?,
+            );
+        }
+
+        let by_crc: HashMap<u32, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.crc, i))
+            .collect();
+        let files = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !entry.is_dir())
+            .map(|(i, entry)| (full_name(i, &entries, &entry_names, &by_crc), entry.clone()))
+            .collect();
+
+        Ok(CpkArchive::from_files(path.as_ref(), files))
+    }
+
+    // Reads the table from `index_path` when it was written for this very
+    // archive, or scans the archive and writes the index for the next time.
+    // Opening the big archives otherwise seeks to every entry for its name.
+    pub fn load_with_index<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        index_path: Q,
+    ) -> Result<Self, Box<dyn Error>> {
+        let stamp = archive_stamp(path.as_ref())?;
+        if let Ok(files) = read_index(index_path.as_ref(), &stamp) {
+            return Ok(CpkArchive::from_files(path.as_ref(), files));
+        }
+
+        let archive = CpkArchive::load_from_file(&path)?;
+        if let Err(e) = archive.write_index(&index_path, &stamp) {
+            println!("Unable to write cpk index {:?}: {}", index_path.as_ref(), e);
+        }
+
+        Ok(archive)
+    }
+
+    fn from_files(path: &Path, files: Vec<(String, CpkEntry)>) -> Self {
+        let mut entries = vec![];
+        let mut entry_names = vec![];
+        let mut by_name = HashMap::new();
+        for (name, entry) in files {
+            by_name.insert(normalize(&name), entries.len());
+            entries.push(entry);
+            entry_names.push(name);
+        }
+
+        let mut names = entry_names.clone();
+        names.sort();
+        CpkArchive {
+            path: path.to_path_buf(),
+            entries,
+            entry_names,
+            files: by_name,
+            names,
+        }
+    }
+
+    // One line per file after the stamp of the archive:
+    // `<crc> <flag> <father_crc> <start_pos> <packed_size> <origin_size> <extra_info_size> <name>`
+    fn write_index<P: AsRef<Path>>(&self, path: P, stamp: &str) -> std::io::Result<()> {
+        let mut text = format!("# opengb cpk index\n{}\n", stamp);
+        for (e, name) in self.entries.iter().zip(&self.entry_names) {
+            text.push_str(&format!(
+                "{:08x} {:08x} {:08x} {} {} {} {} {}\n",
+                e.crc,
+                e.flag,
+                e.father_crc,
+                e.start_pos,
+                e.packed_size,
+                e.origin_size,
+                e.extra_info_size,
+                name
+            ));
+        }
+
+        std::fs::write(path, text)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Every file in the archive, sorted
+    pub fn file_names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&CpkEntry> {
+        self.files.get(&normalize(name)).map(|&i| &self.entries[i])
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.files.contains_key(&normalize(name))
+    }
+
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let entry = self
+            .entry(name)
+            .ok_or_else(|| format!("{} not found in {:?}", name, self.path))?;
+
+        let mut file = fs::File::open(&self.path)?;
+        if entry.end() > file.metadata()?.len() {
+            return Err(format!("{}: past the end of {:?}", name, self.path).into());
+        }
+
+        file.seek(SeekFrom::Start(entry.start_pos as u64))?;
+        let mut packed = vec![0u8; entry.packed_size as usize];
+        file.read_exact(&mut packed)?;
+
+        if entry.is_compressed() {
+            lzo::lzo1x_decompress(&packed, entry.origin_size as usize)
+                .map_err(|e| format!("{}: {}", name, e).into())
+        } else {
+            Ok(packed)
+        }
+    }
+}
+
+// The archive's size and modification time, which the index has to match
+fn archive_stamp(path: &Path) -> Result<String, Box<dyn Error>> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(format!("archive = {} {}", metadata.len(), modified))
+}
+
+fn read_index(path: &Path, stamp: &str) -> Result<Vec<(String, CpkEntry)>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let mut lines = text.lines().filter(|l| !l.starts_with('#'));
+    if lines.next() != Some(stamp) {
+        return Err(format!("{:?}: stale cpk index", path).into());
+    }
+
+    let mut files = vec![];
+    for line in lines {
+        let mut fields = line.splitn(8, ' ');
+        let mut hex = || u32::from_str_radix(fields.next().unwrap_or(""), 16);
+        let (crc, flag, father_crc) = (hex()?, hex()?, hex()?);
+        let mut dec = || fields.next().unwrap_or("").parse::<u32>();
+        let (start_pos, packed_size, origin_size, extra_info_size) =
+            (dec()?, dec()?, dec()?, dec()?);
+        let name = fields.next().ok_or("missing name")?.to_owned();
+        files.push((
+            name,
+            CpkEntry {
+                crc,
+                flag,
+                father_crc,
+                start_pos,
+                packed_size,
+                origin_size,
+                extra_info_size,
+            },
+        ));
+    }
+
+    Ok(files)
+}
+
+pub(crate) fn normalize(name: &str) -> String {
+    name.split(|c| c == '/' || c == '\\')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect::<Vec<&str>>()
+        .join("/")
+        .to_lowercase()
+}
+
+// Parents are looked up by crc, the root having 0. The depth is bounded so
+// that a corrupt table can't loop forever.
+fn full_name(
+    index: usize,
+    entries: &[CpkEntry],
+    names: &[String],
+    by_crc: &HashMap<u32, usize>,
+) -> String {
+    let mut components = vec![names[index].as_str()];
+    let mut father_crc = entries[index].father_crc;
+    while father_crc != 0 && components.len() < 64 {
+        match by_crc.get(&father_crc) {
+            Some(&parent) => {
+                components.push(&names[parent]);
+                father_crc = entries[parent].father_crc;
+            }
+            None => break,
+        }
+    }
+
+    components.reverse();
+    components.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::WriteBytesExt;
+
+    const NOT_COMPRESSED: u32 = CPK_FLAG_VALID | CPK_FLAG_NOT_COMPRESSED;
+
+    // One uncompressed file per (name, data), all in the root directory.
+    // `extra_name_size` pads the last name, to point it past the end.
+    fn archive(files: &[(&str, &[u8])], extra_name_size: u32) -> Vec<u8> {
+        let mut table = vec![];
+        let mut data = vec![];
+        let data_start = CPK_HEADER_SIZE as u32 + 28 * files.len() as u32;
+        for (i, (name, contents)) in files.iter().enumerate() {
+            let name = format!("{}\0", name);
+            let padding = if i + 1 == files.len() {
+                extra_name_size
+            } else {
+                0
+            };
+            for field in &[
+                i as u32 + 1,
+                NOT_COMPRESSED,
+                0,
+                data_start + data.len() as u32,
+                contents.len() as u32,
+                contents.len() as u32,
+                name.len() as u32 + padding,
+            ] {
+                table.write_u32::<LittleEndian>(*field).unwrap();
+            }
+
+            data.extend_from_slice(contents);
+            data.extend_from_slice(name.as_bytes());
+        }
+
+        let mut bytes = vec![];
+        for field in &[
+            CPK_LABEL,
+            1,
+            CPK_HEADER_SIZE as u32,
+            data_start,
+            files.len() as u32,
+        ] {
+            bytes.write_u32::<LittleEndian>(*field).unwrap();
+        }
+
+        bytes.resize(CPK_HEADER_SIZE as usize, 0);
+        bytes.extend(table);
+        bytes.extend(data);
+        bytes
+    }
+
+    fn write_temp(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("opengb-{}-{}.cpk", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_uncompressed_files() {
+        let path = write_temp("read", &archive(&[("a.txt", b"one"), ("B.txt", b"two")], 0));
+        let cpk = CpkArchive::load_from_file(&path).unwrap();
+        assert_eq!(cpk.file_names(), &["B.txt".to_owned(), "a.txt".to_owned()]);
+        assert_eq!(cpk.read("A.TXT").unwrap(), b"one");
+        assert_eq!(cpk.read("b.txt").unwrap(), b"two");
+        assert!(cpk.read("c.txt").is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn entry_past_the_end_fails() {
+        let path = write_temp("past-end", &archive(&[("a.txt", b"one")], 0x7fff_ffff));
+        assert!(CpkArchive::load_from_file(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn truncated_archive_fails() {
+        let bytes = archive(&[("a.txt", b"one")], 0);
+        let path = write_temp("truncated", &bytes[..bytes.len() - 2]);
+        assert!(CpkArchive::load_from_file(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,141 @@
+use crate::geometry::Aabb;
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::{VertexBuffer, VertexComponents};
+
+// Line segments for debug overlays. radiance only draws triangles, so each
+// segment becomes two thin quads crossing along it, which keeps it visible
+// from every direction. Colors are written to the NORMAL slot, as
+// `material::create_vertex_color_material` expects.
+pub struct DebugLines {
+    width: f32,
+    segments: Vec<(Vec3, Vec3, Vec3)>,
+}
+
+impl DebugLines {
+    pub fn new(width: f32) -> Self {
+        DebugLines {
+            width,
+            segments: vec![],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn add_line(&mut self, from: Vec3, to: Vec3, color: Vec3) {
+        self.segments.push((from, to, color));
+    }
+
+    pub fn add_aabb(&mut self, aabb: &Aabb, color: Vec3) {
+        if aabb.is_empty() {
+            return;
+        }
+
+        // Corners are ordered by the bits of their index: x, then y, then z
+        let c = aabb.corners();
+        for &(a, b) in &[
+            (0, 1),
+            (2, 3),
+            (4, 5),
+            (6, 7),
+            (0, 2),
+            (1, 3),
+            (4, 6),
+            (5, 7),
+            (0, 4),
+            (1, 5),
+            (2, 6),
+            (3, 7),
+        ] {
+            self.add_line(c[a], c[b], color);
+        }
+    }
+
+    pub fn add_normals(
+        &mut self,
+        positions: &[[f32; 3]],
+        normals: &[[f32; 3]],
+        length: f32,
+        color: Vec3,
+    ) {
+        for (p, n) in positions.iter().zip(normals) {
+            self.add_line(
+                Vec3::new(p[0], p[1], p[2]),
+                Vec3::new(
+                    p[0] + n[0] * length,
+                    p[1] + n[1] * length,
+                    p[2] + n[2] * length,
+                ),
+                color,
+            );
+        }
+    }
+
+    pub fn vertex_components() -> VertexComponents {
+        VertexComponents::POSITION | VertexComponents::NORMAL | VertexComponents::TEXCOORD
+    }
+
+    pub fn build(&self) -> (VertexBuffer, Vec<u32>) {
+        let mut vertices =
+            VertexBuffer::new(DebugLines::vertex_components(), self.segments.len() * 8);
+        let mut indices = Vec::with_capacity(self.segments.len() * 24);
+        let tex_coord = Vec2::new(0., 0.);
+        let half_width = self.width / 2.;
+        for (i, (from, to, color)) in self.segments.iter().enumerate() {
+            let direction = normalize(&sub(to, from));
+            // Vertical segments have no side relative to y, so use x for them
+            let side = if direction.y.abs() > 0.999 {
+                Vec3::new(1., 0., 0.)
+            } else {
+                normalize(&cross(&direction, &Vec3::new(0., 1., 0.)))
+            };
+            let up = cross(&direction, &side);
+
+            for (j, offset) in [side, up].iter().enumerate() {
+                let base = i * 8 + j * 4;
+                let o = scale(offset, half_width);
+                let corners = [sub(from, &o), add(from, &o), add(to, &o), sub(to, &o)];
+                for (k, corner) in corners.iter().enumerate() {
+                    vertices.set_data(base + k, Some(corner), Some(color), Some(&tex_coord), None);
+                }
+
+                // Both windings, so culling never hides a quad
+                let base = base as u32;
+                indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+                indices.extend_from_slice(&[base, base + 2, base + 1, base, base + 3, base + 2]);
+            }
+        }
+
+        (vertices, indices)
+    }
+}
+
+fn add(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3::new(a.x + b.x, a.y + b.y, a.z + b.z)
+}
+
+fn sub(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3::new(a.x - b.x, a.y - b.y, a.z - b.z)
+}
+
+fn scale(a: &Vec3, s: f32) -> Vec3 {
+    Vec3::new(a.x * s, a.y * s, a.z * s)
+}
+
+fn cross(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3::new(
+        a.y * b.z - a.z * b.y,
+        a.z * b.x - a.x * b.z,
+        a.x * b.y - a.y * b.x,
+    )
+}
+
+fn normalize(a: &Vec3) -> Vec3 {
+    let length = (a.x * a.x + a.y * a.y + a.z * a.z).sqrt();
+    if length == 0. {
+        Vec3::new(0., 0., 1.)
+    } else {
+        scale(a, 1. / length)
+    }
+}
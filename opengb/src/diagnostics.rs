@@ -0,0 +1,205 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+thread_local! {
+    static PENDING_LOADS: RefCell<Vec<AssetLoadRecord>> = RefCell::new(vec![]);
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetLoadRecord {
+    pub path: PathBuf,
+    pub duration: Duration,
+}
+
+// Runs `load` and remembers how long it took, so that the next frame hitch
+// reported by `FrameStats` can be attributed to the assets loaded meanwhile.
+pub fn track_asset_load<P: AsRef<Path>, T, F: FnOnce() -> T>(path: P, load: F) -> T {
+    let start = Instant::now();
+    let ret = load();
+    let record = AssetLoadRecord {
+        path: path.as_ref().to_path_buf(),
+        duration: start.elapsed(),
+    };
+
+    PENDING_LOADS.with(|loads| loads.borrow_mut().push(record));
+    ret
+}
+
+fn take_pending_loads() -> Vec<AssetLoadRecord> {
+    PENDING_LOADS.with(|loads| loads.replace(vec![]))
+}
+
+#[derive(Debug)]
+pub struct FrameHitch {
+    pub frame_index: u64,
+    pub delta_sec: f32,
+    pub median_sec: f32,
+    pub loads: Vec<AssetLoadRecord>,
+}
+
+impl std::fmt::Display for FrameHitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Frame {} took {:.2} ms (median {:.2} ms)",
+            self.frame_index,
+            self.delta_sec * 1000.,
+            self.median_sec * 1000.
+        )?;
+
+        for load in &self.loads {
+            write!(
+                f,
+                "\n    loading {:?} took {:.2} ms",
+                load.path,
+                load.duration.as_secs_f32() * 1000.
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct FrameStats {
+    frame_times: VecDeque<f32>,
+    capacity: usize,
+    hitch_factor: f32,
+    frame_count: u64,
+    hitch_count: u64,
+    worst_frame: f32,
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize, hitch_factor: f32) -> Self {
+        FrameStats {
+            frame_times: VecDeque::with_capacity(capacity),
+            capacity,
+            hitch_factor,
+            frame_count: 0,
+            hitch_count: 0,
+            worst_frame: 0.,
+        }
+    }
+
+    // A frame is considered a hitch when it takes `hitch_factor` times longer
+    // than the median of the recent frames. Asset loads recorded since the
+    // previous frame are attached to the hitch.
+    pub fn update(&mut self, delta_sec: f32) -> Option<FrameHitch> {
+        let loads = take_pending_loads();
+        let median_sec = self.percentile(0.5);
+        let is_hitch = (!self.frame_times.is_empty()
+            && delta_sec > median_sec * self.hitch_factor)
+            || !loads.is_empty();
+
+        if self.frame_times.len() == self.capacity {
+            self.frame_times.pop_front();
+        }
+
+        self.frame_times.push_back(delta_sec);
+        self.frame_count += 1;
+        self.worst_frame = self.worst_frame.max(delta_sec);
+
+        if is_hitch {
+            self.hitch_count += 1;
+            Some(FrameHitch {
+                frame_index: self.frame_count,
+                delta_sec,
+                median_sec,
+                loads,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn hitch_count(&self) -> u64 {
+        self.hitch_count
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.;
+        }
+
+        self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32
+    }
+
+    pub fn percentile(&self, p: f32) -> f32 {
+        if self.frame_times.is_empty() {
+            return 0.;
+        }
+
+        let mut sorted: Vec<f32> = self.frame_times.iter().cloned().collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let index = ((sorted.len() - 1) as f32 * p.min(1.).max(0.)).round() as usize;
+        sorted[index]
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "frames: {} hitches: {} avg: {:.2} ms p50: {:.2} ms p99: {:.2} ms worst: {:.2} ms",
+            self.frame_count,
+            self.hitch_count,
+            self.average() * 1000.,
+            self.percentile(0.5) * 1000.,
+            self.percentile(0.99) * 1000.,
+            self.worst_frame * 1000.
+        )
+    }
+}
+
+// A plain text report users can attach to performance issues. It is only
+// written when asked for and holds nothing but the sections added to it.
+pub struct PerformanceReport {
+    sections: Vec<(String, String)>,
+}
+
+impl PerformanceReport {
+    pub fn new() -> Self {
+        let system = format!(
+            "os = {}\narch = {}\nopengb = {}",
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            env!("CARGO_PKG_VERSION")
+        );
+
+        PerformanceReport {
+            sections: vec![("system".to_owned(), system)],
+        }
+    }
+
+    pub fn add_section(&mut self, title: &str, content: &str) {
+        self.sections.push((title.to_owned(), content.to_owned()));
+    }
+
+    pub fn add_frame_stats(&mut self, stats: &FrameStats) {
+        let content = format!(
+            "{}\np90: {:.2} ms\np95: {:.2} ms",
+            stats.summary(),
+            stats.percentile(0.9) * 1000.,
+            stats.percentile(0.95) * 1000.
+        );
+        self.add_section("frame stats", &content);
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl std::fmt::Display for PerformanceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "OpenPAL3 performance report")?;
+        for (title, content) in &self.sections {
+            writeln!(f, "\n[{}]\n{}", title, content)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,529 @@
+use super::exported_texture_name;
+use crate::animation::Keyframe;
+use crate::json::json_string;
+use crate::loaders::cvdloader::{CvdFile, CvdModel};
+use crate::loaders::mv3loader::Mv3File;
+use crate::loaders::polloader::PolFile;
+use crate::model::{
+    cvd_model_to_flat_meshes, mv3_model_to_flat_meshes, mv3_texture_names, pol_to_flat_meshes,
+    FlatMesh, MV3_TICKS_PER_SECOND,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+// A node of the exported scene. Each of its meshes becomes a primitive of
+// one glTF mesh, and its translation keyframes become an animation channel.
+// Position keyframes hold the positions of every mesh at that time; each
+// becomes a morph target, and the animation blends from one to the next.
+#[derive(Debug, Clone)]
+pub struct GltfNode {
+    pub name: String,
+    pub meshes: Vec<FlatMesh>,
+    pub translation_keys: Vec<Keyframe<[f32; 3]>>,
+    pub position_keys: Vec<Keyframe<Vec<Vec<[f32; 3]>>>>,
+    pub children: Vec<GltfNode>,
+}
+
+impl GltfNode {
+    pub fn new(name: &str, meshes: Vec<FlatMesh>) -> Self {
+        GltfNode {
+            name: name.to_owned(),
+            meshes,
+            translation_keys: vec![],
+            position_keys: vec![],
+            children: vec![],
+        }
+    }
+
+    pub fn with_child(mut self, child: GltfNode) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn meshes_recursive(&self) -> Vec<&FlatMesh> {
+        let mut meshes: Vec<&FlatMesh> = self.meshes.iter().collect();
+        for child in &self.children {
+            meshes.extend(child.meshes_recursive());
+        }
+
+        meshes
+    }
+}
+
+pub fn pol_to_gltf_nodes(pol: &PolFile, name: &str) -> Vec<GltfNode> {
+    vec![GltfNode::new(name, pol_to_flat_meshes(pol))]
+}
+
+// Keeps the model tree and the position keyframes of every model
+pub fn cvd_to_gltf_nodes(cvd: &CvdFile, name: &str) -> Vec<GltfNode> {
+    cvd.models
+        .iter()
+        .enumerate()
+        .map(|(i, model)| cvd_model_to_gltf_node(model, &format!("{}_{}", name, i)))
+        .collect()
+}
+
+fn cvd_model_to_gltf_node(model: &CvdModel, name: &str) -> GltfNode {
+    let mut node = GltfNode::new(name, cvd_model_to_flat_meshes(model));
+    node.translation_keys = model
+        .position_keyframes
+        .iter()
+        .map(|k| Keyframe {
+            timestamp: k.timestamp,
+            value: [k.position.x, k.position.y, k.position.z],
+        })
+        .collect();
+
+    if let Some(children) = &model.children {
+        for (i, child) in children.iter().enumerate() {
+            node = node.with_child(cvd_model_to_gltf_node(child, &format!("{}_{}", name, i)));
+        }
+    }
+
+    node
+}
+
+// A node per model, with every frame as a morph target
+pub fn mv3_to_gltf_nodes(mv3: &Mv3File, name: &str) -> Vec<GltfNode> {
+    let texture_names = mv3_texture_names(mv3);
+    mv3.models
+        .iter()
+        .enumerate()
+        .map(|(i, model)| {
+            let meshes = mv3_model_to_flat_meshes(model, &texture_names, 0);
+            let mut node = GltfNode::new(&format!("{}_{}", name, i), meshes);
+            node.position_keys = model
+                .frames
+                .iter()
+                .enumerate()
+                .map(|(j, frame)| Keyframe {
+                    timestamp: frame.timestamp as f32 / MV3_TICKS_PER_SECOND,
+                    value: mv3_model_to_flat_meshes(model, &texture_names, j)
+                        .into_iter()
+                        .map(|m| m.positions)
+                        .collect(),
+                })
+                .collect();
+            node
+        })
+        .collect()
+}
+
+// Writes `<path>` and its buffer next to it as `<stem>.bin`. Textures are
+// referenced by `exported_texture_name` and are expected to be exported
+// into the same directory.
+pub fn gltf_write_to_file<P: AsRef<Path>>(
+    nodes: &[GltfNode],
+    path: P,
+) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    let bin_path = path.with_extension("bin");
+    let bin_name = bin_path
+        .file_name()
+        .ok_or("gltf: invalid output path")?
+        .to_string_lossy()
+        .into_owned();
+
+    let mut builder = GltfBuilder::new();
+    let roots: Vec<String> = nodes
+        .iter()
+        .map(|n| builder.add_node(n).to_string())
+        .collect();
+
+    std::fs::write(&bin_path, &builder.buffer)?;
+    std::fs::write(path, builder.to_json(&roots, &bin_name))?;
+    Ok(())
+}
+
+struct GltfBuilder {
+    buffer: Vec<u8>,
+    buffer_views: Vec<String>,
+    accessors: Vec<String>,
+    nodes: Vec<String>,
+    meshes: Vec<String>,
+    materials: Vec<String>,
+    images: Vec<String>,
+    material_indices: HashMap<String, usize>,
+    channels: Vec<String>,
+    samplers: Vec<String>,
+}
+
+impl GltfBuilder {
+    fn new() -> Self {
+        GltfBuilder {
+            buffer: vec![],
+            buffer_views: vec![],
+            accessors: vec![],
+            nodes: vec![],
+            meshes: vec![],
+            materials: vec![],
+            images: vec![],
+            material_indices: HashMap::new(),
+            channels: vec![],
+            samplers: vec![],
+        }
+    }
+
+    // Children are added before their parent, which is fine as nodes
+    // refer to each other by index
+    fn add_node(&mut self, node: &GltfNode) -> usize {
+        let children: Vec<String> = node
+            .children
+            .iter()
+            .map(|c| self.add_node(c).to_string())
+            .collect();
+
+        // A single pose isn't worth a morph target
+        let morph_keys: &[Keyframe<Vec<Vec<[f32; 3]>>>] = if node.position_keys.len() > 1 {
+            &node.position_keys
+        } else {
+            &[]
+        };
+        let primitives: Vec<String> = node
+            .meshes
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| !m.indices.is_empty())
+            .map(|(i, m)| {
+                let targets: Vec<&[[f32; 3]]> = morph_keys
+                    .iter()
+                    .map(|k| k.value.get(i).map(|p| p.as_slice()).unwrap_or(&[]))
+                    .collect();
+                self.add_primitive(m, &targets)
+            })
+            .collect();
+
+        let mut fields = vec![format!("\"name\":{}", json_string(&node.name))];
+        if !primitives.is_empty() {
+            // glTF wants the default weights on the mesh
+            let weights = if morph_keys.is_empty() {
+                String::new()
+            } else {
+                format!(",\"weights\":[{}]", vec!["0"; morph_keys.len()].join(","))
+            };
+            self.meshes.push(format!(
+                "{{\"name\":{},\"primitives\":[{}]{}}}",
+                json_string(&node.name),
+                primitives.join(","),
+                weights
+            ));
+            fields.push(format!("\"mesh\":{}", self.meshes.len() - 1));
+        }
+
+        if let Some(first) = node.translation_keys.first() {
+            fields.push(format!("\"translation\":{}", vec3(&first.value)));
+        }
+
+        if !children.is_empty() {
+            fields.push(format!("\"children\":[{}]", children.join(",")));
+        }
+
+        self.nodes.push(format!("{{{}}}", fields.join(",")));
+        let index = self.nodes.len() - 1;
+        if node.translation_keys.len() > 1 {
+            self.add_translation_channel(index, &node.translation_keys);
+        }
+
+        if !primitives.is_empty() && !morph_keys.is_empty() {
+            self.add_weights_channel(index, morph_keys);
+        }
+
+        index
+    }
+
+    // `targets` are the positions of the morph targets, which are written
+    // as offsets from the mesh's own positions
+    fn add_primitive(&mut self, mesh: &FlatMesh, targets: &[&[[f32; 3]]]) -> String {
+        let mut attributes = vec![];
+        let (min, max) = bounds(&mesh.positions);
+        let position = self.add_accessor(
+            &floats(mesh.positions.iter().flatten()),
+            mesh.positions.len(),
+            FLOAT,
+            "VEC3",
+            Some(ARRAY_BUFFER),
+            Some((vec3(&min), vec3(&max))),
+        );
+        attributes.push(format!("\"POSITION\":{}", position));
+
+        if let Some(normals) = &mesh.normals {
+            let normal = self.add_accessor(
+                &floats(normals.iter().flatten()),
+                normals.len(),
+                FLOAT,
+                "VEC3",
+                Some(ARRAY_BUFFER),
+                None,
+            );
+            attributes.push(format!("\"NORMAL\":{}", normal));
+        }
+
+        let tex_coords = mesh.diffuse_tex_coords();
+        let tex_coord = self.add_accessor(
+            &floats(tex_coords.iter().flatten()),
+            tex_coords.len(),
+            FLOAT,
+            "VEC2",
+            Some(ARRAY_BUFFER),
+            None,
+        );
+        attributes.push(format!("\"TEXCOORD_0\":{}", tex_coord));
+
+        if let Some(lightmap_tex_coords) = mesh.lightmap_tex_coords() {
+            let lightmap_tex_coord = self.add_accessor(
+                &floats(lightmap_tex_coords.iter().flatten()),
+                lightmap_tex_coords.len(),
+                FLOAT,
+                "VEC2",
+                Some(ARRAY_BUFFER),
+                None,
+            );
+            attributes.push(format!("\"TEXCOORD_1\":{}", lightmap_tex_coord));
+        }
+
+        let indices: Vec<u8> = mesh
+            .indices
+            .iter()
+            .flat_map(|i| i.to_le_bytes().to_vec())
+            .collect();
+        let indices = self.add_accessor(
+            &indices,
+            mesh.indices.len(),
+            UNSIGNED_INT,
+            "SCALAR",
+            Some(ELEMENT_ARRAY_BUFFER),
+            None,
+        );
+
+        let mut targets_json = vec![];
+        for target in targets {
+            // Meshes missing from a frame stay where they are
+            let offsets: Vec<[f32; 3]> = mesh
+                .positions
+                .iter()
+                .enumerate()
+                .map(|(i, p)| match target.get(i) {
+                    Some(t) => [t[0] - p[0], t[1] - p[1], t[2] - p[2]],
+                    None => [0.; 3],
+                })
+                .collect();
+            let (min, max) = bounds(&offsets);
+            let position = self.add_accessor(
+                &floats(offsets.iter().flatten()),
+                offsets.len(),
+                FLOAT,
+                "VEC3",
+                Some(ARRAY_BUFFER),
+                Some((vec3(&min), vec3(&max))),
+            );
+            targets_json.push(format!("{{\"POSITION\":{}}}", position));
+        }
+
+        let targets = if targets_json.is_empty() {
+            String::new()
+        } else {
+            format!(",\"targets\":[{}]", targets_json.join(","))
+        };
+        let material = self.material_for(mesh.diffuse_texture().unwrap_or(""));
+        format!(
+            "{{\"attributes\":{{{}}},\"indices\":{},\"material\":{}{}}}",
+            attributes.join(","),
+            indices,
+            material,
+            targets
+        )
+    }
+
+    // One material per diffuse texture
+    fn material_for(&mut self, texture_name: &str) -> usize {
+        if let Some(&index) = self.material_indices.get(texture_name) {
+            return index;
+        }
+
+        let material = if texture_name.is_empty() {
+            "{\"name\":\"untextured\",\"doubleSided\":true,\"pbrMetallicRoughness\":{\"metallicFactor\":0}}".to_owned()
+        } else {
+            self.images.push(format!(
+                "{{\"uri\":{}}}",
+                json_string(&exported_texture_name(texture_name))
+            ));
+            format!(
+                "{{\"name\":{},\"doubleSided\":true,\"pbrMetallicRoughness\":{{\"baseColorTexture\":{{\"index\":{}}},\"metallicFactor\":0}}}}",
+                json_string(texture_name),
+                self.images.len() - 1
+            )
+        };
+
+        self.materials.push(material);
+        let index = self.materials.len() - 1;
+        self.material_indices.insert(texture_name.to_owned(), index);
+        index
+    }
+
+    fn add_translation_channel(&mut self, node: usize, keys: &[Keyframe<[f32; 3]>]) {
+        let times: Vec<f32> = keys.iter().map(|k| k.timestamp).collect();
+        let values = floats(keys.iter().flat_map(|k| k.value.iter()));
+        self.add_channel(node, "translation", &times, &values, keys.len(), "VEC3");
+    }
+
+    // Keyframe i gives morph target i the full weight, so that the mesh
+    // blends between consecutive frames
+    fn add_weights_channel(&mut self, node: usize, keys: &[Keyframe<Vec<Vec<[f32; 3]>>>]) {
+        let times: Vec<f32> = keys.iter().map(|k| k.timestamp).collect();
+        let mut weights = vec![0f32; keys.len() * keys.len()];
+        for i in 0..keys.len() {
+            weights[i * keys.len() + i] = 1.;
+        }
+
+        let values = floats(weights.iter());
+        self.add_channel(node, "weights", &times, &values, weights.len(), "SCALAR");
+    }
+
+    fn add_channel(
+        &mut self,
+        node: usize,
+        path: &str,
+        times: &[f32],
+        values: &[u8],
+        count: usize,
+        kind: &str,
+    ) {
+        let min = times.iter().cloned().fold(std::f32::MAX, f32::min);
+        let max = times.iter().cloned().fold(std::f32::MIN, f32::max);
+        let input = self.add_accessor(
+            &floats(times.iter()),
+            times.len(),
+            FLOAT,
+            "SCALAR",
+            None,
+            Some((format!("[{}]", min), format!("[{}]", max))),
+        );
+        let output = self.add_accessor(values, count, FLOAT, kind, None, None);
+
+        self.samplers.push(format!(
+            "{{\"input\":{},\"output\":{},\"interpolation\":\"LINEAR\"}}",
+            input, output
+        ));
+        self.channels.push(format!(
+            "{{\"sampler\":{},\"target\":{{\"node\":{},\"path\":\"{}\"}}}}",
+            self.samplers.len() - 1,
+            node,
+            path
+        ));
+    }
+
+    fn add_accessor(
+        &mut self,
+        data: &[u8],
+        count: usize,
+        component_type: u32,
+        kind: &str,
+        target: Option<u32>,
+        min_max: Option<(String, String)>,
+    ) -> usize {
+        // Every component type used here is 4 bytes wide
+        while self.buffer.len() % 4 != 0 {
+            self.buffer.push(0);
+        }
+
+        let target = match target {
+            Some(target) => format!(",\"target\":{}", target),
+            None => String::new(),
+        };
+        self.buffer_views.push(format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{}{}}}",
+            self.buffer.len(),
+            data.len(),
+            target
+        ));
+        self.buffer.extend_from_slice(data);
+
+        let min_max = match min_max {
+            Some((min, max)) => format!(",\"min\":{},\"max\":{}", min, max),
+            None => String::new(),
+        };
+        self.accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"{}\"{}}}",
+            self.buffer_views.len() - 1,
+            component_type,
+            count,
+            kind,
+            min_max
+        ));
+        self.accessors.len() - 1
+    }
+
+    fn to_json(&self, roots: &[String], bin_name: &str) -> String {
+        let mut fields = vec![
+            "\"asset\":{\"version\":\"2.0\",\"generator\":\"opengb\"}".to_owned(),
+            "\"scene\":0".to_owned(),
+            format!("\"scenes\":[{{\"nodes\":[{}]}}]", roots.join(",")),
+            format!(
+                "\"buffers\":[{{\"uri\":{},\"byteLength\":{}}}]",
+                json_string(bin_name),
+                self.buffer.len()
+            ),
+        ];
+
+        let textures: Vec<String> = (0..self.images.len())
+            .map(|i| format!("{{\"sampler\":0,\"source\":{}}}", i))
+            .collect();
+        let arrays = [
+            ("nodes", &self.nodes),
+            ("meshes", &self.meshes),
+            ("materials", &self.materials),
+            ("images", &self.images),
+            ("textures", &textures),
+            ("bufferViews", &self.buffer_views),
+            ("accessors", &self.accessors),
+        ];
+
+        // glTF doesn't allow empty arrays
+        for (name, items) in arrays.iter() {
+            if !items.is_empty() {
+                fields.push(format!("\"{}\":[\n{}\n]", name, items.join(",\n")));
+            }
+        }
+
+        if !self.images.is_empty() {
+            fields.push("\"samplers\":[{\"wrapS\":10497,\"wrapT\":10497}]".to_owned());
+        }
+
+        if !self.channels.is_empty() {
+            fields.push(format!(
+                "\"animations\":[{{\"name\":\"default\",\"channels\":[{}],\"samplers\":[{}]}}]",
+                self.channels.join(","),
+                self.samplers.join(",")
+            ));
+        }
+
+        format!("{{\n{}\n}}\n", fields.join(",\n"))
+    }
+}
+
+fn floats<'a, I: Iterator<Item = &'a f32>>(values: I) -> Vec<u8> {
+    values.flat_map(|v| v.to_le_bytes().to_vec()).collect()
+}
+
+fn vec3(v: &[f32; 3]) -> String {
+    format!("[{},{},{}]", v[0], v[1], v[2])
+}
+
+fn bounds(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [std::f32::MAX; 3];
+    let mut max = [std::f32::MIN; 3];
+    for p in positions {
+        for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+
+    (min, max)
+}
@@ -0,0 +1,41 @@
+pub mod gltf;
+pub mod obj;
+
+use crate::model::FlatMesh;
+use std::error::Error;
+use std::path::Path;
+
+// Exported textures are written as png, which every tool reads, next to
+// the exported model. Directories in the game's texture names are dropped.
+pub fn exported_texture_name(texture_name: &str) -> String {
+    let file_name = texture_name
+        .rsplit(|c| c == '/' || c == '\\')
+        .next()
+        .unwrap_or(texture_name);
+    let stem = match file_name.rfind('.') {
+        Some(dot) => &file_name[..dot],
+        None => file_name,
+    };
+
+    format!("{}.png", stem)
+}
+
+// The distinct diffuse textures of `meshes`, in the order they are used
+pub fn diffuse_textures<'a, I: IntoIterator<Item = &'a FlatMesh>>(meshes: I) -> Vec<String> {
+    let mut textures: Vec<String> = vec![];
+    for mesh in meshes {
+        if let Some(name) = mesh.diffuse_texture() {
+            if !name.is_empty() && !textures.iter().any(|t| t == name) {
+                textures.push(name.to_owned());
+            }
+        }
+    }
+
+    textures
+}
+
+// Decodes a game texture (DDS, TGA or BMP) and writes it as png
+pub fn export_texture<P: AsRef<Path>>(data: &[u8], path: P) -> Result<(), Box<dyn Error>> {
+    image::load_from_memory(data)?.save(path)?;
+    Ok(())
+}
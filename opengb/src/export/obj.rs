@@ -0,0 +1,85 @@
+use super::exported_texture_name;
+use crate::model::FlatMesh;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+
+// Writes `<path>` and its materials as `<stem>.mtl`. Every mesh becomes a
+// group using the material of its diffuse texture; the texture is
+// referenced by `exported_texture_name` like for glTF. OBJ puts the
+// texture origin at the bottom left, so v is flipped.
+pub fn obj_write_to_file<P: AsRef<Path>>(
+    meshes: &[FlatMesh],
+    path: P,
+) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    let mtl_path = path.with_extension("mtl");
+    let mtl_name = mtl_path
+        .file_name()
+        .ok_or("obj: invalid output path")?
+        .to_string_lossy()
+        .into_owned();
+
+    let mut obj = format!("mtllib {}\n", mtl_name);
+    let mut mtl = String::new();
+    let mut materials: HashMap<&str, String> = HashMap::new();
+    let mut vertex_offset = 1;
+    for (i, mesh) in meshes.iter().enumerate() {
+        if mesh.indices.is_empty() {
+            continue;
+        }
+
+        let texture = mesh.diffuse_texture().unwrap_or("");
+        let material_count = materials.len();
+        let material = materials.entry(texture).or_insert_with(|| {
+            let name = format!("material_{}", material_count);
+            writeln!(
+                mtl,
+                "newmtl {}\nKd 1 1 1\nKa 0 0 0\nKs 0 0 0\nillum 1",
+                name
+            )
+            .unwrap();
+            if !texture.is_empty() {
+                writeln!(mtl, "map_Kd {}", exported_texture_name(texture)).unwrap();
+            }
+            mtl.push('\n');
+            name
+        });
+
+        writeln!(obj, "g mesh_{}\nusemtl {}", i, material)?;
+        for p in &mesh.positions {
+            writeln!(obj, "v {} {} {}", p[0], p[1], p[2])?;
+        }
+
+        for t in mesh.diffuse_tex_coords() {
+            writeln!(obj, "vt {} {}", t[0], 1. - t[1])?;
+        }
+
+        if let Some(normals) = &mesh.normals {
+            for n in normals {
+                writeln!(obj, "vn {} {} {}", n[0], n[1], n[2])?;
+            }
+        }
+
+        // Positions, texture coordinates and normals share the indices
+        for triangle in mesh.indices.chunks(3).filter(|t| t.len() == 3) {
+            obj.push('f');
+            for &index in triangle {
+                let index = index as usize + vertex_offset;
+                if mesh.normals.is_some() {
+                    write!(obj, " {0}/{0}/{0}", index)?;
+                } else {
+                    write!(obj, " {0}/{0}", index)?;
+                }
+            }
+            obj.push('\n');
+        }
+
+        vertex_offset += mesh.positions.len();
+    }
+
+    std::fs::write(&mtl_path, mtl)?;
+    std::fs::write(path, obj)?;
+    Ok(())
+}
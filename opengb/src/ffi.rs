@@ -0,0 +1,164 @@
+use crate::cpk::CpkArchive;
+use crate::model::FlatMesh;
+use std::error::Error;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::path::Path;
+use std::ptr;
+
+// A `FlatMesh` as the bindings present it: the diffuse texture with its
+// coordinates, and the lightmap's coordinates second. The C and Python
+// bindings both go through this, so that they agree on the texture.
+#[derive(Debug, Clone)]
+pub struct BindingMesh {
+    pub texture_name: Option<String>,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Option<Vec<[f32; 3]>>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub tex_coords2: Option<Vec<[f32; 2]>>,
+    pub indices: Vec<u32>,
+}
+
+impl From<FlatMesh> for BindingMesh {
+    fn from(mesh: FlatMesh) -> Self {
+        let texture_name = mesh.diffuse_texture().map(|n| n.to_owned());
+        let tex_coords = mesh.diffuse_tex_coords().to_vec();
+        let tex_coords2 = mesh.lightmap_tex_coords().map(|t| t.to_vec());
+        BindingMesh {
+            texture_name,
+            positions: mesh.positions,
+            normals: mesh.normals,
+            tex_coords,
+            tex_coords2,
+            indices: mesh.indices,
+        }
+    }
+}
+
+// The C view of a `BindingMesh`, declared in opengb_bindings'
+// include/opengb.h. Arrays are tightly packed; normals and tex_coords2 are
+// null when absent.
+#[repr(C)]
+pub struct OpengbMesh {
+    pub texture_name: *mut c_char,
+    pub vertex_count: u32,
+    pub positions: *mut f32,
+    pub normals: *mut f32,
+    pub tex_coords: *mut f32,
+    pub tex_coords2: *mut f32,
+    pub index_count: u32,
+    pub indices: *mut u32,
+}
+
+#[repr(C)]
+pub struct OpengbModel {
+    pub mesh_count: u32,
+    pub meshes: *mut OpengbMesh,
+}
+
+// Ownership passes to the caller, who gives it back to `free_c_model`
+pub fn into_c_model(meshes: Vec<FlatMesh>) -> *mut OpengbModel {
+    let meshes: Vec<OpengbMesh> = meshes
+        .into_iter()
+        .map(|m| to_c_mesh(BindingMesh::from(m)))
+        .collect();
+    Box::into_raw(Box::new(OpengbModel {
+        mesh_count: meshes.len() as u32,
+        meshes: into_array(meshes),
+    }))
+}
+
+pub unsafe fn free_c_model(model: *mut OpengbModel) {
+    if model.is_null() {
+        return;
+    }
+
+    let model = Box::from_raw(model);
+    let meshes = free_array(model.meshes, model.mesh_count as usize);
+    for mesh in meshes.iter() {
+        let vertex_count = mesh.vertex_count as usize;
+        drop(CString::from_raw(mesh.texture_name));
+        free_array(mesh.positions, vertex_count * 3);
+        free_array(mesh.normals, vertex_count * 3);
+        free_array(mesh.tex_coords, vertex_count * 2);
+        free_array(mesh.tex_coords2, vertex_count * 2);
+        free_array(mesh.indices, mesh.index_count as usize);
+    }
+}
+
+fn to_c_mesh(mesh: BindingMesh) -> OpengbMesh {
+    let texture_name = mesh.texture_name.unwrap_or_default().replace('\0', "");
+    OpengbMesh {
+        texture_name: CString::new(texture_name).unwrap().into_raw(),
+        vertex_count: mesh.positions.len() as u32,
+        positions: into_array(mesh.positions.iter().flat_map(|p| p.to_vec()).collect()),
+        normals: mesh
+            .normals
+            .map(|n| into_array(n.iter().flat_map(|n| n.to_vec()).collect()))
+            .unwrap_or(ptr::null_mut()),
+        tex_coords: into_array(mesh.tex_coords.iter().flat_map(|t| t.to_vec()).collect()),
+        tex_coords2: mesh
+            .tex_coords2
+            .map(|t| into_array(t.iter().flat_map(|t| t.to_vec()).collect()))
+            .unwrap_or(ptr::null_mut()),
+        index_count: mesh.indices.len() as u32,
+        indices: into_array(mesh.indices),
+    }
+}
+
+// An open CPK archive behind an opaque pointer, with its file names kept as
+// C strings for as long as it is open
+pub struct OpengbCpk {
+    archive: CpkArchive,
+    names: Vec<CString>,
+}
+
+impl OpengbCpk {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let archive = CpkArchive::load_from_file(path)?;
+        let names = archive
+            .file_names()
+            .iter()
+            .map(|n| CString::new(n.replace('\0', "")).unwrap())
+            .collect();
+        Ok(OpengbCpk { archive, names })
+    }
+
+    pub fn file_count(&self) -> u32 {
+        self.names.len() as u32
+    }
+
+    pub fn file_name(&self, index: u32) -> *const c_char {
+        self.names
+            .get(index as usize)
+            .map(|n| n.as_ptr())
+            .unwrap_or(ptr::null())
+    }
+
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.archive.read(name)
+    }
+}
+
+// Ownership passes to the caller, who gives it back to `free_buffer` with
+// the same length
+pub fn into_buffer(data: Vec<u8>, len: &mut usize) -> *mut u8 {
+    *len = data.len();
+    into_array(data)
+}
+
+pub unsafe fn free_buffer(data: *mut u8, len: usize) {
+    free_array(data, len);
+}
+
+fn into_array<T>(v: Vec<T>) -> *mut T {
+    Box::into_raw(v.into_boxed_slice()) as *mut T
+}
+
+unsafe fn free_array<T>(p: *mut T, len: usize) -> Box<[T]> {
+    if p.is_null() {
+        return Box::new([]);
+    }
+
+    Box::from_raw(std::slice::from_raw_parts_mut(p, len))
+}
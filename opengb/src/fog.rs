@@ -0,0 +1,89 @@
+use crate::config::read_key_values;
+use crate::shader_registry::set_spec_constants;
+use std::error::Error;
+use std::path::Path;
+
+const FOG_START_ID: u32 = 0;
+const FOG_END_ID: u32 = 1;
+const FOG_COLOR_ID: u32 = 2;
+
+// Per-scene linear fog. PAL3 scenes rely on it to hide the draw distance.
+// Distances are measured from the camera in world units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FogParams {
+    pub color: [f32; 3],
+    pub start: f32,
+    pub end: f32,
+}
+
+impl FogParams {
+    pub fn none() -> Self {
+        FogParams {
+            color: [0., 0., 0.],
+            start: 0.,
+            end: 0.,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.end > self.start
+    }
+
+    // Appended to the names of shaders the fog is applied to. Renderers
+    // cache pipelines by shader name, so shaders with different fog need
+    // different names.
+    pub fn shader_name_suffix(&self) -> String {
+        if !self.is_enabled() {
+            return String::new();
+        }
+
+        format!(
+            "_fog_{:08x}_{:08x}_{:08x}_{:08x}_{:08x}",
+            self.start.to_bits(),
+            self.end.to_bits(),
+            self.color[0].to_bits(),
+            self.color[1].to_bits(),
+            self.color[2].to_bits()
+        )
+    }
+
+    // Reads `start = <f32>`, `end = <f32>` and `color = <r> <g> <b>` lines,
+    // with colors in 0..1. Blank lines and lines starting with '#' are
+    // ignored.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut fog = FogParams::none();
+        read_key_values(path, |key, value| match key {
+            "start" => value.parse().map(|v| fog.start = v).is_ok(),
+            "end" => value.parse().map(|v| fog.end = v).is_ok(),
+            "color" => {
+                let rgb: Vec<f32> = value
+                    .split_whitespace()
+                    .filter_map(|c| c.parse().ok())
+                    .collect();
+                if rgb.len() == 3 {
+                    fog.color = [rgb[0], rgb[1], rgb[2]];
+                }
+
+                rgb.len() == 3
+            }
+            _ => false,
+        })?;
+
+        Ok(fog)
+    }
+
+    // Sets the fog constants of a fragment shader declaring them like
+    // lightmap_texture.frag does. Other shaders are returned unchanged.
+    pub fn apply_to_shader(&self, frag_src: &[u8]) -> Vec<u8> {
+        set_spec_constants(
+            frag_src,
+            &[
+                (FOG_START_ID, self.start.to_bits()),
+                (FOG_END_ID, self.end.to_bits()),
+                (FOG_COLOR_ID, self.color[0].to_bits()),
+                (FOG_COLOR_ID + 1, self.color[1].to_bits()),
+                (FOG_COLOR_ID + 2, self.color[2].to_bits()),
+            ],
+        )
+    }
+}
@@ -0,0 +1,103 @@
+use crate::vfs::Vfs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Game {
+    Pal3,
+    // The WaiZhuan expansion
+    Pal3A,
+}
+
+impl Game {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Game::Pal3 => "PAL3",
+            Game::Pal3A => "PAL3A",
+        }
+    }
+
+    fn executable(&self) -> &'static str {
+        match self {
+            Game::Pal3 => "pal3.exe",
+            Game::Pal3A => "pal3a.exe",
+        }
+    }
+}
+
+// Which game an install directory holds, so that one codebase can load
+// either game's data.
+#[derive(Debug, Clone)]
+pub struct GameProfile {
+    pub game: Game,
+    pub root: PathBuf,
+}
+
+impl GameProfile {
+    pub fn new<P: AsRef<Path>>(game: Game, root: P) -> Self {
+        GameProfile {
+            game,
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    // Looks for the game's executable in `root`. The expansion is checked
+    // first as its installs may also carry the original executable.
+    pub fn detect<P: AsRef<Path>>(root: P) -> Option<Self> {
+        let files: Vec<String> = std::fs::read_dir(&root)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_lowercase())
+            .collect();
+
+        [Game::Pal3A, Game::Pal3]
+            .iter()
+            .find(|game| files.iter().any(|f| f == game.executable()))
+            .map(|&game| GameProfile::new(game, root))
+    }
+
+    // Loose files in the install shadow its archives, as patches ship
+    // them that way. Each archive is mounted where it lies, less its
+    // extension, so scene/Q01.cpk gives `scene/Q01/...` and
+    // basedata/basedata.cpk gives `basedata/basedata/...`, as
+    // `pal3tool cpk-extract --all` lays them out.
+    pub fn mount(&self, vfs: &mut Vfs) {
+        vfs.mount(&self.root);
+        for path in self.archives() {
+            let mount_point = path
+                .strip_prefix(&self.root)
+                .unwrap_or(&path)
+                .with_extension("");
+            if let Err(e) = vfs.mount_archive(&path, &mount_point.to_string_lossy(), -1) {
+                println!("Unable to mount {:?}: {}", path, e);
+            }
+        }
+    }
+
+    // The CPKs in the root and the directories right below it, where
+    // basedata, music, scene and the expansion's own data keep theirs
+    pub fn archives(&self) -> Vec<PathBuf> {
+        let mut dirs = vec![self.root.clone()];
+        if let Ok(entries) = std::fs::read_dir(&self.root) {
+            dirs.extend(
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.is_dir()),
+            );
+        }
+
+        let mut archives: Vec<PathBuf> = dirs
+            .iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()))
+            .filter(|p| {
+                p.is_file()
+                    && p.extension()
+                        .map(|ext| ext.to_string_lossy().to_lowercase() == "cpk")
+                        .unwrap_or(false)
+            })
+            .collect();
+        archives.sort();
+        archives
+    }
+}
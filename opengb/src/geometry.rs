@@ -0,0 +1,339 @@
+use radiance::math::{Vec2, Vec3};
+
+// Compacts the vertices referenced by `triangles` into a dense range.
+//
+// Returns the new index list together with the original vertex index of
+// every compacted vertex. A flat lookup table sized by the largest index is
+// used instead of a hash map, since u16 indices keep it small.
+pub fn remap_indices<'a, I>(triangles: I) -> (Vec<u32>, Vec<usize>)
+where
+    I: IntoIterator<Item = &'a [u16; 3]>,
+    I::IntoIter: Clone,
+{
+    let triangles = triangles.into_iter();
+    let max_index = triangles
+        .clone()
+        .flat_map(|t| t.iter())
+        .max()
+        .map(|&i| i as usize + 1)
+        .unwrap_or(0);
+
+    let mut index_map = vec![std::u32::MAX; max_index];
+    let mut reversed_index = vec![];
+    let mut indices = vec![];
+    for t in triangles {
+        for &index in t {
+            let new_index = &mut index_map[index as usize];
+            if *new_index == std::u32::MAX {
+                *new_index = reversed_index.len() as u32;
+                reversed_index.push(index as usize);
+            }
+
+            indices.push(*new_index);
+        }
+    }
+
+    (indices, reversed_index)
+}
+
+// A UV sphere around the origin with equirectangular texture coordinates,
+// v going from the zenith (0) to the nadir (1). Triangles are wound to face
+// inward so that the sphere can be seen from inside.
+pub fn sky_dome(radius: f32, rings: u32, segments: u32) -> (Vec<Vec3>, Vec<Vec2>, Vec<u32>) {
+    let rings = rings.max(2);
+    let segments = segments.max(3);
+    let mut positions = vec![];
+    let mut tex_coords = vec![];
+    for r in 0..=rings {
+        let v = r as f32 / rings as f32;
+        let theta = v * std::f32::consts::PI;
+        for s in 0..=segments {
+            let u = s as f32 / segments as f32;
+            let phi = u * 2. * std::f32::consts::PI;
+            positions.push(Vec3::new(
+                radius * theta.sin() * phi.cos(),
+                radius * theta.cos(),
+                radius * theta.sin() * phi.sin(),
+            ));
+            tex_coords.push(Vec2::new(u, v));
+        }
+    }
+
+    let mut indices = vec![];
+    let stride = segments + 1;
+    for r in 0..rings {
+        for s in 0..segments {
+            let a = r * stride + s;
+            let b = a + 1;
+            let c = a + stride;
+            let d = c + 1;
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+
+    (positions, tex_coords, indices)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn from_arrays(min: &[f32; 3], max: &[f32; 3]) -> Self {
+        Aabb {
+            min: Vec3::new(min[0], min[1], min[2]),
+            max: Vec3::new(max[0], max[1], max[2]),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vec3::new(std::f32::MAX, std::f32::MAX, std::f32::MAX),
+            max: Vec3::new(std::f32::MIN, std::f32::MIN, std::f32::MIN),
+        }
+    }
+
+    pub fn from_points<'a, I: IntoIterator<Item = &'a Vec3>>(points: I) -> Self {
+        let mut aabb = Aabb::empty();
+        for p in points {
+            aabb.expand(p);
+        }
+
+        aabb
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn expand(&mut self, p: &Vec3) {
+        self.min = Vec3::new(self.min.x.min(p.x), self.min.y.min(p.y), self.min.z.min(p.z));
+        self.max = Vec3::new(self.max.x.max(p.x), self.max.y.max(p.y), self.max.z.max(p.z));
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut aabb = *self;
+        if !other.is_empty() {
+            aabb.expand(&other.min);
+            aabb.expand(&other.max);
+        }
+
+        aabb
+    }
+
+    pub fn center(&self) -> Vec3 {
+        Vec3::new(
+            (self.min.x + self.max.x) * 0.5,
+            (self.min.y + self.max.y) * 0.5,
+            (self.min.z + self.max.z) * 0.5,
+        )
+    }
+
+    pub fn size(&self) -> Vec3 {
+        Vec3::new(
+            self.max.x - self.min.x,
+            self.max.y - self.min.y,
+            self.max.z - self.min.z,
+        )
+    }
+
+    pub fn radius(&self) -> f32 {
+        let size = self.size();
+        (size.x * size.x + size.y * size.y + size.z * size.z).sqrt() * 0.5
+    }
+
+    // Moves the box in front of a camera at the origin looking down -z,
+    // close enough for its bounding sphere to fill the vertical field of
+    // view `fov_y`, in radians.
+    pub fn framing_translation(&self, fov_y: f32) -> Vec3 {
+        let center = self.center();
+        let distance = self.radius().max(1.) / (fov_y * 0.5).sin();
+        Vec3::new(-center.x, -center.y, -center.z - distance)
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (a, b) = (&self.min, &self.max);
+        [
+            Vec3::new(a.x, a.y, a.z),
+            Vec3::new(b.x, a.y, a.z),
+            Vec3::new(a.x, b.y, a.z),
+            Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z),
+            Vec3::new(b.x, a.y, b.z),
+            Vec3::new(a.x, b.y, b.z),
+            Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    // `m` uses the row-vector convention of the shaders: p' = p * m
+    pub fn transformed(&self, m: &[[f32; 4]; 4]) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let mut aabb = Aabb::empty();
+        for c in self.corners().iter() {
+            aabb.expand(&transform_point(m, c));
+        }
+
+        aabb
+    }
+}
+
+pub fn transform_point(m: &[[f32; 4]; 4], p: &Vec3) -> Vec3 {
+    let mut out = [0.; 4];
+    for j in 0..4 {
+        out[j] = p.x * m[0][j] + p.y * m[1][j] + p.z * m[2][j] + m[3][j];
+    }
+
+    if out[3] != 0. && out[3] != 1. {
+        Vec3::new(out[0] / out[3], out[1] / out[3], out[2] / out[3])
+    } else {
+        Vec3::new(out[0], out[1], out[2])
+    }
+}
+
+fn sub(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3::new(a.x - b.x, a.y - b.y, a.z - b.z)
+}
+
+fn dot(a: &Vec3, b: &Vec3) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+fn cross(a: &Vec3, b: &Vec3) -> Vec3 {
+    Vec3::new(
+        a.y * b.z - a.z * b.y,
+        a.z * b.x - a.x * b.z,
+        a.x * b.y - a.y * b.x,
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    // `direction` doesn't need to be normalized; distances are measured in
+    // multiples of its length.
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray { origin, direction }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        Vec3::new(
+            self.origin.x + self.direction.x * distance,
+            self.origin.y + self.direction.y * distance,
+            self.origin.z + self.direction.z * distance,
+        )
+    }
+
+    // Slab test. Rays starting inside the box hit it at 0.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        if aabb.is_empty() {
+            return None;
+        }
+
+        let o = [self.origin.x, self.origin.y, self.origin.z];
+        let d = [self.direction.x, self.direction.y, self.direction.z];
+        let min = [aabb.min.x, aabb.min.y, aabb.min.z];
+        let max = [aabb.max.x, aabb.max.y, aabb.max.z];
+        let mut near = 0f32;
+        let mut far = std::f32::MAX;
+        for i in 0..3 {
+            if d[i] == 0. {
+                if o[i] < min[i] || o[i] > max[i] {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let t1 = (min[i] - o[i]) / d[i];
+            let t2 = (max[i] - o[i]) / d[i];
+            near = near.max(t1.min(t2));
+            far = far.min(t1.max(t2));
+            if near > far {
+                return None;
+            }
+        }
+
+        Some(near)
+    }
+
+    // Möller-Trumbore, hitting both sides of the triangle
+    pub fn intersect_triangle(&self, a: &Vec3, b: &Vec3, c: &Vec3) -> Option<f32> {
+        let edge1 = sub(b, a);
+        let edge2 = sub(c, a);
+        let p = cross(&self.direction, &edge2);
+        let det = dot(&edge1, &p);
+        if det.abs() < std::f32::EPSILON {
+            return None;
+        }
+
+        let s = sub(&self.origin, a);
+        let u = dot(&s, &p) / det;
+        if u < 0. || u > 1. {
+            return None;
+        }
+
+        let q = cross(&s, &edge1);
+        let v = dot(&self.direction, &q) / det;
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+
+        let t = dot(&edge2, &q) / det;
+        if t >= 0. {
+            Some(t)
+        } else {
+            None
+        }
+    }
+
+    // The closest hit with an indexed triangle mesh
+    pub fn intersect_mesh(&self, positions: &[Vec3], indices: &[u32]) -> Option<f32> {
+        indices
+            .chunks_exact(3)
+            .filter_map(|t| {
+                self.intersect_triangle(
+                    &positions[t[0] as usize],
+                    &positions[t[1] as usize],
+                    &positions[t[2] as usize],
+                )
+            })
+            .fold(None, |closest: Option<f32>, t| {
+                Some(closest.map_or(t, |c| c.min(t)))
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Hit<T> {
+    pub item: T,
+    pub distance: f32,
+}
+
+// Tests `ray` against world-space bounds, e.g. entity ids paired with their
+// `Aabb` transformed by the entity matrix. Hits are sorted nearest first.
+// Callers wanting exact picking can refine the hits with
+// `Ray::intersect_mesh`.
+pub fn raycast<T, I: IntoIterator<Item = (T, Aabb)>>(ray: &Ray, items: I) -> Vec<Hit<T>> {
+    let mut hits: Vec<Hit<T>> = items
+        .into_iter()
+        .filter_map(|(item, aabb)| {
+            ray.intersect_aabb(&aabb)
+                .map(|distance| Hit { item, distance })
+        })
+        .collect();
+    hits.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+    hits
+}
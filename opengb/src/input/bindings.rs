@@ -0,0 +1,311 @@
+use super::gamepad_db::parse_target;
+use super::prompts::InputDevice;
+use crate::config::read_key_values;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InputContext {
+    // Active alongside every other context, e.g. screenshots
+    Global,
+    Field,
+    Battle,
+    Menu,
+}
+
+impl InputContext {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "global" => Some(InputContext::Global),
+            "field" => Some(InputContext::Field),
+            "battle" => Some(InputContext::Battle),
+            "menu" => Some(InputContext::Menu),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InputContext::Global => "global",
+            InputContext::Field => "field",
+            InputContext::Battle => "battle",
+            InputContext::Menu => "menu",
+        }
+    }
+
+    // Whether both contexts can be listening at the same time, so that one
+    // key can't serve an action in each
+    pub fn overlaps(self, other: InputContext) -> bool {
+        self == other || self == InputContext::Global || other == InputContext::Global
+    }
+}
+
+// A keyboard key ("key:space") or a gamepad target named as in
+// gamecontrollerdb.txt ("pad:a")
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Binding {
+    pub device: InputDevice,
+    pub name: String,
+}
+
+impl Binding {
+    pub fn key(name: &str) -> Self {
+        Binding {
+            device: InputDevice::Keyboard,
+            name: name.to_lowercase(),
+        }
+    }
+
+    pub fn gamepad(name: &str) -> Self {
+        Binding {
+            device: InputDevice::Gamepad,
+            name: name.to_lowercase(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().splitn(2, ':');
+        let device = parts.next()?;
+        let name = parts.next()?.trim();
+        match device {
+            "key" if !name.is_empty() => Some(Binding::key(name)),
+            "pad" if parse_target(&name.to_lowercase()).is_some() => Some(Binding::gamepad(name)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.device {
+            InputDevice::Keyboard => write!(f, "key:{}", self.name),
+            InputDevice::Gamepad => write!(f, "pad:{}", self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub context: InputContext,
+    pub action: String,
+    pub binding: Binding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    // The conflicting actions take over the binding being replaced, or
+    // lose theirs if there was none
+    Swap,
+    Clear,
+}
+
+// The actions of every context and the bindings that trigger them. Files
+// have one `<context>.<action> = <binding>, <binding>` line per action, e.g.
+// `battle.attack = key:z, pad:a`.
+pub struct Keybindings {
+    maps: BTreeMap<InputContext, BTreeMap<String, Vec<Binding>>>,
+}
+
+impl Keybindings {
+    pub fn new() -> Self {
+        Keybindings {
+            maps: BTreeMap::new(),
+        }
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut bindings = Keybindings::new();
+        read_key_values(path, |key, value| {
+            let mut target = key.splitn(2, '.');
+            let context = InputContext::parse(target.next().unwrap_or(""));
+            let action = target.next().unwrap_or("");
+            let parsed: Option<Vec<Binding>> = value
+                .split(',')
+                .filter(|b| !b.trim().is_empty())
+                .map(Binding::parse)
+                .collect();
+            match (context, parsed) {
+                (Some(context), Some(parsed)) if !action.is_empty() => {
+                    bindings
+                        .maps
+                        .entry(context)
+                        .or_insert_with(BTreeMap::new)
+                        .insert(action.to_owned(), parsed);
+                    true
+                }
+                _ => false,
+            }
+        })?;
+
+        Ok(bindings)
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn actions(&self, context: InputContext) -> Vec<&str> {
+        self.maps
+            .get(&context)
+            .map(|m| m.keys().map(|a| a.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn bindings(&self, context: InputContext, action: &str) -> &[Binding] {
+        self.maps
+            .get(&context)
+            .and_then(|m| m.get(action))
+            .map(|b| b.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn action_for(&self, context: InputContext, binding: &Binding) -> Option<&str> {
+        self.maps
+            .get(&context)?
+            .iter()
+            .find_map(|(action, bindings)| {
+                if bindings.contains(binding) {
+                    Some(action.as_str())
+                } else {
+                    None
+                }
+            })
+    }
+
+    pub fn bind(&mut self, context: InputContext, action: &str, binding: Binding) {
+        let bindings = self
+            .maps
+            .entry(context)
+            .or_insert_with(BTreeMap::new)
+            .entry(action.to_owned())
+            .or_insert_with(Vec::new);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, context: InputContext, action: &str, binding: &Binding) {
+        if let Some(bindings) = self.maps.get_mut(&context).and_then(|m| m.get_mut(action)) {
+            bindings.retain(|b| b != binding);
+        }
+    }
+
+    // Other actions that `binding` already triggers while `context` is
+    // listening
+    pub fn conflicts_with(
+        &self,
+        context: InputContext,
+        action: &str,
+        binding: &Binding,
+    ) -> Vec<Conflict> {
+        let mut conflicts = vec![];
+        for (&other_context, actions) in &self.maps {
+            if !context.overlaps(other_context) {
+                continue;
+            }
+
+            for (other_action, bindings) in actions {
+                if (other_context != context || other_action != action)
+                    && bindings.contains(binding)
+                {
+                    conflicts.push(Conflict {
+                        context: other_context,
+                        action: other_action.clone(),
+                        binding: binding.clone(),
+                    });
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    // Every pair of actions sharing a binding, e.g. after editing the file
+    // by hand. Each pair is reported once.
+    pub fn conflicts(&self) -> Vec<(Conflict, Conflict)> {
+        let mut pairs = vec![];
+        for (&context, actions) in &self.maps {
+            for (action, bindings) in actions {
+                for binding in bindings {
+                    for other in self.conflicts_with(context, action, binding) {
+                        if (other.context, &other.action) > (context, action) {
+                            let this = Conflict {
+                                context,
+                                action: action.clone(),
+                                binding: binding.clone(),
+                            };
+                            pairs.push((this, other));
+                        }
+                    }
+                }
+            }
+        }
+
+        pairs
+    }
+
+    // Replaces `old` (or adds a binding if it is None) unless that would
+    // conflict with other actions. The conflicts are returned untouched so
+    // that the player can pick a resolution for `resolve`.
+    pub fn rebind(
+        &mut self,
+        context: InputContext,
+        action: &str,
+        old: Option<&Binding>,
+        new: Binding,
+    ) -> Vec<Conflict> {
+        let conflicts = self.conflicts_with(context, action, &new);
+        if conflicts.is_empty() {
+            self.replace(context, action, old, new);
+        }
+
+        conflicts
+    }
+
+    pub fn resolve(
+        &mut self,
+        context: InputContext,
+        action: &str,
+        old: Option<&Binding>,
+        new: Binding,
+        resolution: Resolution,
+    ) {
+        for conflict in self.conflicts_with(context, action, &new) {
+            self.unbind(conflict.context, &conflict.action, &new);
+            if let (Resolution::Swap, Some(old)) = (resolution, old) {
+                self.bind(conflict.context, &conflict.action, old.clone());
+            }
+        }
+
+        self.replace(context, action, old, new);
+    }
+
+    fn replace(
+        &mut self,
+        context: InputContext,
+        action: &str,
+        old: Option<&Binding>,
+        new: Binding,
+    ) {
+        if let Some(old) = old {
+            self.unbind(context, action, old);
+        }
+
+        self.bind(context, action, new);
+    }
+}
+
+impl fmt::Display for Keybindings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (context, actions) in &self.maps {
+            for (action, bindings) in actions {
+                let bindings: Vec<String> = bindings.iter().map(|b| b.to_string()).collect();
+                writeln!(f, "{}.{} = {}", context.name(), action, bindings.join(", "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    Back,
+    Guide,
+    Start,
+    LeftStick,
+    RightStick,
+    LeftShoulder,
+    RightShoulder,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadTarget {
+    Button(GamepadButton),
+    Axis(GamepadAxis),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AxisRange {
+    Full,
+    Positive,
+    Negative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RawInput {
+    Button(u32),
+    Axis {
+        index: u32,
+        range: AxisRange,
+        inverted: bool,
+    },
+    Hat {
+        index: u32,
+        mask: u32,
+    },
+}
+
+// One line of an SDL gamecontrollerdb.txt, e.g.
+// `030000005e0400008e02000000007200,Xbox 360 Controller,a:b0,b:b1,...,platform:Linux,`
+#[derive(Debug, Clone)]
+pub struct GamepadMapping {
+    pub guid: String,
+    pub name: String,
+    pub platform: Option<String>,
+    pub bindings: Vec<(GamepadTarget, RawInput)>,
+}
+
+impl GamepadMapping {
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let mut fields = line.split(',');
+        let guid = fields.next()?.to_lowercase();
+        let name = fields.next()?.to_owned();
+        let mut platform = None;
+        let mut bindings = vec![];
+        for field in fields.filter(|f| !f.is_empty()) {
+            // A field without a value is skipped like an unknown key
+            let mut kv = field.splitn(2, ':');
+            let key = kv.next().unwrap_or("");
+            let value = match kv.next() {
+                Some(value) => value,
+                None => continue,
+            };
+            if key == "platform" {
+                platform = Some(value.to_owned());
+                continue;
+            }
+
+            // Keys SDL knows but we don't use (e.g. misc1, paddles, touchpad)
+            // are skipped rather than failing the whole mapping.
+            if let (Some(target), Some(raw)) = (parse_target(key), parse_raw_input(value)) {
+                bindings.push((target, raw));
+            }
+        }
+
+        Some(GamepadMapping {
+            guid,
+            name,
+            platform,
+            bindings,
+        })
+    }
+
+    // The layout of XInput pads, used for controllers missing from the
+    // database.
+    pub fn fallback(guid: &str) -> Self {
+        let button = |b, i| (GamepadTarget::Button(b), RawInput::Button(i));
+        let axis = |a, i| {
+            (
+                GamepadTarget::Axis(a),
+                RawInput::Axis {
+                    index: i,
+                    range: AxisRange::Full,
+                    inverted: false,
+                },
+            )
+        };
+        let hat = |b, mask| (GamepadTarget::Button(b), RawInput::Hat { index: 0, mask });
+
+        GamepadMapping {
+            guid: guid.to_lowercase(),
+            name: "Generic Gamepad".to_owned(),
+            platform: None,
+            bindings: vec![
+                button(GamepadButton::A, 0),
+                button(GamepadButton::B, 1),
+                button(GamepadButton::X, 2),
+                button(GamepadButton::Y, 3),
+                button(GamepadButton::LeftShoulder, 4),
+                button(GamepadButton::RightShoulder, 5),
+                button(GamepadButton::Back, 6),
+                button(GamepadButton::Start, 7),
+                button(GamepadButton::LeftStick, 8),
+                button(GamepadButton::RightStick, 9),
+                button(GamepadButton::Guide, 10),
+                hat(GamepadButton::DPadUp, 1),
+                hat(GamepadButton::DPadRight, 2),
+                hat(GamepadButton::DPadDown, 4),
+                hat(GamepadButton::DPadLeft, 8),
+                axis(GamepadAxis::LeftX, 0),
+                axis(GamepadAxis::LeftY, 1),
+                axis(GamepadAxis::LeftTrigger, 2),
+                axis(GamepadAxis::RightX, 3),
+                axis(GamepadAxis::RightY, 4),
+                axis(GamepadAxis::RightTrigger, 5),
+            ],
+        }
+    }
+
+    pub fn target_for(&self, raw: &RawInput) -> Option<GamepadTarget> {
+        self.bindings
+            .iter()
+            .find(|(_, r)| match (r, raw) {
+                (RawInput::Axis { index: a, .. }, RawInput::Axis { index: b, .. }) => a == b,
+                (RawInput::Hat { index: a, mask: m }, RawInput::Hat { index: b, mask: n }) => {
+                    a == b && m & n != 0
+                }
+                _ => r == raw,
+            })
+            .map(|(t, _)| *t)
+    }
+
+    pub fn raw_for(&self, target: GamepadTarget) -> Option<RawInput> {
+        self.bindings
+            .iter()
+            .find(|(t, _)| *t == target)
+            .map(|(_, r)| *r)
+    }
+}
+
+pub(crate) fn parse_target(key: &str) -> Option<GamepadTarget> {
+    // Half-axis targets ("+leftx") map onto the same logical axis
+    let key = key.trim_start_matches(|c| c == '+' || c == '-');
+    let target = match key {
+        "a" => GamepadTarget::Button(GamepadButton::A),
+        "b" => GamepadTarget::Button(GamepadButton::B),
+        "x" => GamepadTarget::Button(GamepadButton::X),
+        "y" => GamepadTarget::Button(GamepadButton::Y),
+        "back" => GamepadTarget::Button(GamepadButton::Back),
+        "guide" => GamepadTarget::Button(GamepadButton::Guide),
+        "start" => GamepadTarget::Button(GamepadButton::Start),
+        "leftstick" => GamepadTarget::Button(GamepadButton::LeftStick),
+        "rightstick" => GamepadTarget::Button(GamepadButton::RightStick),
+        "leftshoulder" => GamepadTarget::Button(GamepadButton::LeftShoulder),
+        "rightshoulder" => GamepadTarget::Button(GamepadButton::RightShoulder),
+        "dpup" => GamepadTarget::Button(GamepadButton::DPadUp),
+        "dpdown" => GamepadTarget::Button(GamepadButton::DPadDown),
+        "dpleft" => GamepadTarget::Button(GamepadButton::DPadLeft),
+        "dpright" => GamepadTarget::Button(GamepadButton::DPadRight),
+        "leftx" => GamepadTarget::Axis(GamepadAxis::LeftX),
+        "lefty" => GamepadTarget::Axis(GamepadAxis::LeftY),
+        "rightx" => GamepadTarget::Axis(GamepadAxis::RightX),
+        "righty" => GamepadTarget::Axis(GamepadAxis::RightY),
+        "lefttrigger" => GamepadTarget::Axis(GamepadAxis::LeftTrigger),
+        "righttrigger" => GamepadTarget::Axis(GamepadAxis::RightTrigger),
+        _ => return None,
+    };
+
+    Some(target)
+}
+
+fn parse_raw_input(value: &str) -> Option<RawInput> {
+    let (range, value) = if value.starts_with('+') {
+        (AxisRange::Positive, &value[1..])
+    } else if value.starts_with('-') {
+        (AxisRange::Negative, &value[1..])
+    } else {
+        (AxisRange::Full, value)
+    };
+
+    let (inverted, value) = if value.ends_with('~') {
+        (true, &value[..value.len() - 1])
+    } else {
+        (false, value)
+    };
+
+    if value.len() < 2 || !value.is_char_boundary(1) {
+        return None;
+    }
+
+    let (kind, rest) = value.split_at(1);
+    match kind {
+        "b" => rest.parse().ok().map(RawInput::Button),
+        "a" => rest.parse().ok().map(|index| RawInput::Axis {
+            index,
+            range,
+            inverted,
+        }),
+        "h" => {
+            let mut parts = rest.splitn(2, '.');
+            let index = parts.next()?.parse().ok()?;
+            let mask = parts.next()?.parse().ok()?;
+            Some(RawInput::Hat { index, mask })
+        }
+        _ => None,
+    }
+}
+
+pub struct GamepadDatabase {
+    mappings: HashMap<String, GamepadMapping>,
+}
+
+impl GamepadDatabase {
+    pub fn new() -> Self {
+        GamepadDatabase {
+            mappings: HashMap::new(),
+        }
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P, platform: &str) -> Result<Self, Box<dyn Error>> {
+        let mut db = GamepadDatabase::new();
+        db.add_mappings(&std::fs::read_to_string(path)?, platform);
+        Ok(db)
+    }
+
+    // Adds every mapping in `text` for `platform`. Later entries override
+    // earlier ones, so user mappings can be appended after the shipped db.
+    pub fn add_mappings(&mut self, text: &str, platform: &str) -> usize {
+        let mut count = 0;
+        for mapping in text.lines().filter_map(GamepadMapping::parse) {
+            let matches_platform = match &mapping.platform {
+                Some(p) => p == platform,
+                None => true,
+            };
+
+            if matches_platform {
+                self.mappings.insert(mapping.guid.clone(), mapping);
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.mappings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    pub fn get(&self, guid: &str) -> Option<&GamepadMapping> {
+        self.mappings.get(&guid.to_lowercase())
+    }
+
+    pub fn get_or_fallback(&self, guid: &str) -> GamepadMapping {
+        self.get(guid)
+            .cloned()
+            .unwrap_or_else(|| GamepadMapping::fallback(guid))
+    }
+}
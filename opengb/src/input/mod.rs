@@ -0,0 +1,4 @@
+pub mod bindings;
+pub mod gamepad_db;
+pub mod prompts;
+pub mod sequence;
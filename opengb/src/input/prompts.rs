@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputDevice {
+    Keyboard,
+    Gamepad,
+}
+
+// Resolves `{button:Action}` markup in UI strings to the glyph of the
+// device the player used last, e.g. "Press {button:Confirm}" becomes
+// "Press Enter" or "Press (A)".
+pub struct ButtonPrompts {
+    active_device: InputDevice,
+    glyphs: HashMap<(InputDevice, String), String>,
+}
+
+impl ButtonPrompts {
+    pub fn new() -> Self {
+        ButtonPrompts {
+            active_device: InputDevice::Keyboard,
+            glyphs: HashMap::new(),
+        }
+    }
+
+    pub fn set_glyph(&mut self, device: InputDevice, action: &str, glyph: &str) {
+        self.glyphs
+            .insert((device, action.to_owned()), glyph.to_owned());
+    }
+
+    pub fn active_device(&self) -> InputDevice {
+        self.active_device
+    }
+
+    pub fn notify_device_used(&mut self, device: InputDevice) {
+        self.active_device = device;
+    }
+
+    pub fn glyph(&self, action: &str) -> Option<&str> {
+        self.glyphs
+            .get(&(self.active_device, action.to_owned()))
+            .map(|g| g.as_str())
+    }
+
+    // Unknown actions are left untouched so that missing glyphs are easy to
+    // spot on screen.
+    pub fn resolve(&self, text: &str) -> String {
+        const OPEN: &str = "{button:";
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(OPEN) {
+            result.push_str(&rest[..start]);
+            let after = &rest[start + OPEN.len()..];
+            match after.find('}') {
+                Some(end) => {
+                    let action = &after[..end];
+                    match self.glyph(action) {
+                        Some(glyph) => result.push_str(glyph),
+                        None => result.push_str(&rest[start..start + OPEN.len() + end + 1]),
+                    }
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    result.push_str(&rest[start..]);
+                    rest = "";
+                }
+            }
+        }
+
+        result.push_str(rest);
+        result
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone)]
+pub struct InputSequence<A> {
+    pub actions: Vec<A>,
+    // Longest allowed gap between two consecutive actions, in seconds
+    pub max_interval: f32,
+    // Longest allowed time from the first to the last action, in seconds
+    pub max_duration: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BufferedInput<A> {
+    action: A,
+    time: f32,
+}
+
+// Keeps the most recent actions with their timestamps so that mini-games
+// and QTE-style script commands can look for timed sequences.
+pub struct InputBuffer<A> {
+    inputs: VecDeque<BufferedInput<A>>,
+    capacity: usize,
+    max_age: f32,
+    time: f32,
+}
+
+impl<A: Copy + PartialEq> InputBuffer<A> {
+    pub fn new(capacity: usize, max_age: f32) -> Self {
+        InputBuffer {
+            inputs: VecDeque::with_capacity(capacity),
+            capacity,
+            max_age,
+            time: 0.,
+        }
+    }
+
+    pub fn update(&mut self, delta_sec: f32) {
+        self.time += delta_sec;
+        while let Some(input) = self.inputs.front() {
+            if self.time - input.time > self.max_age {
+                self.inputs.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub fn push(&mut self, action: A) {
+        if self.inputs.len() == self.capacity {
+            self.inputs.pop_front();
+        }
+
+        self.inputs.push_back(BufferedInput {
+            action,
+            time: self.time,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.inputs.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    // Whether the latest buffered actions complete `sequence`.
+    pub fn matches(&self, sequence: &InputSequence<A>) -> bool {
+        let count = sequence.actions.len();
+        if count == 0 || count > self.inputs.len() {
+            return false;
+        }
+
+        let start = self.inputs.len() - count;
+        let inputs: Vec<&BufferedInput<A>> = self.inputs.iter().skip(start).collect();
+        for (i, input) in inputs.iter().enumerate() {
+            if input.action != sequence.actions[i] {
+                return false;
+            }
+
+            if i > 0 && input.time - inputs[i - 1].time > sequence.max_interval {
+                return false;
+            }
+        }
+
+        inputs[count - 1].time - inputs[0].time <= sequence.max_duration
+    }
+}
+
+// Detects named sequences as actions come in. When several sequences
+// complete on the same action, the longest one wins and the buffer is
+// cleared so its prefix doesn't trigger again.
+pub struct SequenceDetector<A> {
+    buffer: InputBuffer<A>,
+    sequences: Vec<(String, InputSequence<A>)>,
+}
+
+impl<A: Copy + PartialEq> SequenceDetector<A> {
+    pub fn new(buffer: InputBuffer<A>) -> Self {
+        SequenceDetector {
+            buffer,
+            sequences: vec![],
+        }
+    }
+
+    pub fn register(&mut self, name: &str, sequence: InputSequence<A>) {
+        self.sequences.push((name.to_owned(), sequence));
+        self.sequences
+            .sort_by(|a, b| b.1.actions.len().cmp(&a.1.actions.len()));
+    }
+
+    pub fn update(&mut self, delta_sec: f32) {
+        self.buffer.update(delta_sec);
+    }
+
+    pub fn push(&mut self, action: A) -> Option<String> {
+        self.buffer.push(action);
+        let buffer = &self.buffer;
+        let detected = self
+            .sequences
+            .iter()
+            .find(|(_, sequence)| buffer.matches(sequence))
+            .map(|(name, _)| name.clone());
+
+        if detected.is_some() {
+            self.buffer.clear();
+        }
+
+        detected
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+    }
+}
@@ -0,0 +1,101 @@
+use crate::animation::Interpolate;
+use radiance::math::{Quaternion, Vec3};
+
+// Splits variable frame times into fixed simulation steps. The remainder
+// is kept for the next frame and tells how far rendering is between the
+// last two steps.
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    // At most `max_steps` run per frame, so that a long hitch doesn't
+    // snowball into ever longer frames.
+    pub fn new(step: f32, max_steps: u32) -> Self {
+        FixedTimestep {
+            step: step.max(std::f32::EPSILON),
+            accumulator: 0.,
+            max_steps: max_steps.max(1),
+        }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    // Returns the number of steps to simulate this frame
+    pub fn advance(&mut self, delta_sec: f32) -> u32 {
+        self.accumulator += delta_sec.max(0.);
+        let steps = (self.accumulator / self.step) as u32;
+        if steps > self.max_steps {
+            self.accumulator = 0.;
+            return self.max_steps;
+        }
+
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    // How far the frame is past the last step, in 0..1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).min(1.)
+    }
+}
+
+// Keeps the positions and rotations of the last two simulation steps of an
+// entity, so that it can be drawn in between them. Entities moved by a
+// fixed-step simulation, like roles, add it as a component,
+// `push` after each step and apply `position`/`rotation` to their
+// transform before drawing. Disabled entities snap to the latest step.
+pub struct InterpolatedTransform {
+    previous: (Vec3, Quaternion),
+    current: (Vec3, Quaternion),
+    enabled: bool,
+}
+
+impl InterpolatedTransform {
+    pub fn new(position: Vec3, rotation: Quaternion) -> Self {
+        InterpolatedTransform {
+            previous: (position, rotation),
+            current: (position, rotation),
+            enabled: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn push(&mut self, position: Vec3, rotation: Quaternion) {
+        self.previous = self.current;
+        self.current = (position, rotation);
+    }
+
+    // Moves without interpolating, e.g. when teleporting
+    pub fn reset(&mut self, position: Vec3, rotation: Quaternion) {
+        self.previous = (position, rotation);
+        self.current = (position, rotation);
+    }
+
+    pub fn position(&self, alpha: f32) -> Vec3 {
+        if self.enabled {
+            self.previous.0.interpolate(&self.current.0, alpha)
+        } else {
+            self.current.0
+        }
+    }
+
+    pub fn rotation(&self, alpha: f32) -> Quaternion {
+        if self.enabled {
+            self.previous.1.interpolate(&self.current.1, alpha)
+        } else {
+            self.current.1
+        }
+    }
+}
@@ -1,2 +1,42 @@
+pub mod ambience;
+pub mod animation;
+pub mod cache;
+pub mod config;
+pub mod cpk;
+pub mod debug_draw;
+pub mod diagnostics;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fog;
+pub mod game;
+pub mod geometry;
+pub mod input;
+pub mod interpolation;
+pub mod json;
 pub mod loaders;
 pub mod material;
+pub mod material_overrides;
+pub mod model;
+pub mod particles;
+pub mod plugins;
+pub mod role;
+pub mod scene_desc;
+pub mod settings;
+pub mod shader_registry;
+#[cfg(debug_assertions)]
+pub mod shader_reload;
+pub mod sprite;
+pub mod texture_animation;
+pub mod texture_cache;
+pub mod thumbnail;
+pub mod ui;
+pub mod vfs;
+pub mod water;
+
+// The stable entry points for tools depending on opengb. Modules stay public
+// for the engine's own use, but their layout may change between versions.
+pub use loaders::cvdloader::{cvd_load_from_file, CvdFile, CvdMaterial, CvdMesh, CvdModel};
+pub use loaders::mv3loader::{mv3_load_from_file, Mv3File, Mv3Mesh, Mv3Model};
+pub use loaders::polloader::{pol_load_from_file, PolFile, PolMaterialInfo, PolMesh};
+pub use vfs::Vfs;
@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::error::Error;
-use std::io::{Read, BufReader};
+use std::io::{Read, BufReader, Cursor};
 use radiance::math::{ Mat44, Vec3, Vec2, Quaternion };
 use byteorder::{LittleEndian, ReadBytesExt};
 use super::{calc_vertex_size, read_vec};
@@ -82,7 +82,21 @@ pub struct CvdFile {
 }
 
 pub fn cvd_load_from_file<P: AsRef<Path>>(path: P) -> Result<CvdFile, Box<dyn Error>> {
+    let mut ani_path: PathBuf = path.as_ref().to_path_buf();
+    ani_path.set_extension("ani");
+    if ani_path.exists() {
+        println!("Found ani file {:?} which isn't supported yet", ani_path);
+    }
+
     let mut reader = BufReader::new(fs::File::open(&path).unwrap());
+    cvd_load_from_reader(&mut reader)
+}
+
+pub fn cvd_load_from_bytes(data: &[u8]) -> Result<CvdFile, Box<dyn Error>> {
+    cvd_load_from_reader(&mut Cursor::new(data))
+}
+
+pub fn cvd_load_from_reader(mut reader: &mut dyn Read) -> Result<CvdFile, Box<dyn Error>> {
     let mut magic = [0u8; 4];
     reader.read_exact(&mut magic).unwrap();
 
@@ -92,12 +106,6 @@ pub fn cvd_load_from_file<P: AsRef<Path>>(path: P) -> Result<CvdFile, Box<dyn Er
         _ => panic!("Not a valid cvd file"),
     };
 
-    let mut ani_path: PathBuf = path.as_ref().to_path_buf();
-    ani_path.set_extension("ani");
-    if ani_path.exists() {
-        println!("Found ani file {:?} which isn't supported yet", ani_path);
-    }
-    
     let model_count = reader.read_u32::<LittleEndian>().unwrap();
 
     println!("model_count: {}", model_count);
@@ -114,7 +122,7 @@ pub fn cvd_load_from_file<P: AsRef<Path>>(path: P) -> Result<CvdFile, Box<dyn Er
     })
 }
 
-pub fn cvd_load_model(reader: &mut dyn Read, unknown_float: f32) -> Result<Option<CvdModel>, Box<dyn Error>> {
+fn cvd_load_model(reader: &mut dyn Read, unknown_float: f32) -> Result<Option<CvdModel>, Box<dyn Error>> {
     let unknown_byte = reader.read_u8().unwrap();
     println!("unknown_byte {}", unknown_byte);
     if unknown_byte == 0 {
@@ -154,7 +162,7 @@ pub fn cvd_load_model(reader: &mut dyn Read, unknown_float: f32) -> Result<Optio
     }))
 }
 
-pub fn cvd_load_mesh(reader: &mut dyn Read, unknown_float: f32) -> Result<CvdMesh, Box<dyn Error>> {
+fn cvd_load_mesh(reader: &mut dyn Read, unknown_float: f32) -> Result<CvdMesh, Box<dyn Error>> {
     let frame_count = reader.read_u32::<LittleEndian>().unwrap();
     let vertex_count = reader.read_u32::<LittleEndian>().unwrap();
     let vertex_size = calc_vertex_size(19);
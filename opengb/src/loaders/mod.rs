@@ -2,6 +2,35 @@ pub mod mv3loader;
 pub mod polloader;
 pub mod cvdloader;
 
+// Model formats come in several revisions across PAL3 and its expansion.
+// The revision decides which optional blocks a file has.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModelFormat {
+    // Versions above 100 carry node matrices after the node descriptions
+    Pol { version: u32 },
+    // b's' or b'f', the last byte of the magic
+    Cvd { revision: u8 },
+    Mv3,
+}
+
+// Tells the format from the first 8 bytes of a file
+pub fn detect_model_format(header: &[u8]) -> Option<ModelFormat> {
+    if header.len() < 8 {
+        return None;
+    }
+
+    match &header[0..4] {
+        b"POLY" => Some(ModelFormat::Pol {
+            version: u32::from_le_bytes([header[4], header[5], header[6], header[7]]),
+        }),
+        b"cvds" | b"cvdf" => Some(ModelFormat::Cvd {
+            revision: header[3],
+        }),
+        b"MV3\0" => Some(ModelFormat::Mv3),
+        _ => None,
+    }
+}
+
 fn read_vec(reader: &mut dyn std::io::Read, size: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     let mut buf = vec![0u8; size];
     reader.read_exact(&mut buf.as_mut_slice())?;
@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::Path;
 use std::error::Error;
-use std::io::{Read, BufReader};
+use std::io::{Read, BufReader, Cursor};
 use byteorder::{LittleEndian, ReadBytesExt};
 use super::read_vec;
 
@@ -84,6 +84,14 @@ pub struct Mv3File {
 
 pub fn mv3_load_from_file<P: AsRef<Path>>(path: P) -> Result<Mv3File, Box<dyn Error>> {
     let mut reader = BufReader::new(fs::File::open(path)?);
+    mv3_load_from_reader(&mut reader)
+}
+
+pub fn mv3_load_from_bytes(data: &[u8]) -> Result<Mv3File, Box<dyn Error>> {
+    mv3_load_from_reader(&mut Cursor::new(data))
+}
+
+pub fn mv3_load_from_reader(mut reader: &mut dyn Read) -> Result<Mv3File, Box<dyn Error>> {
     let mut magic = [0u8; 4];
     reader.read_exact(&mut magic)?;
 
@@ -1,8 +1,9 @@
 use std::fs;
 use std::path::Path;
 use std::error::Error;
-use std::io::{Read, BufReader};
-use radiance::math::Mat44;
+use std::io::{Read, BufReader, Cursor};
+use radiance::math::{Mat44, Vec2, Vec3};
+use radiance::rendering::{VertexBuffer, VertexComponents};
 use byteorder::{LittleEndian, ReadBytesExt};
 use super::read_vec;
 use encoding::{Encoding, DecoderTrap};
@@ -13,6 +14,8 @@ impl PolVertexComponents {
     pub const POSITION: Self = PolVertexComponents(0b1);
     pub const NORMAL: Self = PolVertexComponents(0b10);
     pub const UNKNOWN4: Self = PolVertexComponents(0b100);
+    // UNKNOWN4 is where D3D vertex formats keep the diffuse color
+    pub const DIFFUSE: Self = PolVertexComponents(0b100);
     pub const UNKNOWN8: Self = PolVertexComponents(0b1000);
     pub const TEXCOORD: Self = PolVertexComponents(0b10000);
     pub const TEXCOORD2: Self = PolVertexComponents(0b100000);
@@ -51,6 +54,21 @@ pub struct PolVertex {
     pub unknown100: Option<[f32; 4]>,
 }
 
+impl PolVertex {
+    // The baked diffuse color as rgb in 0..1, stored as a D3DCOLOR
+    // (0xAARRGGBB) in the `unknown4` field.
+    pub fn diffuse_color(&self) -> Option<[f32; 3]> {
+        self.unknown4.map(|c| {
+            let argb = c[0].to_bits();
+            [
+                ((argb >> 16) & 0xff) as f32 / 255.,
+                ((argb >> 8) & 0xff) as f32 / 255.,
+                (argb & 0xff) as f32 / 255.,
+            ]
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct PolMaterialInfo {
     pub unknown_dw0: u32,
@@ -81,6 +99,46 @@ pub struct PolMesh {
     pub material_info: Vec<PolMaterialInfo>,
 }
 
+impl PolMesh {
+    // Builds a vertex buffer from the vertices referenced by `vertex_indices`,
+    // in that order, without going through an intermediate copy.
+    pub fn to_vertex_buffer(&self, components: VertexComponents, vertex_indices: &[usize]) -> VertexBuffer {
+        let mut vertices = VertexBuffer::new(components, vertex_indices.len());
+        self.fill_vertex_buffer(&mut vertices, 0, vertex_indices);
+        vertices
+    }
+
+    // Same as `to_vertex_buffer` but writes into an existing buffer starting
+    // at `offset`, so that several meshes can share one buffer.
+    pub fn fill_vertex_buffer(&self, vertices: &mut VertexBuffer, offset: usize, vertex_indices: &[usize]) {
+        for (i, &index) in vertex_indices.iter().enumerate() {
+            let vert = &self.vertices[index];
+            vertices.set_data(
+                offset + i,
+                Some(&Vec3::new(vert.position.x, vert.position.y, vert.position.z)),
+                vert.normal.map(|n| Vec3::new(n[0], n[1], n[2])).as_ref(),
+                Some(&Vec2::new(vert.tex_coord.u, vert.tex_coord.v)),
+                vert.tex_coord2
+                    .as_ref()
+                    .map(|tex_coord2| Vec2::new(tex_coord2.u, tex_coord2.v))
+                    .as_ref(),
+            );
+        }
+    }
+
+    // Writes the diffuse colors into the NORMAL slot, after
+    // `fill_vertex_buffer`, for the vertex color materials. Meshes without
+    // colors are filled with white.
+    pub fn fill_vertex_colors(&self, vertices: &mut VertexBuffer, offset: usize, vertex_indices: &[usize]) {
+        for (i, &index) in vertex_indices.iter().enumerate() {
+            let color = self.vertices[index].diffuse_color().unwrap_or([1., 1., 1.]);
+            vertices.set_component(offset + i, VertexComponents::NORMAL, |c: &mut Vec3| {
+                *c = Vec3::new(color[0], color[1], color[2]);
+            });
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct UnknownData {
     pub unknown: Vec<u8>, // size: 32
@@ -108,6 +166,14 @@ pub struct PolFile {
 
 pub fn pol_load_from_file<P: AsRef<Path>>(path: P) -> Result<PolFile, Box<dyn Error>> {
     let mut reader = BufReader::new(fs::File::open(path)?);
+    pol_load_from_reader(&mut reader)
+}
+
+pub fn pol_load_from_bytes(data: &[u8]) -> Result<PolFile, Box<dyn Error>> {
+    pol_load_from_reader(&mut Cursor::new(data))
+}
+
+pub fn pol_load_from_reader(mut reader: &mut dyn Read) -> Result<PolFile, Box<dyn Error>> {
     let mut magic = [0u8; 4];
     reader.read_exact(&mut magic)?;
 
@@ -1,37 +1,147 @@
+use crate::fog::FogParams;
+use crate::particles::ParticleEmitter;
+use crate::sprite::Billboard;
+use crate::texture_cache::{load_texture_image, texture_cache_enabled};
+use crate::ui::ScreenQuad;
+use crate::water::WaterSurface;
 use radiance::rendering::{Shader, Material, VertexComponents, Texture};
+use std::borrow::Cow;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 
 static LIGHTMAP_TEXTURE_VERT: &'static [u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/lightmap_texture.vert.spv"));
 static LIGHTMAP_TEXTURE_FRAG: &'static [u8] =
     include_bytes!(concat!(env!("OUT_DIR"), "/lightmap_texture.frag.spv"));
+static LIGHTMAP_ONLY_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/lightmap_only.frag.spv"));
+static DIFFUSE_ONLY_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/diffuse_only.frag.spv"));
+static LIGHTMAP_VERTEX_COLOR_VERT: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/lightmap_vertex_color.vert.spv"));
+static LIGHTMAP_VERTEX_COLOR_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/lightmap_vertex_color.frag.spv"));
+static SIMPLE_TEXTURE_VERT: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/simple_texture.vert.spv"));
+static SIMPLE_TEXTURE_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/simple_texture.frag.spv"));
+static LIT_TEXTURE_VERT: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/lit_texture.vert.spv"));
+static LIT_TEXTURE_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/lit_texture.frag.spv"));
+static VERTEX_COLOR_VERT: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/vertex_color.vert.spv"));
+static VERTEX_COLOR_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/vertex_color.frag.spv"));
+static WATER_VERT: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/water.vert.spv"));
+static WATER_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/water.frag.spv"));
+static SKY_VERT: &'static [u8] = include_bytes!(concat!(env!("OUT_DIR"), "/sky.vert.spv"));
+static SKY_FRAG: &'static [u8] = include_bytes!(concat!(env!("OUT_DIR"), "/sky.frag.spv"));
+static BILLBOARD_VERT: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/billboard.vert.spv"));
+static BILLBOARD_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/billboard.frag.spv"));
+static PARTICLE_VERT: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/particle.vert.spv"));
+static PARTICLE_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/particle.frag.spv"));
+static SCREEN_TEXTURE_VERT: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/screen_texture.vert.spv"));
+static SCREEN_TEXTURE_FRAG: &'static [u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/screen_texture.frag.spv"));
 pub static WHITE_TEXTURE_FILE: &'static [u8] = include_bytes!(concat!(
     env!("CARGO_MANIFEST_DIR"),
     "/embed/textures/white.png"
 ));
 
 
-pub struct LightMapShader {}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightMapMode {
+    Combined,
+    LightMapOnly,
+    DiffuseOnly,
+}
+
+// With vertex colors, the color is read from the NORMAL slot of the vertex
+// buffer and modulates the diffuse texture. Only the combined mode applies
+// it; the debug modes keep showing the plain textures.
+pub struct LightMapShader {
+    mode: LightMapMode,
+    vertex_colors: bool,
+    fog: FogParams,
+    name: String,
+    vert_src: Cow<'static, [u8]>,
+    frag_src: Cow<'static, [u8]>,
+}
+
+impl LightMapShader {
+    fn new(mode: LightMapMode, vertex_colors: bool, fog: FogParams) -> Self {
+        let (name, frag_src) = match mode {
+            LightMapMode::Combined if vertex_colors => {
+                ("lightmap_vertex_color", LIGHTMAP_VERTEX_COLOR_FRAG)
+            }
+            LightMapMode::Combined => ("lightmap_texture", LIGHTMAP_TEXTURE_FRAG),
+            LightMapMode::LightMapOnly => ("lightmap_only", LIGHTMAP_ONLY_FRAG),
+            LightMapMode::DiffuseOnly => ("diffuse_only", DIFFUSE_ONLY_FRAG),
+        };
+        let frag_src = builtin_shader(&format!("{}.frag", name), frag_src);
+        let vert_src = if vertex_colors {
+            builtin_shader("lightmap_vertex_color.vert", LIGHTMAP_VERTEX_COLOR_VERT)
+        } else {
+            builtin_shader("lightmap_texture.vert", LIGHTMAP_TEXTURE_VERT)
+        };
+
+        // The vertex layout differs, so the name has to as well
+        let name = if vertex_colors && mode != LightMapMode::Combined {
+            format!("{}_vertex_color{}", name, fog.shader_name_suffix())
+        } else {
+            format!("{}{}", name, fog.shader_name_suffix())
+        };
+
+        let frag_src = if fog.is_enabled() {
+            Cow::Owned(fog.apply_to_shader(&frag_src))
+        } else {
+            frag_src
+        };
+
+        LightMapShader {
+            mode,
+            vertex_colors,
+            fog,
+            name,
+            vert_src,
+            frag_src,
+        }
+    }
+}
 
 impl Shader for LightMapShader {
     fn name(&self) -> &str {
-        "lightmap_texture"
+        &self.name
     }
 
     fn vertex_components(&self) -> VertexComponents {
-        VertexComponents::POSITION | VertexComponents::TEXCOORD | VertexComponents::TEXCOORD2
+        if self.vertex_colors {
+            VertexComponents::POSITION
+                | VertexComponents::NORMAL
+                | VertexComponents::TEXCOORD
+                | VertexComponents::TEXCOORD2
+        } else {
+            VertexComponents::POSITION | VertexComponents::TEXCOORD | VertexComponents::TEXCOORD2
+        }
     }
 
     fn vert_src(&self) -> &[u8] {
-        LIGHTMAP_TEXTURE_VERT
+        &self.vert_src
     }
 
     fn frag_src(&self) -> &[u8] {
-        LIGHTMAP_TEXTURE_FRAG
+        &self.frag_src
     }
 }
-    
 
 pub struct LightMapMaterial {
     textures: Vec<Texture>,
@@ -40,18 +150,25 @@ pub struct LightMapMaterial {
 
 impl LightMapMaterial {
     pub fn new(texture_paths: &[PathBuf]) -> Self {
-        let textures: Vec<Texture> = texture_paths.iter().map(|p| {
-            if p.file_stem() == None {
-                Texture::new_with_iamge(image::load_from_memory(&WHITE_TEXTURE_FILE).unwrap().to_rgba())
-            } else {
-                Texture::new(p)
-            }
-        }).collect();
+        Self::new_with_mode(texture_paths, LightMapMode::Combined)
+    }
+
+    pub fn new_with_mode(texture_paths: &[PathBuf], mode: LightMapMode) -> Self {
         LightMapMaterial {
-            textures,
-            shader: LightMapShader {},
+            textures: load_textures(texture_paths),
+            shader: LightMapShader::new(mode, false, FogParams::none()),
         }
     }
+
+    pub fn with_fog(mut self, fog: &FogParams) -> Self {
+        self.shader = LightMapShader::new(self.shader.mode, self.shader.vertex_colors, *fog);
+        self
+    }
+
+    pub fn with_vertex_colors(mut self) -> Self {
+        self.shader = LightMapShader::new(self.shader.mode, true, self.shader.fog);
+        self
+    }
 }
 
 impl Material for LightMapMaterial {
@@ -67,3 +184,280 @@ impl Material for LightMapMaterial {
         &self.textures
     }
 }
+
+// Debug builds pick up shaders recompiled by `shader_reload::ShaderWatcher`
+#[cfg(debug_assertions)]
+fn builtin_shader(name: &str, src: &'static [u8]) -> Cow<'static, [u8]> {
+    match crate::shader_reload::reloaded_shader(name) {
+        Some(reloaded) => Cow::Owned(reloaded),
+        None => Cow::Borrowed(src),
+    }
+}
+
+#[cfg(not(debug_assertions))]
+fn builtin_shader(_name: &str, src: &'static [u8]) -> Cow<'static, [u8]> {
+    Cow::Borrowed(src)
+}
+
+static MAX_TEXTURE_SIZE: AtomicU32 = AtomicU32::new(0);
+
+// Textures larger than `size` in either dimension are downscaled when
+// materials load them. 0 disables the limit.
+pub fn set_max_texture_size(size: u32) {
+    MAX_TEXTURE_SIZE.store(size, Ordering::Relaxed);
+}
+
+static UV_CHECKER: AtomicBool = AtomicBool::new(false);
+
+// Replaces the diffuse texture of every material loaded afterwards with a
+// checker pattern, to check texture coordinates from the loaders.
+pub fn set_uv_checker(enabled: bool) {
+    UV_CHECKER.store(enabled, Ordering::Relaxed);
+}
+
+// `cells` squares along each side, with a color gradient across them so
+// that flipped or rotated coordinates stand out.
+pub fn uv_checker_image(size: u32, cells: u32) -> image::RgbaImage {
+    let cell_size = (size / cells.max(1)).max(1);
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        let (cx, cy) = (x / cell_size, y / cell_size);
+        let shade = if (cx + cy) % 2 == 0 { 255 } else { 96 };
+        let r = (cx * 255 / cells.max(1)) as u8;
+        let g = (cy * 255 / cells.max(1)) as u8;
+        image::Rgba([
+            (r as u32 * shade / 255) as u8,
+            (g as u32 * shade / 255) as u8,
+            shade as u8,
+            255,
+        ])
+    })
+}
+
+fn load_textures(texture_paths: &[PathBuf]) -> Vec<Texture> {
+    let max_size = MAX_TEXTURE_SIZE.load(Ordering::Relaxed);
+    let uv_checker = UV_CHECKER.load(Ordering::Relaxed);
+    texture_paths.iter().enumerate().map(|(i, p)| {
+        // The diffuse texture is the last one
+        if uv_checker && i + 1 == texture_paths.len() {
+            Texture::new_with_iamge(uv_checker_image(256, 8))
+        } else if p.file_stem() == None {
+            Texture::new_with_iamge(image::load_from_memory(&WHITE_TEXTURE_FILE).unwrap().to_rgba())
+        } else if max_size > 0 || texture_cache_enabled() {
+            match load_texture_image(p, max_size) {
+                Some(image) => Texture::new_with_iamge(image),
+                // Formats the image crate can't decode are left to radiance
+                None => Texture::new(p),
+            }
+        } else {
+            Texture::new(p)
+        }
+    }).collect()
+}
+
+// A shader described by data rather than a dedicated type, so that new
+// PAL3-specific materials only need their SPIR-V and vertex layout.
+pub struct CustomShader {
+    name: String,
+    vertex_components: VertexComponents,
+    vert_src: Cow<'static, [u8]>,
+    frag_src: Cow<'static, [u8]>,
+}
+
+impl CustomShader {
+    pub fn new<V: Into<Cow<'static, [u8]>>, F: Into<Cow<'static, [u8]>>>(
+        name: &str,
+        vertex_components: VertexComponents,
+        vert_src: V,
+        frag_src: F,
+    ) -> Self {
+        CustomShader {
+            name: name.to_owned(),
+            vertex_components,
+            vert_src: vert_src.into(),
+            frag_src: frag_src.into(),
+        }
+    }
+
+    // Only has an effect on fragment shaders declaring the fog constants
+    pub fn with_fog(mut self, fog: &FogParams) -> Self {
+        self.name.push_str(&fog.shader_name_suffix());
+        self.frag_src = Cow::Owned(fog.apply_to_shader(&self.frag_src));
+        self
+    }
+}
+
+impl Shader for CustomShader {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn vertex_components(&self) -> VertexComponents {
+        self.vertex_components
+    }
+
+    fn vert_src(&self) -> &[u8] {
+        &self.vert_src
+    }
+
+    fn frag_src(&self) -> &[u8] {
+        &self.frag_src
+    }
+}
+
+// Textures are bound to the sampler array in the order of `texture_paths`.
+// A path without a file name binds the embedded white texture.
+pub struct CustomMaterial {
+    name: String,
+    textures: Vec<Texture>,
+    shader: CustomShader,
+}
+
+impl CustomMaterial {
+    pub fn new(name: &str, shader: CustomShader, texture_paths: &[PathBuf]) -> Self {
+        CustomMaterial {
+            name: name.to_owned(),
+            textures: load_textures(texture_paths),
+            shader,
+        }
+    }
+
+    pub fn with_fog(mut self, fog: &FogParams) -> Self {
+        self.shader = self.shader.with_fog(fog);
+        self
+    }
+}
+
+impl Material for CustomMaterial {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn shader(&self) -> &dyn Shader {
+        &self.shader
+    }
+
+    fn textures(&self) -> &[Texture] {
+        &self.textures
+    }
+}
+
+// An unlit single-texture material. Unlike radiance's `SimpleMaterial`,
+// it loads its texture like the other materials here, so the texture
+// cache, `set_max_texture_size` and `set_uv_checker` apply to it.
+pub fn create_simple_material(texture_path: &PathBuf) -> CustomMaterial {
+    CustomMaterial::new(
+        "simple_texture_material",
+        CustomShader::new(
+            "simple_texture",
+            VertexComponents::POSITION | VertexComponents::TEXCOORD,
+            builtin_shader("simple_texture.vert", SIMPLE_TEXTURE_VERT),
+            builtin_shader("simple_texture.frag", SIMPLE_TEXTURE_FRAG),
+        ),
+        &[texture_path.clone()],
+    )
+}
+
+// A single-texture material shaded by a fixed directional light. Requires
+// normals in the vertex buffer.
+pub fn create_lit_material(texture_path: &PathBuf) -> CustomMaterial {
+    CustomMaterial::new(
+        "lit_material",
+        CustomShader::new(
+            "lit_texture",
+            VertexComponents::POSITION | VertexComponents::NORMAL | VertexComponents::TEXCOORD,
+            builtin_shader("lit_texture.vert", LIT_TEXTURE_VERT),
+            builtin_shader("lit_texture.frag", LIT_TEXTURE_FRAG),
+        ),
+        &[texture_path.clone()],
+    )
+}
+
+// A single-texture material modulated by per-vertex colors, which are read
+// from the NORMAL slot of the vertex buffer.
+pub fn create_vertex_color_material(texture_path: &PathBuf) -> CustomMaterial {
+    CustomMaterial::new(
+        "vertex_color_material",
+        CustomShader::new(
+            "vertex_color",
+            VertexComponents::POSITION | VertexComponents::NORMAL | VertexComponents::TEXCOORD,
+            builtin_shader("vertex_color.vert", VERTEX_COLOR_VERT),
+            builtin_shader("vertex_color.frag", VERTEX_COLOR_FRAG),
+        ),
+        &[texture_path.clone()],
+    )
+}
+
+// For vertex buffers filled by `water::WaterSurface`. The alpha only shows
+// through where the pipeline blends; there is no reflection yet, as that
+// needs rendering the scene into a texture first.
+pub fn create_water_material(texture_path: &PathBuf, surface: &WaterSurface) -> CustomMaterial {
+    let frag_src = surface.apply_to_shader(&builtin_shader("water.frag", WATER_FRAG));
+    CustomMaterial::new(
+        "water_material",
+        CustomShader::new(
+            &format!("water{}", surface.shader_name_suffix()),
+            WaterSurface::vertex_components(),
+            builtin_shader("water.vert", WATER_VERT),
+            frag_src,
+        ),
+        &[texture_path.clone()],
+    )
+}
+
+// An unlit material for sky domes. The vertex shader ignores the camera
+// position, so the dome always surrounds the viewer.
+pub fn create_sky_material(texture_path: &PathBuf) -> CustomMaterial {
+    CustomMaterial::new(
+        "sky_material",
+        CustomShader::new(
+            "sky",
+            VertexComponents::POSITION | VertexComponents::TEXCOORD,
+            builtin_shader("sky.vert", SKY_VERT),
+            builtin_shader("sky.frag", SKY_FRAG),
+        ),
+        &[texture_path.clone()],
+    )
+}
+
+// For vertex buffers filled by `sprite::Billboard`.
+pub fn create_billboard_material(texture_path: &PathBuf) -> CustomMaterial {
+    CustomMaterial::new(
+        "billboard_material",
+        CustomShader::new(
+            "billboard",
+            Billboard::vertex_components(),
+            builtin_shader("billboard.vert", BILLBOARD_VERT),
+            builtin_shader("billboard.frag", BILLBOARD_FRAG),
+        ),
+        &[texture_path.clone()],
+    )
+}
+
+// For vertex buffers filled by `particles::ParticleEmitter`. The texture is
+// tinted by each particle's color.
+pub fn create_particle_material(texture_path: &PathBuf) -> CustomMaterial {
+    CustomMaterial::new(
+        "particle_material",
+        CustomShader::new(
+            "particle",
+            ParticleEmitter::vertex_components(),
+            builtin_shader("particle.vert", PARTICLE_VERT),
+            builtin_shader("particle.frag", PARTICLE_FRAG),
+        ),
+        &[texture_path.clone()],
+    )
+}
+
+// For vertex buffers filled by `ui::ScreenQuad`, e.g. text and UI panels.
+pub fn create_screen_material(texture_path: &PathBuf) -> CustomMaterial {
+    CustomMaterial::new(
+        "screen_material",
+        CustomShader::new(
+            "screen_texture",
+            ScreenQuad::vertex_components(),
+            builtin_shader("screen_texture.vert", SCREEN_TEXTURE_VERT),
+            builtin_shader("screen_texture.frag", SCREEN_TEXTURE_FRAG),
+        ),
+        &[texture_path.clone()],
+    )
+}
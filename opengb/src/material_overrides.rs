@@ -0,0 +1,91 @@
+use crate::config::read_key_values;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Replacements for the material of a sub-mesh, for working out what the
+// unknown fields of POL and CVD materials do.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaterialOverride {
+    // Replaces the diffuse texture
+    pub texture: Option<PathBuf>,
+    // Leaves the sub-mesh out, e.g. to find the one causing an artifact
+    pub hidden: bool,
+}
+
+// Sub-meshes are named by their diffuse texture, one
+// `texture_name key = value` line per setting, e.g.
+// `floor01.tga texture = debug/grid.dds` or `water02.tga hidden = true`.
+// Relative texture paths are
+// resolved against the directory of the file. Blank lines and lines
+// starting with '#' are ignored.
+pub struct MaterialOverrides {
+    overrides: BTreeMap<String, MaterialOverride>,
+}
+
+impl MaterialOverrides {
+    pub fn new() -> Self {
+        MaterialOverrides {
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut base_dir = path.as_ref().to_path_buf();
+        base_dir.pop();
+
+        let mut overrides = MaterialOverrides::new();
+        read_key_values(path, |key, value| {
+            let mut name_key = key.split_whitespace();
+            match (name_key.next(), name_key.next(), name_key.next()) {
+                (Some(name), Some("texture"), None) if !value.is_empty() => {
+                    overrides.entry(name).texture = Some(base_dir.join(value));
+                    true
+                }
+                (Some(name), Some("hidden"), None) => value
+                    .parse()
+                    .map(|hidden| overrides.entry(name).hidden = hidden)
+                    .is_ok(),
+                _ => false,
+            }
+        })?;
+
+        Ok(overrides)
+    }
+
+    pub fn entry(&mut self, texture_name: &str) -> &mut MaterialOverride {
+        self.overrides
+            .entry(texture_name.to_lowercase())
+            .or_insert_with(MaterialOverride::default)
+    }
+
+    pub fn for_texture(&self, texture_name: &str) -> Option<&MaterialOverride> {
+        self.overrides.get(&texture_name.to_lowercase())
+    }
+
+    pub fn is_hidden(&self, texture_name: &str) -> bool {
+        self.for_texture(texture_name)
+            .map(|o| o.hidden)
+            .unwrap_or(false)
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl std::fmt::Display for MaterialOverrides {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (name, o) in &self.overrides {
+            if let Some(texture) = &o.texture {
+                writeln!(f, "{} texture = {}", name, texture.display())?;
+            }
+
+            if o.hidden {
+                writeln!(f, "{} hidden = true", name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use crate::geometry::remap_indices;
+use crate::loaders::cvdloader::{CvdFile, CvdModel};
+use crate::loaders::mv3loader::{Mv3File, Mv3Model};
+use crate::loaders::polloader::PolFile;
+use std::collections::HashMap;
+
+// MV3 positions are fixed point
+pub const MV3_POSITION_SCALE: f32 = 0.01562;
+pub const MV3_TICKS_PER_SECOND: f32 = 4580.;
+
+// A triangle mesh using a single material, in the same form for every model
+// format. Texture coordinates are as sampled by the renderer, with the
+// origin at the top left of the texture. Animated formats give their first
+// frame; `mv3_model_to_flat_meshes` gives the others.
+#[derive(Debug, Clone)]
+pub struct FlatMesh {
+    pub texture_names: Vec<String>,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Option<Vec<[f32; 3]>>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub tex_coords2: Option<Vec<[f32; 2]>>,
+    pub indices: Vec<u32>,
+}
+
+impl FlatMesh {
+    // The diffuse texture is the last one. POL materials with a lightmap
+    // list it first, and sample the diffuse texture with the second set of
+    // texture coordinates.
+    pub fn diffuse_texture(&self) -> Option<&str> {
+        self.texture_names.last().map(|n| n.as_str())
+    }
+
+    pub fn diffuse_tex_coords(&self) -> &[[f32; 2]] {
+        match &self.tex_coords2 {
+            Some(tex_coords2) if self.texture_names.len() > 1 => tex_coords2,
+            _ => &self.tex_coords,
+        }
+    }
+
+    pub fn lightmap_tex_coords(&self) -> Option<&[[f32; 2]]> {
+        match &self.tex_coords2 {
+            Some(_) if self.texture_names.len() > 1 => Some(&self.tex_coords),
+            _ => None,
+        }
+    }
+}
+
+pub fn pol_to_flat_meshes(pol: &PolFile) -> Vec<FlatMesh> {
+    let mut meshes = vec![];
+    for mesh in &pol.meshes {
+        for material in &mesh.material_info {
+            let (indices, reversed_index) =
+                remap_indices(material.triangles.iter().map(|t| &t.indices));
+            let vertices: Vec<_> = reversed_index.iter().map(|&i| &mesh.vertices[i]).collect();
+            let has_normals = vertices.iter().all(|v| v.normal.is_some());
+            let has_tex_coords2 = vertices.iter().all(|v| v.tex_coord2.is_some());
+
+            meshes.push(FlatMesh {
+                texture_names: material.texture_names.clone(),
+                positions: vertices
+                    .iter()
+                    .map(|v| [v.position.x, v.position.y, v.position.z])
+                    .collect(),
+                normals: if has_normals && !vertices.is_empty() {
+                    Some(vertices.iter().map(|v| v.normal.unwrap()).collect())
+                } else {
+                    None
+                },
+                tex_coords: vertices
+                    .iter()
+                    .map(|v| [v.tex_coord.u, v.tex_coord.v])
+                    .collect(),
+                tex_coords2: if has_tex_coords2 && !vertices.is_empty() {
+                    Some(
+                        vertices
+                            .iter()
+                            .map(|v| {
+                                let t = v.tex_coord2.as_ref().unwrap();
+                                [t.u, t.v]
+                            })
+                            .collect(),
+                    )
+                } else {
+                    None
+                },
+                indices,
+            });
+        }
+    }
+
+    meshes
+}
+
+pub fn cvd_to_flat_meshes(cvd: &CvdFile) -> Vec<FlatMesh> {
+    let mut meshes = vec![];
+    for model in &cvd.models {
+        add_cvd_model(model, &mut meshes);
+    }
+
+    meshes
+}
+
+fn add_cvd_model(model: &CvdModel, meshes: &mut Vec<FlatMesh>) {
+    meshes.extend(cvd_model_to_flat_meshes(model));
+    if let Some(children) = &model.children {
+        for child in children {
+            add_cvd_model(child, meshes);
+        }
+    }
+}
+
+// The meshes of a single CVD model, leaving out its children
+pub fn cvd_model_to_flat_meshes(model: &CvdModel) -> Vec<FlatMesh> {
+    let mut meshes = vec![];
+    if let Some(frame) = model.mesh.frames.first() {
+        for material in &model.mesh.materials {
+            let (indices, reversed_index) =
+                remap_indices(material.triangles.iter().map(|t| &t.indices));
+            let vertices: Vec<_> = reversed_index.iter().map(|&i| &frame[i]).collect();
+            meshes.push(FlatMesh {
+                texture_names: vec![material.texture_name.clone()],
+                positions: vertices
+                    .iter()
+                    .map(|v| [v.position.x, v.position.y, v.position.z])
+                    .collect(),
+                normals: Some(
+                    vertices
+                        .iter()
+                        .map(|v| [v.normal.x, v.normal.y, v.normal.z])
+                        .collect(),
+                ),
+                tex_coords: vertices
+                    .iter()
+                    .map(|v| [v.tex_coord.x, v.tex_coord.y])
+                    .collect(),
+                tex_coords2: None,
+                indices,
+            });
+        }
+    }
+
+    meshes
+}
+
+pub fn mv3_to_flat_meshes(mv3: &Mv3File) -> Vec<FlatMesh> {
+    let texture_names = mv3_texture_names(mv3);
+    mv3.models
+        .iter()
+        .flat_map(|model| mv3_model_to_flat_meshes(model, &texture_names, 0))
+        .collect()
+}
+
+// The textures MV3 meshes are drawn with. The format doesn't say which
+// texture a mesh uses, so every mesh gets the first one, as in the game.
+pub fn mv3_texture_names(mv3: &Mv3File) -> Vec<String> {
+    mv3.textures
+        .iter()
+        .take(1)
+        .filter_map(|t| t.names.first())
+        .map(|n| String::from_utf8_lossy(n).into_owned())
+        .collect()
+}
+
+// The meshes of `model` posed at `frame`, or none if it has no such frame.
+// Vertices come in the same order for every frame, so that the frames can
+// be used as morph targets of each other.
+pub fn mv3_model_to_flat_meshes(
+    model: &Mv3Model,
+    texture_names: &[String],
+    frame: usize,
+) -> Vec<FlatMesh> {
+    let frame = match model.frames.get(frame) {
+        Some(frame) => frame,
+        None => return vec![],
+    };
+
+    let mut meshes = vec![];
+    for mesh in &model.meshes {
+        // Positions and texture coordinates are indexed separately
+        let mut index_map = HashMap::new();
+        let mut positions = vec![];
+        let mut tex_coords = vec![];
+        let mut indices = vec![];
+        for t in &mesh.triangles {
+            for (&i, &j) in t.indices.iter().zip(&t.texcoord_indices) {
+                let index = *index_map.entry((i, j)).or_insert_with(|| {
+                    let v = &frame.vertices[i as usize];
+                    let t = &model.texcoords[j as usize];
+                    positions.push([
+                        v.x as f32 * MV3_POSITION_SCALE,
+                        v.y as f32 * MV3_POSITION_SCALE,
+                        v.z as f32 * MV3_POSITION_SCALE,
+                    ]);
+                    tex_coords.push([t.u, -t.v]);
+                    positions.len() as u32 - 1
+                });
+                indices.push(index);
+            }
+        }
+
+        meshes.push(FlatMesh {
+            texture_names: texture_names.to_vec(),
+            positions,
+            normals: None,
+            tex_coords,
+            tex_coords2: None,
+            indices,
+        });
+    }
+
+    meshes
+}
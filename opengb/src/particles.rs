@@ -0,0 +1,198 @@
+use crate::sprite::Billboard;
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::{VertexBuffer, VertexComponents};
+
+#[derive(Debug, Clone)]
+pub struct EmitterDesc {
+    pub spawn_rate: f32,
+    pub max_particles: usize,
+    pub lifetime: (f32, f32),
+    pub velocity: Vec3,
+    pub velocity_spread: Vec3,
+    pub acceleration: Vec3,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub start_color: Vec3,
+    pub end_color: Vec3,
+}
+
+impl Default for EmitterDesc {
+    fn default() -> Self {
+        EmitterDesc {
+            spawn_rate: 20.,
+            max_particles: 64,
+            lifetime: (1., 2.),
+            velocity: Vec3::new(0., 50., 0.),
+            velocity_spread: Vec3::new(20., 10., 20.),
+            acceleration: Vec3::new(0., 0., 0.),
+            start_size: 20.,
+            end_size: 5.,
+            start_color: Vec3::new(1., 1., 1.),
+            end_color: Vec3::new(0., 0., 0.),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    lifetime: f32,
+}
+
+// A CPU simulated particle emitter, drawn as one billboard per particle.
+// Positions are local to the owning entity.
+pub struct ParticleEmitter {
+    desc: EmitterDesc,
+    particles: Vec<Particle>,
+    spawn_accumulator: f32,
+    emitting: bool,
+    rng: u32,
+}
+
+impl ParticleEmitter {
+    pub fn new(desc: EmitterDesc, seed: u32) -> Self {
+        ParticleEmitter {
+            particles: Vec::with_capacity(desc.max_particles),
+            desc,
+            spawn_accumulator: 0.,
+            emitting: true,
+            rng: seed.max(1),
+        }
+    }
+
+    pub fn desc(&self) -> &EmitterDesc {
+        &self.desc
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn start(&mut self) {
+        self.emitting = true;
+    }
+
+    // Stops spawning; live particles finish their lifetime.
+    pub fn stop(&mut self) {
+        self.emitting = false;
+        self.spawn_accumulator = 0.;
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.emitting || !self.particles.is_empty()
+    }
+
+    pub fn update(&mut self, delta_sec: f32) {
+        let acceleration = self.desc.acceleration;
+        for p in &mut self.particles {
+            p.age += delta_sec;
+            p.velocity = Vec3::new(
+                p.velocity.x + acceleration.x * delta_sec,
+                p.velocity.y + acceleration.y * delta_sec,
+                p.velocity.z + acceleration.z * delta_sec,
+            );
+            p.position = Vec3::new(
+                p.position.x + p.velocity.x * delta_sec,
+                p.position.y + p.velocity.y * delta_sec,
+                p.position.z + p.velocity.z * delta_sec,
+            );
+        }
+
+        self.particles.retain(|p| p.age < p.lifetime);
+
+        if self.emitting {
+            self.spawn_accumulator += delta_sec * self.desc.spawn_rate;
+            while self.spawn_accumulator >= 1. {
+                self.spawn_accumulator -= 1.;
+                if self.particles.len() < self.desc.max_particles {
+                    let particle = self.spawn();
+                    self.particles.push(particle);
+                }
+            }
+        }
+    }
+
+    pub fn vertex_components() -> VertexComponents {
+        Billboard::vertex_components() | VertexComponents::NORMAL
+    }
+
+    // The buffer holds a quad for each of `max_particles`. Unused quads are
+    // collapsed to zero size. The color over life is stored in NORMAL.
+    pub fn fill_vertex_buffer(&self, vertices: &mut VertexBuffer) {
+        let rect = (Vec2::new(0., 0.), Vec2::new(1., 1.));
+        for i in 0..self.desc.max_particles {
+            let (billboard, color) = match self.particles.get(i) {
+                Some(p) => {
+                    let t = (p.age / p.lifetime).min(1.);
+                    (
+                        Billboard {
+                            center: p.position,
+                            size: {
+                                let size = lerp(self.desc.start_size, self.desc.end_size, t);
+                                Vec2::new(size, size)
+                            },
+                        },
+                        Vec3::new(
+                            lerp(self.desc.start_color.x, self.desc.end_color.x, t),
+                            lerp(self.desc.start_color.y, self.desc.end_color.y, t),
+                            lerp(self.desc.start_color.z, self.desc.end_color.z, t),
+                        ),
+                    )
+                }
+                None => (
+                    Billboard {
+                        center: Vec3::new(0., 0., 0.),
+                        size: Vec2::new(0., 0.),
+                    },
+                    Vec3::new(0., 0., 0.),
+                ),
+            };
+
+            billboard.fill_vertex_buffer(vertices, i, rect);
+            for j in 0..4 {
+                vertices.set_component(i * 4 + j, VertexComponents::NORMAL, |c: &mut Vec3| {
+                    *c = color;
+                });
+            }
+        }
+    }
+
+    pub fn indices(&self) -> Vec<u32> {
+        Billboard::indices(self.desc.max_particles)
+    }
+
+    fn spawn(&mut self) -> Particle {
+        let lifetime = lerp(
+            self.desc.lifetime.0,
+            self.desc.lifetime.1,
+            self.next_random(),
+        );
+        let spread = self.desc.velocity_spread;
+        let velocity = Vec3::new(
+            self.desc.velocity.x + spread.x * (self.next_random() * 2. - 1.),
+            self.desc.velocity.y + spread.y * (self.next_random() * 2. - 1.),
+            self.desc.velocity.z + spread.z * (self.next_random() * 2. - 1.),
+        );
+
+        Particle {
+            position: Vec3::new(0., 0., 0.),
+            velocity,
+            age: 0.,
+            lifetime,
+        }
+    }
+
+    // xorshift32, in [0, 1)
+    fn next_random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a * (1. - t) + b * t
+}
@@ -0,0 +1,119 @@
+use crate::loaders::cvdloader::cvd_load_from_bytes;
+use crate::loaders::mv3loader::mv3_load_from_bytes;
+use crate::loaders::polloader::pol_load_from_bytes;
+use crate::model::{cvd_to_flat_meshes, mv3_to_flat_meshes, pol_to_flat_meshes, FlatMesh};
+use radiance::rendering::Material;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Parses a model format into flat meshes. Extensions are matched ignoring
+// case and given without the dot. Meshes are drawn with the registered
+// material factory named by `material`, if any.
+pub trait ModelLoader {
+    fn extensions(&self) -> &[&str];
+    fn load(&self, data: &[u8]) -> Result<Vec<FlatMesh>, Box<dyn Error>>;
+
+    fn material(&self) -> Option<&str> {
+        None
+    }
+}
+
+pub trait MaterialFactory {
+    fn create(&self, texture_paths: &[PathBuf]) -> Box<dyn Material>;
+}
+
+// Implemented by crates adding formats or materials, e.g. for the sister
+// games, so that they can be supported without changes to opengb.
+pub trait Plugin {
+    fn register(&self, registry: &mut PluginRegistry);
+}
+
+struct FnLoader {
+    extensions: Vec<&'static str>,
+    load: fn(&[u8]) -> Result<Vec<FlatMesh>, Box<dyn Error>>,
+}
+
+impl ModelLoader for FnLoader {
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+
+    fn load(&self, data: &[u8]) -> Result<Vec<FlatMesh>, Box<dyn Error>> {
+        (self.load)(data)
+    }
+}
+
+pub struct PluginRegistry {
+    loaders: Vec<Box<dyn ModelLoader>>,
+    materials: HashMap<String, Box<dyn MaterialFactory>>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        PluginRegistry {
+            loaders: vec![],
+            materials: HashMap::new(),
+        }
+    }
+
+    // A registry knowing the PAL3 model formats.
+    pub fn with_builtin() -> Self {
+        let mut registry = PluginRegistry::new();
+        registry.register_loader(Box::new(FnLoader {
+            extensions: vec!["pol"],
+            load: |data| Ok(pol_to_flat_meshes(&pol_load_from_bytes(data)?)),
+        }));
+        registry.register_loader(Box::new(FnLoader {
+            extensions: vec!["cvd"],
+            load: |data| Ok(cvd_to_flat_meshes(&cvd_load_from_bytes(data)?)),
+        }));
+        registry.register_loader(Box::new(FnLoader {
+            extensions: vec!["mv3"],
+            load: |data| Ok(mv3_to_flat_meshes(&mv3_load_from_bytes(data)?)),
+        }));
+
+        registry
+    }
+
+    pub fn add_plugin(&mut self, plugin: &dyn Plugin) {
+        plugin.register(self);
+    }
+
+    // Loaders registered later take precedence for the same extension.
+    pub fn register_loader(&mut self, loader: Box<dyn ModelLoader>) {
+        self.loaders.push(loader);
+    }
+
+    pub fn register_material(&mut self, name: &str, factory: Box<dyn MaterialFactory>) {
+        self.materials.insert(name.to_owned(), factory);
+    }
+
+    pub fn loader_for(&self, path: &Path) -> Option<&dyn ModelLoader> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        self.loaders
+            .iter()
+            .rev()
+            .find(|l| {
+                l.extensions()
+                    .iter()
+                    .any(|e| e.eq_ignore_ascii_case(&extension))
+            })
+            .map(|l| l.as_ref())
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FlatMesh>, Box<dyn Error>> {
+        let loader = self
+            .loader_for(path.as_ref())
+            .ok_or_else(|| format!("No loader for {:?}", path.as_ref()))?;
+        loader.load(&std::fs::read(path)?)
+    }
+
+    pub fn create_material(
+        &self,
+        name: &str,
+        texture_paths: &[PathBuf],
+    ) -> Option<Box<dyn Material>> {
+        self.materials.get(name).map(|f| f.create(texture_paths))
+    }
+}
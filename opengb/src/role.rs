@@ -0,0 +1,212 @@
+use radiance::math::Vec3;
+use std::collections::VecDeque;
+
+// Game units per second
+const WALK_SPEED: f32 = 80.;
+const RUN_SPEED: f32 = 200.;
+// Radians per second
+const TURN_SPEED: f32 = 4. * std::f32::consts::PI;
+// Targets closer than this count as reached
+const ARRIVE_DISTANCE: f32 = 1.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoleState {
+    Idle,
+    Walk,
+    Run,
+}
+
+// The MV3 action played in each state, by file stem in the role's
+// directory, e.g. ROLE/101/C01.mv3
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleActions {
+    pub idle: String,
+    pub walk: String,
+    pub run: String,
+}
+
+impl RoleActions {
+    pub fn new() -> Self {
+        RoleActions {
+            idle: "C01".to_owned(),
+            walk: "C02".to_owned(),
+            run: "C03".to_owned(),
+        }
+    }
+
+    pub fn action(&self, state: RoleState) -> &str {
+        match state {
+            RoleState::Idle => &self.idle,
+            RoleState::Walk => &self.walk,
+            RoleState::Run => &self.run,
+        }
+    }
+}
+
+// Scripted movement, as SCE role commands give it. Commands run one after
+// another; `y` of targets is ignored, as roles stay on the ground.
+#[derive(Debug, Clone, Copy)]
+pub enum RoleCommand {
+    MoveTo { target: Vec3, run: bool },
+    // Yaw in radians, 0 facing +z
+    FaceTo(f32),
+    Wait(f32),
+}
+
+// Moves a role across the scene from player input or scripted commands,
+// and tells which action it should be playing. Player input takes over
+// while it is held; scripted commands carry on once it is released.
+pub struct RoleController {
+    position: Vec3,
+    yaw: f32,
+    state: RoleState,
+    input: Option<(f32, f32, bool)>,
+    commands: VecDeque<RoleCommand>,
+    waited: f32,
+    walk_speed: f32,
+    run_speed: f32,
+}
+
+impl RoleController {
+    pub fn new(position: Vec3, yaw: f32) -> Self {
+        RoleController {
+            position,
+            yaw,
+            state: RoleState::Idle,
+            input: None,
+            commands: VecDeque::new(),
+            waited: 0.,
+            walk_speed: WALK_SPEED,
+            run_speed: RUN_SPEED,
+        }
+    }
+
+    pub fn with_speeds(mut self, walk_speed: f32, run_speed: f32) -> Self {
+        self.walk_speed = walk_speed;
+        self.run_speed = run_speed;
+        self
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn state(&self) -> RoleState {
+        self.state
+    }
+
+    // A direction on the ground, e.g. from a stick or the arrow keys turned
+    // by the camera. Zero lets go.
+    pub fn set_input(&mut self, x: f32, z: f32, run: bool) {
+        self.input = if x == 0. && z == 0. {
+            None
+        } else {
+            Some((x, z, run))
+        };
+    }
+
+    pub fn push(&mut self, command: RoleCommand) {
+        self.commands.push_back(command);
+    }
+
+    pub fn clear_commands(&mut self) {
+        self.commands.clear();
+        self.waited = 0.;
+    }
+
+    // Scripts moving the role wait while this is true
+    pub fn is_busy(&self) -> bool {
+        !self.commands.is_empty()
+    }
+
+    // Returns the new state when it changes, for the caller to switch
+    // actions
+    pub fn update(&mut self, delta_sec: f32) -> Option<RoleState> {
+        let state = match self.input {
+            Some((x, z, run)) => {
+                self.step(x, z, run, std::f32::MAX, delta_sec);
+                if run {
+                    RoleState::Run
+                } else {
+                    RoleState::Walk
+                }
+            }
+            None => self.run_command(delta_sec),
+        };
+
+        if state == self.state {
+            None
+        } else {
+            self.state = state;
+            Some(state)
+        }
+    }
+
+    fn run_command(&mut self, delta_sec: f32) -> RoleState {
+        let command = match self.commands.front() {
+            Some(command) => *command,
+            None => return RoleState::Idle,
+        };
+
+        match command {
+            RoleCommand::MoveTo { target, run } => {
+                let x = target.x - self.position.x;
+                let z = target.z - self.position.z;
+                let distance = (x * x + z * z).sqrt();
+                // Goes straight on with the next command, so that a role
+                // doesn't stop for a frame between points of a path
+                if distance <= ARRIVE_DISTANCE {
+                    self.commands.pop_front();
+                    return self.run_command(delta_sec);
+                }
+
+                self.step(x, z, run, distance, delta_sec);
+                if run {
+                    RoleState::Run
+                } else {
+                    RoleState::Walk
+                }
+            }
+            RoleCommand::FaceTo(yaw) => {
+                self.yaw = yaw;
+                self.commands.pop_front();
+                self.run_command(delta_sec)
+            }
+            RoleCommand::Wait(sec) => {
+                self.waited += delta_sec;
+                if self.waited >= sec {
+                    self.waited = 0.;
+                    self.commands.pop_front();
+                }
+                RoleState::Idle
+            }
+        }
+    }
+
+    // Moves along (x, z) by at most `max_distance`, turning towards it
+    fn step(&mut self, x: f32, z: f32, run: bool, max_distance: f32, delta_sec: f32) {
+        let length = (x * x + z * z).sqrt();
+        let speed = if run { self.run_speed } else { self.walk_speed };
+        let distance = (speed * delta_sec).min(max_distance);
+        self.position.x += x / length * distance;
+        self.position.z += z / length * distance;
+        self.yaw = turn_towards(self.yaw, x.atan2(z), TURN_SPEED * delta_sec);
+    }
+}
+
+// By the shorter way round
+fn turn_towards(from: f32, to: f32, max_step: f32) -> f32 {
+    let tau = 2. * std::f32::consts::PI;
+    let mut difference = (to - from) % tau;
+    if difference > std::f32::consts::PI {
+        difference -= tau;
+    } else if difference < -std::f32::consts::PI {
+        difference += tau;
+    }
+
+    from + difference.max(-max_step).min(max_step)
+}
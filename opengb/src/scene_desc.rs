@@ -0,0 +1,146 @@
+use crate::json::{json_string, JsonValue};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityDesc {
+    pub asset: PathBuf,
+    pub position: [f32; 3],
+    // Degrees around the y axis, which is how PAL3 places its props
+    pub rotation_y: f32,
+    pub material_overrides: Option<PathBuf>,
+}
+
+impl EntityDesc {
+    pub fn new<P: AsRef<Path>>(asset: P) -> Self {
+        EntityDesc {
+            asset: asset.as_ref().to_path_buf(),
+            position: [0., 0., 0.],
+            rotation_y: 0.,
+            material_overrides: None,
+        }
+    }
+}
+
+// An assembled scene as a list of assets and where they go, for custom
+// scenes and test fixtures, stored as JSON:
+//
+//     {"entities": [{"asset": "q01/q01.pol", "position": [0, 0, -500],
+//                    "rotation_y": 90, "material_overrides": "q01.mat"}]}
+//
+// Only "asset" is required. Relative paths are resolved against the
+// directory of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneDesc {
+    pub entities: Vec<EntityDesc>,
+}
+
+impl SceneDesc {
+    pub fn new() -> Self {
+        SceneDesc { entities: vec![] }
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let json = JsonValue::parse(&std::fs::read_to_string(&path)?)?;
+        let mut base_dir = path.as_ref().to_path_buf();
+        base_dir.pop();
+
+        let entities = json
+            .get("entities")
+            .and_then(|e| e.as_array())
+            .ok_or("no entities array")?;
+        let mut scene = SceneDesc::new();
+        for (i, value) in entities.iter().enumerate() {
+            let asset = value
+                .get("asset")
+                .and_then(|a| a.as_str())
+                .ok_or_else(|| format!("entity {}: no asset", i))?;
+            let mut entity = EntityDesc::new(base_dir.join(asset));
+            if let Some(position) = value.get("position") {
+                let xyz: Vec<f32> = position
+                    .as_array()
+                    .unwrap_or(&[])
+                    .iter()
+                    .filter_map(|c| c.as_f64())
+                    .map(|c| c as f32)
+                    .collect();
+                if xyz.len() != 3 {
+                    return Err(format!("entity {}: expected 3 numbers in position", i).into());
+                }
+
+                entity.position = [xyz[0], xyz[1], xyz[2]];
+            }
+
+            if let Some(rotation_y) = value.get("rotation_y") {
+                entity.rotation_y = rotation_y
+                    .as_f64()
+                    .ok_or_else(|| format!("entity {}: rotation_y is not a number", i))?
+                    as f32;
+            }
+
+            if let Some(overrides) = value.get("material_overrides") {
+                let overrides = overrides
+                    .as_str()
+                    .ok_or_else(|| format!("entity {}: material_overrides is not a path", i))?;
+                entity.material_overrides = Some(base_dir.join(overrides));
+            }
+
+            scene.entities.push(entity);
+        }
+
+        Ok(scene)
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl std::fmt::Display for SceneDesc {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{{")?;
+        writeln!(f, "  \"entities\": [")?;
+        for (i, entity) in self.entities.iter().enumerate() {
+            let mut fields = vec![
+                format!(
+                    "\"asset\": {}",
+                    json_string(&entity.asset.to_string_lossy())
+                ),
+                format!(
+                    "\"position\": [{}, {}, {}]",
+                    json_number(entity.position[0]),
+                    json_number(entity.position[1]),
+                    json_number(entity.position[2])
+                ),
+            ];
+            if entity.rotation_y != 0. {
+                fields.push(format!(
+                    "\"rotation_y\": {}",
+                    json_number(entity.rotation_y)
+                ));
+            }
+
+            if let Some(overrides) = &entity.material_overrides {
+                fields.push(format!(
+                    "\"material_overrides\": {}",
+                    json_string(&overrides.to_string_lossy())
+                ));
+            }
+
+            let separator = if i + 1 < self.entities.len() { "," } else { "" };
+            writeln!(f, "    {{ {} }}{}", fields.join(", "), separator)?;
+        }
+
+        writeln!(f, "  ]")?;
+        writeln!(f, "}}")
+    }
+}
+
+// JSON has no NaN or infinity
+fn json_number(n: f32) -> String {
+    if n.is_finite() {
+        n.to_string()
+    } else {
+        "0".to_owned()
+    }
+}
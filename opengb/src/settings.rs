@@ -0,0 +1,59 @@
+use crate::cache::MemoryBudgets;
+use crate::config::key_value_lines;
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphicsProfile {
+    Normal,
+    Low,
+}
+
+// Rendering options derived from a profile. The low profile targets
+// integrated GPUs and machines with little RAM: smaller textures, and
+// lightmaps and lighting turned off. Both keep the memory caches off.
+#[derive(Debug, Clone)]
+pub struct GraphicsSettings {
+    pub profile: GraphicsProfile,
+    pub lightmaps: bool,
+    pub lit_materials: bool,
+    // 0 keeps textures at their original size
+    pub max_texture_size: u32,
+    pub memory_budgets: MemoryBudgets,
+}
+
+impl GraphicsSettings {
+    pub fn for_profile(profile: GraphicsProfile) -> Self {
+        match profile {
+            GraphicsProfile::Normal => GraphicsSettings {
+                profile,
+                lightmaps: true,
+                lit_materials: true,
+                max_texture_size: 0,
+                memory_budgets: MemoryBudgets::default(),
+            },
+            GraphicsProfile::Low => GraphicsSettings {
+                profile,
+                lightmaps: false,
+                lit_materials: false,
+                max_texture_size: 256,
+                memory_budgets: MemoryBudgets::default(),
+            },
+        }
+    }
+
+    // Reads `profile = normal|low` from a key = value file.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(&path)?;
+        let mut profile = GraphicsProfile::Normal;
+        for line in key_value_lines(&text).filter(|l| l.key == "profile") {
+            profile = match line.value {
+                Some("normal") => GraphicsProfile::Normal,
+                Some("low") => GraphicsProfile::Low,
+                value => return Err(format!("unknown graphics profile {:?}", value).into()),
+            };
+        }
+
+        Ok(GraphicsSettings::for_profile(profile))
+    }
+}
@@ -0,0 +1,179 @@
+use crate::config::read_key_values;
+use crate::material::CustomShader;
+use radiance::rendering::VertexComponents;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const SPIRV_MAGIC: u32 = 0x07230203;
+
+pub fn validate_spirv(name: &str, bytes: &[u8]) -> Result<(), String> {
+    if bytes.len() < 20 || bytes.len() % 4 != 0 {
+        return Err(format!(
+            "{}: not a SPIR-V module (size {})",
+            name,
+            bytes.len()
+        ));
+    }
+
+    let magic = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    if magic != SPIRV_MAGIC {
+        return Err(format!("{}: bad SPIR-V magic {:#x}", name, magic));
+    }
+
+    Ok(())
+}
+
+const OP_SPEC_CONSTANT: u32 = 50;
+const OP_DECORATE: u32 = 71;
+const DECORATION_SPEC_ID: u32 = 1;
+
+// Replaces the default values of 32-bit specialization constants, given
+// as (constant_id, bits) pairs. As radiance creates pipelines without
+// specialization info, the defaults are what the shader runs with.
+pub fn set_spec_constants(spirv: &[u8], values: &[(u32, u32)]) -> Vec<u8> {
+    let mut words: Vec<u32> = spirv
+        .chunks_exact(4)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+        .collect();
+
+    let mut spec_ids = HashMap::new();
+    let mut constants = vec![];
+    let mut i = 5;
+    while i < words.len() {
+        let word_count = (words[i] >> 16) as usize;
+        let opcode = words[i] & 0xffff;
+        if word_count == 0 || i + word_count > words.len() {
+            break;
+        }
+
+        if opcode == OP_DECORATE && word_count == 4 && words[i + 2] == DECORATION_SPEC_ID {
+            spec_ids.insert(words[i + 1], words[i + 3]);
+        } else if opcode == OP_SPEC_CONSTANT && word_count == 4 {
+            constants.push((words[i + 2], i + 3));
+        }
+
+        i += word_count;
+    }
+
+    for (result_id, value_index) in constants {
+        let value = spec_ids
+            .get(&result_id)
+            .and_then(|id| values.iter().find(|(v, _)| v == id));
+        if let Some((_, bits)) = value {
+            words[value_index] = *bits;
+        }
+    }
+
+    words.iter().flat_map(|w| w.to_le_bytes().to_vec()).collect()
+}
+
+struct ShaderVariant {
+    vert_src: Vec<u8>,
+    frag_src: Vec<u8>,
+}
+
+// Shader variants supplied at runtime (by scene data or mods) rather than
+// built into opengb. Lookups of unknown or invalid variants fail so that
+// callers can fall back to the default material. A variant must accept
+// the vertex layout of the meshes it is applied to.
+pub struct ShaderRegistry {
+    variants: HashMap<String, ShaderVariant>,
+}
+
+impl ShaderRegistry {
+    pub fn new() -> Self {
+        ShaderRegistry {
+            variants: HashMap::new(),
+        }
+    }
+
+    pub fn register(
+        &mut self,
+        name: &str,
+        vert_src: Vec<u8>,
+        frag_src: Vec<u8>,
+    ) -> Result<(), Box<dyn Error>> {
+        validate_spirv(&format!("{}.vert", name), &vert_src)?;
+        validate_spirv(&format!("{}.frag", name), &frag_src)?;
+        self.variants
+            .insert(name.to_owned(), ShaderVariant { vert_src, frag_src });
+
+        Ok(())
+    }
+
+    // Loads `<dir>/<name>.vert.spv` and `<dir>/<name>.frag.spv`.
+    pub fn load_from_dir<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+        name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let vert_src = std::fs::read(dir.as_ref().join(format!("{}.vert.spv", name)))?;
+        let frag_src = std::fs::read(dir.as_ref().join(format!("{}.frag.spv", name)))?;
+        self.register(name, vert_src, frag_src)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.variants.contains_key(name)
+    }
+
+    pub fn create_shader(
+        &self,
+        name: &str,
+        vertex_components: VertexComponents,
+    ) -> Option<CustomShader> {
+        self.variants.get(name).map(|v| {
+            CustomShader::new(
+                name,
+                vertex_components,
+                v.vert_src.clone(),
+                v.frag_src.clone(),
+            )
+        })
+    }
+}
+
+// Maps mesh names to shader variants, one `mesh_name = shader_name` pair
+// per line. Blank lines and lines starting with '#' are ignored.
+pub struct ShaderOverrides {
+    shader_dir: PathBuf,
+    overrides: HashMap<String, String>,
+}
+
+impl ShaderOverrides {
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut overrides = HashMap::new();
+        read_key_values(&path, |mesh, shader| {
+            overrides.insert(mesh.to_lowercase(), shader.to_owned());
+            true
+        })?;
+
+        let mut shader_dir = path.as_ref().to_path_buf();
+        shader_dir.pop();
+        Ok(ShaderOverrides {
+            shader_dir,
+            overrides,
+        })
+    }
+
+    pub fn shader_for(&self, mesh_name: &str) -> Option<&str> {
+        self.overrides
+            .get(&mesh_name.to_lowercase())
+            .map(|s| s.as_str())
+    }
+
+    // Registers every referenced variant from the overrides file's directory.
+    // Variants that fail to load are reported and left out, so their meshes
+    // keep the default material.
+    pub fn load_shaders(&self, registry: &mut ShaderRegistry) {
+        for shader in self.overrides.values() {
+            if registry.contains(shader) {
+                continue;
+            }
+
+            if let Err(e) = registry.load_from_dir(&self.shader_dir, shader) {
+                println!("Unable to load shader variant {}: {}", shader, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::shader_registry::validate_spirv;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+pub const SHADER_SOURCE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders");
+
+// The variants build.rs compiles from each source, besides the source
+// itself: (source, output, defines)
+const VARIANTS: &[(&str, &str, &[&str])] = &[
+    (
+        "lightmap_texture.frag",
+        "lightmap_only.frag",
+        &["LIGHTMAP_MODE=1"],
+    ),
+    (
+        "lightmap_texture.frag",
+        "diffuse_only.frag",
+        &["LIGHTMAP_MODE=2"],
+    ),
+    (
+        "lightmap_texture.vert",
+        "lightmap_vertex_color.vert",
+        &["VERTEX_COLOR"],
+    ),
+    (
+        "lightmap_texture.frag",
+        "lightmap_vertex_color.frag",
+        &["VERTEX_COLOR"],
+    ),
+    ("particle.frag", "screen_texture.frag", &[]),
+];
+
+thread_local! {
+    static RELOADED_SHADERS: RefCell<HashMap<String, Vec<u8>>> = RefCell::new(HashMap::new());
+}
+
+// The latest SPIR-V compiled by a watcher on this thread for a built-in
+// shader, e.g. "lit_texture.frag"
+pub fn reloaded_shader(name: &str) -> Option<Vec<u8>> {
+    RELOADED_SHADERS.with(|shaders| shaders.borrow().get(name).cloned())
+}
+
+// Watches the GLSL sources of the built-in shaders and recompiles them with
+// glslc when they change. Materials created afterwards on the same thread
+// use the new SPIR-V; existing ones have to be recreated. Compile errors
+// are printed and the previous shader stays in use.
+pub struct ShaderWatcher {
+    src_dir: PathBuf,
+    out_dir: PathBuf,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl ShaderWatcher {
+    pub fn new<P: AsRef<Path>>(src_dir: P) -> Self {
+        let mut watcher = ShaderWatcher {
+            src_dir: src_dir.as_ref().to_path_buf(),
+            out_dir: std::env::temp_dir().join("opengb_shaders"),
+            modified: HashMap::new(),
+        };
+
+        watcher.changed_sources();
+        watcher
+    }
+
+    // Returns true when at least one shader was recompiled
+    pub fn poll(&mut self) -> bool {
+        let mut reloaded = false;
+        for source in self.changed_sources() {
+            let file_name = match source.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => continue,
+            };
+
+            let mut outputs = vec![(file_name.clone(), vec![])];
+            for (variant_source, output, defines) in VARIANTS {
+                if *variant_source == file_name {
+                    outputs.push((output.to_string(), defines.to_vec()));
+                }
+            }
+
+            for (output, defines) in outputs {
+                match self.compile(&source, &output, &defines) {
+                    Ok(spirv) => {
+                        println!("Reloaded shader {}", output);
+                        RELOADED_SHADERS.with(|shaders| shaders.borrow_mut().insert(output, spirv));
+                        reloaded = true;
+                    }
+                    Err(e) => println!("Unable to reload shader {}: {}", output, e),
+                }
+            }
+        }
+
+        reloaded
+    }
+
+    fn changed_sources(&mut self) -> Vec<PathBuf> {
+        let entries = match std::fs::read_dir(&self.src_dir) {
+            Ok(entries) => entries,
+            Err(_) => return vec![],
+        };
+
+        let mut changed = vec![];
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let modified = match entry.metadata().and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+
+            if self.modified.insert(path.clone(), modified) != Some(modified) {
+                changed.push(path);
+            }
+        }
+
+        changed
+    }
+
+    fn compile(&self, source: &Path, output: &str, defines: &[&str]) -> Result<Vec<u8>, String> {
+        std::fs::create_dir_all(&self.out_dir).map_err(|e| e.to_string())?;
+        let out_path = self.out_dir.join(format!("{}.spv", output));
+        let result = Command::new("glslc")
+            .args(defines.iter().map(|d| format!("-D{}", d)))
+            .arg(source)
+            .arg("-o")
+            .arg(&out_path)
+            .output()
+            .map_err(|e| format!("unable to run glslc: {}", e))?;
+
+        if !result.status.success() {
+            return Err(String::from_utf8_lossy(&result.stderr).into_owned());
+        }
+
+        let spirv = std::fs::read(&out_path).map_err(|e| e.to_string())?;
+        validate_spirv(output, &spirv)?;
+        Ok(spirv)
+    }
+}
@@ -1,13 +1,39 @@
 #version 450
 #extension GL_ARB_separate_shader_objects : enable
 
+// LIGHTMAP_MODE: 0 = lightmap modulates diffuse, 1 = lightmap only, 2 = diffuse only
+#ifndef LIGHTMAP_MODE
+#define LIGHTMAP_MODE 0
+#endif
+
 layout(set = 1, binding = 0) uniform sampler2D texSampler[2];
 
 layout(location = 0) in vec2 fragTexCoord;
 layout(location = 1) in vec2 fragTexCoord2;
+layout(location = 2) in float fragDistance;
+#ifdef VERTEX_COLOR
+layout(location = 3) in vec3 fragColor;
+#endif
 
 layout(location = 0) out vec4 outColor;
 
+// Linear fog, disabled while fogEnd <= fogStart. The values are patched
+// into the SPIR-V per scene, see opengb::fog.
+layout(constant_id = 0) const float fogStart = 0.0;
+layout(constant_id = 1) const float fogEnd = 0.0;
+layout(constant_id = 2) const float fogRed = 0.0;
+layout(constant_id = 3) const float fogGreen = 0.0;
+layout(constant_id = 4) const float fogBlue = 0.0;
+
+vec4 applyFog(vec4 color) {
+    if (fogEnd <= fogStart) {
+        return color;
+    }
+
+    float f = clamp((fogEnd - fragDistance) / (fogEnd - fogStart), 0.0, 1.0);
+    return vec4(mix(vec3(fogRed, fogGreen, fogBlue), color.rgb, f), color.a);
+}
+
 void main() {
     vec4 lightMap = texture(texSampler[0], fragTexCoord);
     vec4 color = texture(texSampler[1], fragTexCoord2);
@@ -15,5 +41,16 @@ void main() {
         discard;
     }
 
-    outColor = (lightMap * 1.5 + 0.15) * color;
+#ifdef VERTEX_COLOR
+    color.rgb *= fragColor;
+#endif
+
+#if LIGHTMAP_MODE == 1
+    outColor = vec4(lightMap.rgb, 1.0);
+#elif LIGHTMAP_MODE == 2
+    outColor = applyFog(color);
+#else
+    // Same as D3DTOP_MODULATE2X used by the original renderer
+    outColor = applyFog(vec4(clamp(lightMap.rgb * color.rgb * 2.0, 0.0, 1.0), color.a));
+#endif
 }
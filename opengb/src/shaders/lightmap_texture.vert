@@ -8,11 +8,18 @@ layout(binding = 0) uniform UniformBufferObject {
 } mvp;
 
 layout(location = 0) in vec3 position;
+#ifdef VERTEX_COLOR
+layout(location = 1) in vec3 inColor;
+#endif
 layout(location = 2) in vec2 inTexCoord;
 layout(location = 3) in vec2 inTexCoord2;
 
 layout(location = 0) out vec2 fragTexCoord;
 layout(location = 1) out vec2 fragTexCoord2;
+layout(location = 2) out float fragDistance;
+#ifdef VERTEX_COLOR
+layout(location = 3) out vec3 fragColor;
+#endif
 
 mat4 clip = mat4(vec4(1.0, 0.0, 0.0, 0.0),
                  vec4(0.0, -1.0, 0.0, 0.0),
@@ -20,8 +27,13 @@ mat4 clip = mat4(vec4(1.0, 0.0, 0.0, 0.0),
                  vec4(0.0, 0.0, 0, 1.0));
 
 void main() {
-    gl_Position = vec4(position, 1.0) * mvp.model * mvp.view * mvp.proj * clip;
+    vec4 viewPosition = vec4(position, 1.0) * mvp.model * mvp.view;
+    gl_Position = viewPosition * mvp.proj * clip;
+    fragDistance = length(viewPosition.xyz);
 
     fragTexCoord = inTexCoord;
     fragTexCoord2 = inTexCoord2;
+#ifdef VERTEX_COLOR
+    fragColor = inColor;
+#endif
 }
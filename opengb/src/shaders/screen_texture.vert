@@ -0,0 +1,17 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 inColor;
+layout(location = 2) in vec2 inTexCoord;
+
+layout(location = 0) out vec2 fragTexCoord;
+layout(location = 1) out vec3 fragColor;
+
+void main() {
+    // Positions are already in normalized device coordinates
+    gl_Position = vec4(position, 1.0);
+
+    fragTexCoord = inTexCoord;
+    fragColor = inColor;
+}
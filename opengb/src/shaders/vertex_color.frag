@@ -0,0 +1,36 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(set = 1, binding = 0) uniform sampler2D texSampler;
+
+layout(location = 0) in vec2 fragTexCoord;
+layout(location = 1) in vec3 fragColor;
+layout(location = 2) in float fragDistance;
+
+layout(location = 0) out vec4 outColor;
+
+// Linear fog, disabled while fogEnd <= fogStart. The values are patched
+// into the SPIR-V per scene, see opengb::fog.
+layout(constant_id = 0) const float fogStart = 0.0;
+layout(constant_id = 1) const float fogEnd = 0.0;
+layout(constant_id = 2) const float fogRed = 0.0;
+layout(constant_id = 3) const float fogGreen = 0.0;
+layout(constant_id = 4) const float fogBlue = 0.0;
+
+vec4 applyFog(vec4 color) {
+    if (fogEnd <= fogStart) {
+        return color;
+    }
+
+    float f = clamp((fogEnd - fragDistance) / (fogEnd - fogStart), 0.0, 1.0);
+    return vec4(mix(vec3(fogRed, fogGreen, fogBlue), color.rgb, f), color.a);
+}
+
+void main() {
+    vec4 color = texture(texSampler, fragTexCoord);
+    if (color.a == 0.0) {
+        discard;
+    }
+
+    outColor = applyFog(vec4(color.rgb * fragColor, color.a));
+}
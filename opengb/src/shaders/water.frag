@@ -0,0 +1,38 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(set = 1, binding = 0) uniform sampler2D texSampler;
+
+layout(location = 0) in vec2 fragTexCoord;
+layout(location = 1) in vec2 fragRipple;
+layout(location = 2) in float fragDistance;
+
+layout(location = 0) out vec4 outColor;
+
+layout(constant_id = 0) const float fogStart = 0.0;
+layout(constant_id = 1) const float fogEnd = 0.0;
+layout(constant_id = 2) const float fogRed = 0.0;
+layout(constant_id = 3) const float fogGreen = 0.0;
+layout(constant_id = 4) const float fogBlue = 0.0;
+
+// Patched per surface, see opengb::water
+layout(constant_id = 5) const float waterAlpha = 0.7;
+layout(constant_id = 6) const float waterDistortion = 0.02;
+
+vec4 applyFog(vec4 color) {
+    if (fogEnd <= fogStart) {
+        return color;
+    }
+
+    float f = clamp((fogEnd - fragDistance) / (fogEnd - fogStart), 0.0, 1.0);
+    return vec4(mix(vec3(fogRed, fogGreen, fogBlue), color.rgb, f), color.a);
+}
+
+const float TWO_PI = 6.2831853;
+
+void main() {
+    // The ripple coordinates move over time, which makes the offsets wave
+    vec2 offset = vec2(sin(fragRipple.y * TWO_PI), cos(fragRipple.x * TWO_PI)) * waterDistortion;
+    vec4 color = texture(texSampler, fragTexCoord + offset);
+    outColor = applyFog(vec4(color.rgb, color.a * waterAlpha));
+}
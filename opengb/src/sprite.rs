@@ -0,0 +1,164 @@
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::{VertexBuffer, VertexComponents};
+
+// A texture split into a grid of equally sized frames, numbered row by row
+// from the top left.
+#[derive(Debug, Clone, Copy)]
+pub struct SpriteAtlas {
+    pub columns: u32,
+    pub rows: u32,
+    pub frame_count: u32,
+}
+
+impl SpriteAtlas {
+    pub fn new(columns: u32, rows: u32) -> Self {
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+        SpriteAtlas {
+            columns,
+            rows,
+            frame_count: columns * rows,
+        }
+    }
+
+    // For atlases whose last row is not full.
+    pub fn with_frame_count(mut self, frame_count: u32) -> Self {
+        self.frame_count = frame_count.max(1).min(self.columns * self.rows);
+        self
+    }
+
+    // Returns the top left and bottom right texture coordinates of `frame`.
+    pub fn frame_rect(&self, frame: u32) -> (Vec2, Vec2) {
+        let frame = frame % self.frame_count;
+        let column = (frame % self.columns) as f32;
+        let row = (frame / self.columns) as f32;
+        let width = 1. / self.columns as f32;
+        let height = 1. / self.rows as f32;
+        (
+            Vec2::new(column * width, row * height),
+            Vec2::new((column + 1.) * width, (row + 1.) * height),
+        )
+    }
+}
+
+// Plays the frames of an atlas at a fixed rate. Entities add it as a
+// component and refresh their texture coordinates when `update` reports a
+// new frame.
+pub struct SpriteAnimation {
+    atlas: SpriteAtlas,
+    frames_per_second: f32,
+    looping: bool,
+    time: f32,
+    frame: u32,
+}
+
+impl SpriteAnimation {
+    pub fn new(atlas: SpriteAtlas, frames_per_second: f32, looping: bool) -> Self {
+        SpriteAnimation {
+            atlas,
+            frames_per_second: frames_per_second.max(0.),
+            looping,
+            time: 0.,
+            frame: 0,
+        }
+    }
+
+    pub fn atlas(&self) -> &SpriteAtlas {
+        &self.atlas
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.frame == self.atlas.frame_count - 1
+    }
+
+    pub fn update(&mut self, delta_sec: f32) -> Option<u32> {
+        self.time += delta_sec;
+        let elapsed_frames = (self.time * self.frames_per_second) as u32;
+        let frame = if self.looping {
+            elapsed_frames % self.atlas.frame_count
+        } else {
+            elapsed_frames.min(self.atlas.frame_count - 1)
+        };
+
+        if frame == self.frame {
+            None
+        } else {
+            self.frame = frame;
+            Some(frame)
+        }
+    }
+}
+
+// A camera facing quad. Its vertices all store the quad center as the
+// position and their corner offset in TEXCOORD2, and the billboard shader
+// expands them in view space.
+#[derive(Debug, Clone, Copy)]
+pub struct Billboard {
+    pub center: Vec3,
+    pub size: Vec2,
+}
+
+impl Billboard {
+    pub fn vertex_components() -> VertexComponents {
+        VertexComponents::POSITION | VertexComponents::TEXCOORD | VertexComponents::TEXCOORD2
+    }
+
+    // Writes the 4 vertices of the billboard numbered `index` in `vertices`.
+    pub fn fill_vertex_buffer(
+        &self,
+        vertices: &mut VertexBuffer,
+        index: usize,
+        rect: (Vec2, Vec2),
+    ) {
+        let (half_width, half_height) = (self.size.x / 2., self.size.y / 2.);
+        let corners = [
+            Vec2::new(-half_width, -half_height),
+            Vec2::new(half_width, -half_height),
+            Vec2::new(half_width, half_height),
+            Vec2::new(-half_width, half_height),
+        ];
+
+        let tex_coords = quad_tex_coords(rect);
+        for i in 0..4 {
+            vertices.set_data(
+                index * 4 + i,
+                Some(&self.center),
+                None,
+                Some(&tex_coords[i]),
+                Some(&corners[i]),
+            );
+        }
+    }
+
+    // Only updates the texture coordinates, e.g. when a sprite changes frames.
+    pub fn set_frame(vertices: &mut VertexBuffer, index: usize, rect: (Vec2, Vec2)) {
+        let tex_coords = quad_tex_coords(rect);
+        for i in 0..4 {
+            vertices.set_component(index * 4 + i, VertexComponents::TEXCOORD, |t: &mut Vec2| {
+                *t = tex_coords[i];
+            });
+        }
+    }
+
+    pub fn indices(count: usize) -> Vec<u32> {
+        (0..count as u32)
+            .flat_map(|i| {
+                let b = i * 4;
+                vec![b, b + 1, b + 2, b, b + 2, b + 3]
+            })
+            .collect()
+    }
+}
+
+fn quad_tex_coords((min, max): (Vec2, Vec2)) -> [Vec2; 4] {
+    [
+        Vec2::new(min.x, max.y),
+        Vec2::new(max.x, max.y),
+        Vec2::new(max.x, min.y),
+        Vec2::new(min.x, min.y),
+    ]
+}
@@ -0,0 +1,131 @@
+use crate::config::read_key_values;
+use crate::sprite::SpriteAtlas;
+use radiance::math::Vec2;
+use radiance::rendering::{VertexBuffer, VertexComponents};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+// Material-level texture animation, applied by rewriting the texture
+// coordinates of a mesh every frame.
+#[derive(Debug, Clone)]
+pub enum TextureAnimation {
+    // In texture sizes per second, for water, waterfalls and lava. Needs a
+    // texture sampled with wrapping.
+    Scroll {
+        speed: [f32; 2],
+    },
+    // Plays `frames` of an atlas, or all of them when empty. Texture
+    // coordinates must stay within 0..1 to land inside one frame.
+    Frames {
+        atlas: SpriteAtlas,
+        frames: Vec<u32>,
+        frames_per_second: f32,
+    },
+}
+
+impl TextureAnimation {
+    // Parses `scroll <u> <v>` or `frames <columns>x<rows> <fps> [frame]...`
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut words = value.split_whitespace();
+        match words.next()? {
+            "scroll" => {
+                let u = words.next()?.parse().ok()?;
+                let v = words.next()?.parse().ok()?;
+                Some(TextureAnimation::Scroll { speed: [u, v] })
+            }
+            "frames" => {
+                let mut grid = words.next()?.splitn(2, 'x');
+                let columns = grid.next()?.parse().ok()?;
+                let rows = grid.next()?.parse().ok()?;
+                let frames_per_second = words.next()?.parse().ok()?;
+                let frames: Result<Vec<u32>, _> = words.map(|w| w.parse()).collect();
+                Some(TextureAnimation::Frames {
+                    atlas: SpriteAtlas::new(columns, rows),
+                    frames: frames.ok()?,
+                    frames_per_second,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn tex_coord_at(&self, tex_coord: &Vec2, time: f32) -> Vec2 {
+        match self {
+            TextureAnimation::Scroll { speed } => Vec2::new(
+                tex_coord.x + (speed[0] * time).fract(),
+                tex_coord.y + (speed[1] * time).fract(),
+            ),
+            TextureAnimation::Frames {
+                atlas,
+                frames,
+                frames_per_second,
+            } => {
+                let step = (time * frames_per_second).max(0.) as u32;
+                let frame = if frames.is_empty() {
+                    step % atlas.frame_count
+                } else {
+                    frames[step as usize % frames.len()]
+                };
+
+                let (top_left, bottom_right) = atlas.frame_rect(frame);
+                Vec2::new(
+                    top_left.x + tex_coord.x * (bottom_right.x - top_left.x),
+                    top_left.y + tex_coord.y * (bottom_right.y - top_left.y),
+                )
+            }
+        }
+    }
+
+    // Writes the animated `base_tex_coords` to `component`, TEXCOORD or
+    // TEXCOORD2 depending on which one the animated texture is sampled with.
+    pub fn fill_vertex_buffer(
+        &self,
+        vertices: &mut VertexBuffer,
+        component: VertexComponents,
+        base_tex_coords: &[Vec2],
+        time: f32,
+    ) {
+        for (i, tex_coord) in base_tex_coords.iter().enumerate() {
+            let animated = self.tex_coord_at(tex_coord, time);
+            vertices.set_component(i, component, |t: &mut Vec2| {
+                *t = Vec2::new(animated.x, animated.y);
+            });
+        }
+    }
+}
+
+// Maps texture names to animations, one `texture_name = animation` pair per
+// line as accepted by `TextureAnimation::parse`. Blank lines and lines
+// starting with '#' are ignored.
+pub struct TextureAnimations {
+    animations: HashMap<String, TextureAnimation>,
+}
+
+impl TextureAnimations {
+    pub fn new() -> Self {
+        TextureAnimations {
+            animations: HashMap::new(),
+        }
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut animations = TextureAnimations::new();
+        read_key_values(path, |name, value| {
+            TextureAnimation::parse(value)
+                .map(|animation| animations.insert(name, animation))
+                .is_some()
+        })?;
+
+        Ok(animations)
+    }
+
+    pub fn insert(&mut self, texture_name: &str, animation: TextureAnimation) {
+        self.animations
+            .insert(texture_name.to_lowercase(), animation);
+    }
+
+    pub fn for_texture(&self, texture_name: &str) -> Option<&TextureAnimation> {
+        self.animations.get(&texture_name.to_lowercase())
+    }
+}
@@ -0,0 +1,154 @@
+use crate::cache::{BudgetedCache, CacheReport, EvictionPolicy};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use image::RgbaImage;
+use std::cell::RefCell;
+use std::error::Error;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+const BLOB_MAGIC: &[u8; 8] = b"OGBTEX01";
+
+thread_local! {
+    static CACHE_DIR: RefCell<Option<PathBuf>> = RefCell::new(None);
+    static MEMORY_CACHE: RefCell<Option<BudgetedCache<(PathBuf, u32), RgbaImage>>> =
+        RefCell::new(None);
+}
+
+// Materials created afterwards on this thread keep their decoded textures
+// in `dir`, so that later launches skip DDS and TGA decoding. None turns the
+// cache off, which is the default.
+pub fn set_texture_cache_dir(dir: Option<PathBuf>) {
+    if let Some(dir) = &dir {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            println!("Unable to create texture cache {:?}: {}", dir, e);
+        }
+    }
+
+    CACHE_DIR.with(|cache_dir| *cache_dir.borrow_mut() = dir);
+}
+
+pub fn texture_cache_dir() -> Option<PathBuf> {
+    CACHE_DIR.with(|cache_dir| cache_dir.borrow().clone())
+}
+
+// Keeps up to `budget` bytes of decoded textures in memory for the
+// materials created afterwards on this thread, so that models sharing
+// textures decode them once. 0 turns it off, which is the default.
+pub fn set_texture_memory_budget(budget: usize, policy: EvictionPolicy) {
+    let cache = if budget > 0 {
+        Some(BudgetedCache::new("textures", budget, policy))
+    } else {
+        None
+    };
+
+    MEMORY_CACHE.with(|memory_cache| *memory_cache.borrow_mut() = cache);
+}
+
+pub fn texture_memory_report() -> Option<CacheReport> {
+    MEMORY_CACHE.with(|memory_cache| memory_cache.borrow().as_ref().map(|c| c.report()))
+}
+
+// Whether textures should go through load_texture_image rather than
+// straight to radiance
+pub fn texture_cache_enabled() -> bool {
+    texture_cache_dir().is_some() || MEMORY_CACHE.with(|c| c.borrow().is_some())
+}
+
+// Keyed by the source bytes rather than the path, so that a mod replacing
+// a texture gets a new entry instead of the stale one
+pub fn texture_cache_key(data: &[u8], max_size: u32) -> String {
+    // FNV-1a
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in data {
+        hash = (hash ^ b as u64).wrapping_mul(0x100000001b3);
+    }
+
+    format!("{:016x}_{}_{}", hash, data.len(), max_size)
+}
+
+// Decodes the texture at `path`, downscaled to `max_size` unless that is 0,
+// going through the memory cache and the cache directory when they are
+// set. None when the image crate can't decode it, leaving the file to
+// radiance.
+pub fn load_texture_image(path: &Path, max_size: u32) -> Option<RgbaImage> {
+    let key = (path.to_path_buf(), max_size);
+    let cached = MEMORY_CACHE.with(|memory_cache| {
+        memory_cache
+            .borrow_mut()
+            .as_mut()
+            .and_then(|c| c.get(&key).cloned())
+    });
+    if cached.is_some() {
+        return cached;
+    }
+
+    let image = decode_texture_image(path, max_size)?;
+    MEMORY_CACHE.with(|memory_cache| {
+        if let Some(cache) = memory_cache.borrow_mut().as_mut() {
+            let size = image.as_raw().len();
+            cache.insert(key, image.clone(), size);
+        }
+    });
+
+    Some(image)
+}
+
+fn decode_texture_image(path: &Path, max_size: u32) -> Option<RgbaImage> {
+    let data = std::fs::read(path).ok()?;
+    let cache_dir = texture_cache_dir();
+    let blob_path = cache_dir.as_ref().map(|dir| {
+        dir.join(texture_cache_key(&data, max_size))
+            .with_extension("rgba")
+    });
+
+    if let Some(blob_path) = &blob_path {
+        if let Ok(image) = read_blob(blob_path) {
+            return Some(image);
+        }
+    }
+
+    let mut decoded = image::load_from_memory(&data).ok()?;
+    if max_size > 0 && (decoded.width() > max_size || decoded.height() > max_size) {
+        decoded = decoded.resize(max_size, max_size, image::imageops::FilterType::Triangle);
+    }
+
+    let image = decoded.to_rgba();
+    if let Some(blob_path) = &blob_path {
+        if let Err(e) = write_blob(blob_path, &image) {
+            println!("Unable to cache texture {:?}: {}", path, e);
+        }
+    }
+
+    Some(image)
+}
+
+// The magic, width and height, then the pixels as stored by the image crate
+fn read_blob(path: &Path) -> Result<RgbaImage, Box<dyn Error>> {
+    let data = std::fs::read(path)?;
+    let mut reader = Cursor::new(&data);
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != BLOB_MAGIC {
+        return Err(format!("{:?}: not a texture cache blob", path).into());
+    }
+
+    let width = reader.read_u32::<LittleEndian>()?;
+    let height = reader.read_u32::<LittleEndian>()?;
+    let pixels = data[reader.position() as usize..].to_vec();
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| format!("{:?}: truncated texture cache blob", path).into())
+}
+
+fn write_blob(path: &Path, image: &RgbaImage) -> Result<(), Box<dyn Error>> {
+    let mut data = Vec::with_capacity(16 + image.as_raw().len());
+    data.extend_from_slice(BLOB_MAGIC);
+    data.write_u32::<LittleEndian>(image.width())?;
+    data.write_u32::<LittleEndian>(image.height())?;
+    data.extend_from_slice(image.as_raw());
+
+    // Written aside and renamed, so that a crash never leaves a torn blob
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, data)?;
+    std::fs::rename(temp_path, path)?;
+    Ok(())
+}
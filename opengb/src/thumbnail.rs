@@ -0,0 +1,223 @@
+use crate::model::FlatMesh;
+use image::{Rgba, RgbaImage};
+use std::collections::HashMap;
+
+// Untextured meshes, and meshes whose texture is missing
+const UNTEXTURED: [u8; 4] = [200, 200, 200, 255];
+// Texels below this alpha are cut out, as the engine's alpha test does
+const ALPHA_CUTOFF: u8 = 128;
+
+// A small CPU rasterizer for model thumbnails. It needs no window or GPU,
+// so it runs from the command line and on CI machines, and gives the same
+// pixels everywhere, which golden image comparisons rely on. Models are
+// drawn orthographically from the front, turned by `yaw` and tilted by
+// `pitch`, and framed to fill the image.
+pub struct ThumbnailRenderer {
+    size: u32,
+    yaw: f32,
+    pitch: f32,
+    background: [u8; 4],
+}
+
+impl ThumbnailRenderer {
+    pub fn new(size: u32) -> Self {
+        ThumbnailRenderer {
+            size: size.max(1),
+            yaw: 30f32.to_radians(),
+            pitch: 20f32.to_radians(),
+            background: [0, 0, 0, 0],
+        }
+    }
+
+    // In degrees
+    pub fn with_angles(mut self, yaw: f32, pitch: f32) -> Self {
+        self.yaw = yaw.to_radians();
+        self.pitch = pitch.to_radians();
+        self
+    }
+
+    pub fn with_background(mut self, background: [u8; 4]) -> Self {
+        self.background = background;
+        self
+    }
+
+    // `textures` is keyed by texture name, as in `FlatMesh::texture_names`
+    pub fn render(&self, meshes: &[FlatMesh], textures: &HashMap<String, RgbaImage>) -> RgbaImage {
+        let size = self.size as usize;
+        let mut image = RgbaImage::from_fn(self.size, self.size, |_, _| Rgba(self.background));
+        let mut depth = vec![std::f32::NEG_INFINITY; size * size];
+
+        let views: Vec<Vec<[f32; 3]>> = meshes
+            .iter()
+            .map(|m| m.positions.iter().map(|p| self.rotate(p)).collect())
+            .collect();
+        let (mut min, mut max) = ([std::f32::MAX; 2], [std::f32::MIN; 2]);
+        for p in views.iter().flatten() {
+            for i in 0..2 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+
+        if min[0] > max[0] {
+            return image;
+        }
+
+        // A margin of 5% on each side
+        let extent = (max[0] - min[0])
+            .max(max[1] - min[1])
+            .max(std::f32::EPSILON);
+        let scale = self.size as f32 * 0.9 / extent;
+        let center = [(min[0] + max[0]) / 2., (min[1] + max[1]) / 2.];
+        let half = self.size as f32 / 2.;
+        let to_screen = |p: &[f32; 3]| {
+            [
+                (p[0] - center[0]) * scale + half,
+                half - (p[1] - center[1]) * scale,
+                p[2],
+            ]
+        };
+
+        for (mesh, view) in meshes.iter().zip(&views) {
+            let screen: Vec<[f32; 3]> = view.iter().map(|p| to_screen(p)).collect();
+            let tex_coords = mesh.diffuse_tex_coords();
+            let texture = mesh.diffuse_texture().and_then(|name| textures.get(name));
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [
+                    triangle[0] as usize,
+                    triangle[1] as usize,
+                    triangle[2] as usize,
+                ];
+                if a >= screen.len() || b >= screen.len() || c >= screen.len() {
+                    continue;
+                }
+
+                let shade = shade(&view[a], &view[b], &view[c]);
+                let (sa, sb, sc) = (&screen[a], &screen[b], &screen[c]);
+                let area = edge(sa, sb, sc);
+                if area.abs() < std::f32::EPSILON {
+                    continue;
+                }
+
+                let x0 = sa[0].min(sb[0]).min(sc[0]).floor().max(0.) as usize;
+                let y0 = sa[1].min(sb[1]).min(sc[1]).floor().max(0.) as usize;
+                let x1 = (sa[0].max(sb[0]).max(sc[0]).ceil() as usize).min(size);
+                let y1 = (sa[1].max(sb[1]).max(sc[1]).ceil() as usize).min(size);
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let p = [x as f32 + 0.5, y as f32 + 0.5, 0.];
+                        // Barycentric weights; both windings are drawn
+                        let wa = edge(sb, sc, &p) / area;
+                        let wb = edge(sc, sa, &p) / area;
+                        let wc = edge(sa, sb, &p) / area;
+                        if wa < 0. || wb < 0. || wc < 0. {
+                            continue;
+                        }
+
+                        let z = wa * sa[2] + wb * sb[2] + wc * sc[2];
+                        if z <= depth[y * size + x] {
+                            continue;
+                        }
+
+                        let color = match (texture, tex_coords.get(a)) {
+                            (Some(texture), Some(ta)) => {
+                                let (tb, tc) = (tex_coords[b], tex_coords[c]);
+                                let u = wa * ta[0] + wb * tb[0] + wc * tc[0];
+                                let v = wa * ta[1] + wb * tb[1] + wc * tc[1];
+                                sample(texture, u, v)
+                            }
+                            _ => UNTEXTURED,
+                        };
+                        if color[3] < ALPHA_CUTOFF {
+                            continue;
+                        }
+
+                        depth[y * size + x] = z;
+                        image.put_pixel(
+                            x as u32,
+                            y as u32,
+                            Rgba([
+                                (color[0] as f32 * shade) as u8,
+                                (color[1] as f32 * shade) as u8,
+                                (color[2] as f32 * shade) as u8,
+                                255,
+                            ]),
+                        );
+                    }
+                }
+            }
+        }
+
+        image
+    }
+
+    // Turns around y, then tilts around x, so that the camera looks down -z
+    fn rotate(&self, p: &[f32; 3]) -> [f32; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let x = p[0] * cos_yaw + p[2] * sin_yaw;
+        let z = -p[0] * sin_yaw + p[2] * cos_yaw;
+        let y = p[1] * cos_pitch - z * sin_pitch;
+        let z = p[1] * sin_pitch + z * cos_pitch;
+        [x, y, z]
+    }
+}
+
+// The fraction of pixels differing by more than `tolerance` in any
+// channel, or None when the sizes differ.
+pub fn image_difference(a: &RgbaImage, b: &RgbaImage, tolerance: u8) -> Option<f32> {
+    if a.dimensions() != b.dimensions() {
+        return None;
+    }
+
+    let (width, height) = a.dimensions();
+    let mut differing = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let (pa, pb) = (a.get_pixel(x, y).0, b.get_pixel(x, y).0);
+            if pa
+                .iter()
+                .zip(&pb)
+                .any(|(ca, cb)| (*ca as i32 - *cb as i32).abs() > tolerance as i32)
+            {
+                differing += 1;
+            }
+        }
+    }
+
+    Some(differing as f32 / (width as f32 * height as f32).max(1.))
+}
+
+// Twice the signed area of the triangle abc, in screen space
+fn edge(a: &[f32; 3], b: &[f32; 3], c: &[f32; 3]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+// Lit from the camera, so that faces turned away are darker but never black
+fn shade(a: &[f32; 3], b: &[f32; 3], c: &[f32; 3]) -> f32 {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let n = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+    if length == 0. {
+        return 1.;
+    }
+
+    0.4 + 0.6 * (n[2] / length).abs()
+}
+
+// Nearest texel, with the texture repeating
+fn sample(texture: &RgbaImage, u: f32, v: f32) -> [u8; 4] {
+    let (width, height) = texture.dimensions();
+    if width == 0 || height == 0 {
+        return UNTEXTURED;
+    }
+
+    let x = ((u * width as f32).floor() as i64).rem_euclid(width as i64) as u32;
+    let y = ((v * height as f32).floor() as i64).rem_euclid(height as i64) as u32;
+    texture.get_pixel(x, y).0
+}
@@ -0,0 +1,316 @@
+use super::overlay::UiLayer;
+use super::settings_menu::MenuInput;
+use super::text::BitmapFont;
+use crate::loaders::cvdloader::{CvdFile, CvdModel};
+use crate::loaders::mv3loader::Mv3File;
+use crate::loaders::polloader::{PolFile, PolVertexComponents};
+use radiance::math::{Vec2, Vec3};
+use std::collections::BTreeSet;
+
+const INDENT: f32 = 16.;
+// Long blobs are cut, the full bytes are in `pal3tool dump`
+const MAX_HEX_BYTES: usize = 32;
+
+// One line of the parsed file structure. Nodes standing for a sub-mesh the
+// viewer draws separately carry its index, so that it can be hidden.
+#[derive(Debug, Clone)]
+pub struct InspectorNode {
+    pub label: String,
+    pub mesh: Option<usize>,
+    pub children: Vec<InspectorNode>,
+}
+
+impl InspectorNode {
+    pub fn new(label: String) -> Self {
+        InspectorNode {
+            label,
+            mesh: None,
+            children: vec![],
+        }
+    }
+
+    pub fn with_mesh(mut self, mesh: usize) -> Self {
+        self.mesh = Some(mesh);
+        self
+    }
+
+    pub fn with_child(mut self, child: InspectorNode) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    // Depth first, with the depth of each node
+    pub fn rows(&self) -> Vec<(usize, &InspectorNode)> {
+        let mut rows = vec![];
+        self.collect_rows(0, &mut rows);
+        rows
+    }
+
+    fn collect_rows<'a>(&'a self, depth: usize, rows: &mut Vec<(usize, &'a InspectorNode)>) {
+        rows.push((depth, self));
+        for child in &self.children {
+            child.collect_rows(depth + 1, rows);
+        }
+    }
+}
+
+impl std::fmt::Display for InspectorNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (depth, node) in self.rows() {
+            writeln!(f, "{}{}", "    ".repeat(depth), node.label)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    let mut text: Vec<String> = bytes
+        .iter()
+        .take(MAX_HEX_BYTES)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    if bytes.len() > MAX_HEX_BYTES {
+        text.push(format!("... ({} bytes)", bytes.len()));
+    }
+
+    text.join(" ")
+}
+
+// Meshes are numbered in file order
+pub fn pol_inspector_tree(name: &str, pol: &PolFile) -> InspectorNode {
+    let mut root = InspectorNode::new(format!("{} (pol, {} meshes)", name, pol.meshes.len()));
+    for (i, mesh) in pol.meshes.iter().enumerate() {
+        let triangles: usize = mesh.material_info.iter().map(|m| m.triangles.len()).sum();
+        let mut node = InspectorNode::new(format!(
+            "mesh {}: {} vertices, {} triangles",
+            i,
+            mesh.vertices.len(),
+            triangles
+        ))
+        .with_mesh(i)
+        .with_child(InspectorNode::new(format!(
+            "normals: {}, aabb: {:?} - {:?}",
+            mesh.vertex_type.has(PolVertexComponents::NORMAL),
+            mesh.aabb_min,
+            mesh.aabb_max
+        )));
+        for (j, material) in mesh.material_info.iter().enumerate() {
+            node = node.with_child(
+                InspectorNode::new(format!(
+                    "material {}: {} triangles, textures: {}",
+                    j,
+                    material.triangles.len(),
+                    material.texture_names.join(", ")
+                ))
+                .with_child(InspectorNode::new(format!(
+                    "unknown_dw0: {:#x}, unknown_float: {}, unknown2-4: {} {} {}",
+                    material.unknown_dw0,
+                    material.unknown_float,
+                    material.unknown2,
+                    material.unknown3,
+                    material.unknown4
+                )))
+                .with_child(InspectorNode::new(format!(
+                    "unknown_68: {}",
+                    hex(&material.unknown_68)
+                ))),
+            );
+        }
+
+        root = root.with_child(node);
+    }
+
+    root
+}
+
+// Top level models are numbered in file order, with their children
+pub fn cvd_inspector_tree(name: &str, cvd: &CvdFile) -> InspectorNode {
+    let mut root = InspectorNode::new(format!("{} (cvd, {} models)", name, cvd.models.len()));
+    for (i, model) in cvd.models.iter().enumerate() {
+        root = root.with_child(cvd_model_node(&format!("model {}", i), model).with_mesh(i));
+    }
+
+    root
+}
+
+fn cvd_model_node(label: &str, model: &CvdModel) -> InspectorNode {
+    let mesh = &model.mesh;
+    let mut node = InspectorNode::new(format!(
+        "{}: {} vertices, {} frames, {} position keyframes",
+        label,
+        mesh.vertex_count,
+        mesh.frame_count,
+        model.position_keyframes.len()
+    ))
+    .with_child(InspectorNode::new(format!(
+        "unknown_byte: {:#x}, unknown_dword: {}, unknown_data: {} floats",
+        model.unknown_byte,
+        model.unknown_dword,
+        mesh.unknown_data.len()
+    )));
+    for (i, material) in mesh.materials.iter().enumerate() {
+        node = node.with_child(InspectorNode::new(format!(
+            "material {}: {} triangles, texture: {}, colors: {:08x} {:08x} {:08x} {:08x}",
+            i,
+            material.triangles.len(),
+            material.texture_name,
+            material.color1,
+            material.color2,
+            material.color3,
+            material.color4
+        )));
+    }
+
+    if let Some(children) = &model.children {
+        for (i, child) in children.iter().enumerate() {
+            node = node.with_child(cvd_model_node(&format!("child {}", i), child));
+        }
+    }
+
+    node
+}
+
+pub fn mv3_inspector_tree(name: &str, mv3: &Mv3File) -> InspectorNode {
+    let mut root = InspectorNode::new(format!(
+        "{} (mv3, {} models, {} actions)",
+        name,
+        mv3.models.len(),
+        mv3.action_desc.len()
+    ));
+    for (i, texture) in mv3.textures.iter().enumerate() {
+        let names: Vec<String> = texture
+            .names
+            .iter()
+            .map(|n| {
+                String::from_utf8_lossy(n)
+                    .trim_end_matches('\0')
+                    .to_string()
+            })
+            .collect();
+        root = root.with_child(
+            InspectorNode::new(format!("texture {}: {}", i, names.join(", "))).with_child(
+                InspectorNode::new(format!("unknown: {}", hex(&texture.unknown))),
+            ),
+        );
+    }
+
+    for (i, model) in mv3.models.iter().enumerate() {
+        let mut node = InspectorNode::new(format!(
+            "model {}: {} vertices, {} frames, {} meshes",
+            i,
+            model.vertex_per_frame,
+            model.frames.len(),
+            model.meshes.len()
+        ))
+        .with_child(InspectorNode::new(format!(
+            "unknown: {}",
+            hex(&model.unknown)
+        )));
+        for (j, mesh) in model.meshes.iter().enumerate() {
+            node = node.with_child(InspectorNode::new(format!(
+                "mesh {}: {} triangles, unknown: {:#x}, {} unknown entries",
+                j,
+                mesh.triangles.len(),
+                mesh.unknown,
+                mesh.unknown_data.len()
+            )));
+        }
+
+        root = root.with_child(node);
+    }
+
+    root
+}
+
+// A scrolling view of an inspector tree. Confirm on a sub-mesh row toggles
+// whether it is drawn; callers check `is_hidden` to apply that.
+pub struct InspectorPanel {
+    root: InspectorNode,
+    hidden: BTreeSet<usize>,
+    row: usize,
+    visible_rows: usize,
+}
+
+impl InspectorPanel {
+    pub fn new(root: InspectorNode, visible_rows: usize) -> Self {
+        InspectorPanel {
+            root,
+            hidden: BTreeSet::new(),
+            row: 0,
+            visible_rows: visible_rows.max(1),
+        }
+    }
+
+    pub fn with_hidden(mut self, hidden: &[usize]) -> Self {
+        self.hidden.extend(hidden);
+        self
+    }
+
+    pub fn root(&self) -> &InspectorNode {
+        &self.root
+    }
+
+    pub fn is_hidden(&self, mesh: usize) -> bool {
+        self.hidden.contains(&mesh)
+    }
+
+    // Returns the sub-mesh whose visibility changed
+    pub fn handle(&mut self, input: MenuInput) -> Option<usize> {
+        let count = self.root.rows().len();
+        match input {
+            MenuInput::Up => self.row = self.row.saturating_sub(1),
+            MenuInput::Down => self.row = (self.row + 1).min(count - 1),
+            MenuInput::PreviousPage => self.row = self.row.saturating_sub(self.visible_rows),
+            MenuInput::NextPage => self.row = (self.row + self.visible_rows).min(count - 1),
+            MenuInput::Confirm => {
+                let mesh = self.root.rows()[self.row].1.mesh?;
+                if !self.hidden.remove(&mesh) {
+                    self.hidden.insert(mesh);
+                }
+
+                return Some(mesh);
+            }
+            _ => (),
+        }
+
+        None
+    }
+
+    pub fn draw(&self, layer: &mut UiLayer, font: &BitmapFont, position: &Vec2) {
+        let white = Vec3::new(1., 1., 1.);
+        let grey = Vec3::new(0.5, 0.5, 0.5);
+        let highlight = Vec3::new(1., 0.85, 0.4);
+        let line_height = font.line_height();
+
+        // Keeps the selected row inside the window
+        let first = (self.row + 1).saturating_sub(self.visible_rows);
+        let rows = self.root.rows();
+        for (i, (depth, node)) in rows.iter().enumerate().skip(first).take(self.visible_rows) {
+            let hidden = node.mesh.map(|m| self.is_hidden(m)).unwrap_or(false);
+            let color = if i == self.row {
+                &highlight
+            } else if hidden {
+                &grey
+            } else {
+                &white
+            };
+            let label = if hidden {
+                format!("{} [hidden]", node.label)
+            } else {
+                node.label.clone()
+            };
+
+            layer.draw_text(
+                font,
+                &label,
+                &Vec2::new(
+                    position.x + *depth as f32 * INDENT,
+                    position.y + (i - first) as f32 * line_height,
+                ),
+                None,
+                color,
+            );
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use super::overlay::UiLayer;
+use super::settings_menu::MenuInput;
+use super::strings::StringTable;
+use super::text::BitmapFont;
+use crate::input::bindings::{Conflict, Resolution};
+use radiance::math::{Vec2, Vec3};
+
+const ROW_SPACING: f32 = 1.5;
+const CHOICES: [Option<Resolution>; 3] = [Some(Resolution::Swap), Some(Resolution::Clear), None];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptResult {
+    Pending,
+    Resolve(Resolution),
+    Cancel,
+}
+
+// Shown when a rebind in `Keybindings::rebind` hits other actions. Lists
+// them and lets the player swap, clear or keep the old binding.
+pub struct KeybindingPrompt {
+    conflicts: Vec<Conflict>,
+    choice: usize,
+}
+
+impl KeybindingPrompt {
+    pub fn new(conflicts: Vec<Conflict>) -> Self {
+        KeybindingPrompt {
+            conflicts,
+            choice: 0,
+        }
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    pub fn handle(&mut self, input: MenuInput) -> PromptResult {
+        match input {
+            MenuInput::Left | MenuInput::Up => {
+                self.choice = (self.choice + CHOICES.len() - 1) % CHOICES.len()
+            }
+            MenuInput::Right | MenuInput::Down => self.choice = (self.choice + 1) % CHOICES.len(),
+            MenuInput::Confirm => {
+                return match CHOICES[self.choice] {
+                    Some(resolution) => PromptResult::Resolve(resolution),
+                    None => PromptResult::Cancel,
+                }
+            }
+            MenuInput::Cancel => return PromptResult::Cancel,
+            _ => (),
+        }
+
+        PromptResult::Pending
+    }
+
+    pub fn draw(
+        &self,
+        layer: &mut UiLayer,
+        font: &BitmapFont,
+        strings: &StringTable,
+        position: &Vec2,
+    ) {
+        let white = Vec3::new(1., 1., 1.);
+        let highlight = Vec3::new(1., 0.85, 0.4);
+        let line_height = font.line_height() * ROW_SPACING;
+        for (i, conflict) in self.conflicts.iter().enumerate() {
+            let context_key = format!("keybinding.context.{}", conflict.context.name());
+            let text = strings.format(
+                "keybinding.conflict",
+                &[
+                    ("binding", &conflict.binding.name),
+                    ("action", &conflict.action),
+                    ("context", strings.get(&context_key)),
+                ],
+            );
+            let y = position.y + line_height * i as f32;
+            layer.draw_text(font, &text, &Vec2::new(position.x, y), None, &white);
+        }
+
+        let y = position.y + line_height * (self.conflicts.len() + 1) as f32;
+        let mut x = position.x;
+        for (i, choice) in CHOICES.iter().enumerate() {
+            let key = match choice {
+                Some(Resolution::Swap) => "keybinding.swap",
+                Some(Resolution::Clear) => "keybinding.clear",
+                None => "dialog.cancel",
+            };
+            let color = if i == self.choice { &highlight } else { &white };
+            let text = strings.get(key);
+            layer.draw_text(font, text, &Vec2::new(x, y), None, color);
+            x += font.text_width(text) + font.line_height() * 2.;
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use super::overlay::UiLayer;
+use super::text::BitmapFont;
+use radiance::math::{Vec2, Vec3};
+
+// Pixels between the anchor point and the bottom of a label
+const LABEL_MARGIN: f32 = 4.;
+
+#[derive(Debug, Clone)]
+pub struct DebugLabel {
+    pub text: String,
+    pub position: Vec3,
+}
+
+// Names and ids of entities drawn above them in world space, to tell which
+// entity is which while debugging. Labels are collected every frame and
+// drawn into a `UiLayer`; they are hidden unless enabled.
+pub struct DebugLabels {
+    labels: Vec<DebugLabel>,
+    color: Vec3,
+    enabled: bool,
+}
+
+impl DebugLabels {
+    pub fn new() -> Self {
+        DebugLabels {
+            labels: vec![],
+            color: Vec3::new(1., 1., 0.),
+            enabled: false,
+        }
+    }
+
+    pub fn with_color(mut self, color: Vec3) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn toggle(&mut self) -> bool {
+        self.enabled = !self.enabled;
+        self.enabled
+    }
+
+    pub fn labels(&self) -> &[DebugLabel] {
+        &self.labels
+    }
+
+    pub fn clear(&mut self) {
+        self.labels.clear();
+    }
+
+    // `position` is the top of the entity, e.g. the top center of its bounds
+    pub fn add(&mut self, text: &str, position: Vec3) {
+        self.labels.push(DebugLabel {
+            text: text.to_owned(),
+            position,
+        });
+    }
+
+    // Draws the labels centered above their positions. Returns how many
+    // were on screen.
+    pub fn draw(&self, layer: &mut UiLayer, font: &BitmapFont, view_proj: &[[f32; 4]; 4]) -> usize {
+        if !self.enabled {
+            return 0;
+        }
+
+        let screen = *layer.screen();
+        let mut count = 0;
+        for label in &self.labels {
+            let anchor = match screen.project(view_proj, &label.position) {
+                Some(anchor) => anchor,
+                None => continue,
+            };
+
+            let width = font.text_width(&label.text);
+            let position = Vec2::new(
+                anchor.x - width * 0.5,
+                anchor.y - font.line_height() - LABEL_MARGIN,
+            );
+            layer.draw_text(font, &label.text, &position, None, &self.color);
+            count += 1;
+        }
+
+        count
+    }
+}
@@ -0,0 +1,132 @@
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::{VertexBuffer, VertexComponents};
+
+pub mod inspector;
+pub mod keybinding_prompt;
+pub mod labels;
+pub mod overlay;
+pub mod settings_menu;
+pub mod strings;
+pub mod text;
+
+// Screen-space elements are drawn through the regular 3D pipeline with a
+// shader that takes positions in normalized device coordinates as-is. Each
+// quad gets a slightly smaller depth than the previous one so that later
+// quads are drawn on top of earlier ones.
+const BASE_DEPTH: f32 = 0.1;
+const DEPTH_STEP: f32 = 0.000_01;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScreenSpace {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ScreenSpace {
+    pub fn new(width: f32, height: f32) -> Self {
+        ScreenSpace { width, height }
+    }
+
+    // Pixels, with the origin at the top left, to Vulkan NDC.
+    pub fn to_ndc(&self, p: &Vec2, depth: f32) -> Vec3 {
+        Vec3::new(
+            p.x / self.width * 2. - 1.,
+            p.y / self.height * 2. - 1.,
+            depth,
+        )
+    }
+
+    // World to pixels through a view-projection matrix in the row-vector
+    // convention (p * view * proj), before the Vulkan clip correction.
+    // Points behind the camera or outside the view give None.
+    pub fn project(&self, view_proj: &[[f32; 4]; 4], p: &Vec3) -> Option<Vec2> {
+        let m = view_proj;
+        let mut clip = [0.; 4];
+        for (j, c) in clip.iter_mut().enumerate() {
+            *c = p.x * m[0][j] + p.y * m[1][j] + p.z * m[2][j] + m[3][j];
+        }
+
+        if clip[3] <= 0. {
+            return None;
+        }
+
+        let (x, y) = (clip[0] / clip[3], clip[1] / clip[3]);
+        if x < -1. || x > 1. || y < -1. || y > 1. {
+            return None;
+        }
+
+        // The clip correction flips y, so that +y points down the screen
+        Some(Vec2::new(
+            (x + 1.) * 0.5 * self.width,
+            (1. - y) * 0.5 * self.height,
+        ))
+    }
+}
+
+// A textured and tinted rectangle, in pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct ScreenQuad {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub rect: (Vec2, Vec2),
+    pub color: Vec3,
+}
+
+impl ScreenQuad {
+    // The tint color is stored in NORMAL.
+    pub fn vertex_components() -> VertexComponents {
+        VertexComponents::POSITION | VertexComponents::NORMAL | VertexComponents::TEXCOORD
+    }
+
+    // Writes the 4 vertices of the quad numbered `index` in `vertices`.
+    pub fn fill_vertex_buffer(
+        &self,
+        screen: &ScreenSpace,
+        vertices: &mut VertexBuffer,
+        index: usize,
+    ) {
+        self.fill_vertex_buffer_ordered(screen, vertices, index, index);
+    }
+
+    // Like `fill_vertex_buffer`, but the quad is layered by `order` rather
+    // than its index, for quads split across several buffers.
+    pub fn fill_vertex_buffer_ordered(
+        &self,
+        screen: &ScreenSpace,
+        vertices: &mut VertexBuffer,
+        index: usize,
+        order: usize,
+    ) {
+        let depth = (BASE_DEPTH - order as f32 * DEPTH_STEP).max(0.);
+        let (min, max) = self.rect;
+        let corners = [
+            (Vec2::new(0., 0.), Vec2::new(min.x, min.y)),
+            (Vec2::new(self.size.x, 0.), Vec2::new(max.x, min.y)),
+            (Vec2::new(self.size.x, self.size.y), Vec2::new(max.x, max.y)),
+            (Vec2::new(0., self.size.y), Vec2::new(min.x, max.y)),
+        ];
+
+        for (i, (offset, tex_coord)) in corners.iter().enumerate() {
+            let position = screen.to_ndc(
+                &Vec2::new(self.position.x + offset.x, self.position.y + offset.y),
+                depth,
+            );
+            vertices.set_data(
+                index * 4 + i,
+                Some(&position),
+                Some(&self.color),
+                Some(tex_coord),
+                None,
+            );
+        }
+    }
+
+    pub fn indices(count: usize) -> Vec<u32> {
+        (0..count as u32)
+            .flat_map(|i| {
+                let b = i * 4;
+                vec![b, b + 2, b + 1, b, b + 3, b + 2]
+            })
+            .collect()
+    }
+}
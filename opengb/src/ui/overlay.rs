@@ -0,0 +1,204 @@
+use super::text::{layout_text, text_quads, BitmapFont};
+use super::{ScreenQuad, ScreenSpace};
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::{VertexBuffer, VertexComponents};
+use std::path::{Path, PathBuf};
+
+// A panel texture whose corners keep their size while the edges and the
+// center stretch. Borders are in texture pixels: left, top, right, bottom.
+#[derive(Debug, Clone, Copy)]
+pub struct NineSlice {
+    pub texture_size: Vec2,
+    pub borders: [f32; 4],
+}
+
+impl NineSlice {
+    pub fn quads(&self, position: &Vec2, size: &Vec2, color: &Vec3) -> Vec<ScreenQuad> {
+        let [left, top, right, bottom] = self.borders;
+        // Panels smaller than their borders shrink the borders
+        let scale_x = (size.x / (left + right)).min(1.);
+        let scale_y = (size.y / (top + bottom)).min(1.);
+        let xs = [0., left * scale_x, size.x - right * scale_x, size.x];
+        let ys = [0., top * scale_y, size.y - bottom * scale_y, size.y];
+        let us = [
+            0.,
+            left / self.texture_size.x,
+            1. - right / self.texture_size.x,
+            1.,
+        ];
+        let vs = [
+            0.,
+            top / self.texture_size.y,
+            1. - bottom / self.texture_size.y,
+            1.,
+        ];
+
+        let mut quads = vec![];
+        for row in 0..3 {
+            for column in 0..3 {
+                let width = xs[column + 1] - xs[column];
+                let height = ys[row + 1] - ys[row];
+                if width <= 0. || height <= 0. {
+                    continue;
+                }
+
+                quads.push(ScreenQuad {
+                    position: Vec2::new(position.x + xs[column], position.y + ys[row]),
+                    size: Vec2::new(width, height),
+                    rect: (
+                        Vec2::new(us[column], vs[row]),
+                        Vec2::new(us[column + 1], vs[row + 1]),
+                    ),
+                    color: *color,
+                });
+            }
+        }
+
+        quads
+    }
+}
+
+// The quads of a layer using one texture. Each batch becomes one
+// RenderObject; quads keep the drawing order of the whole layer.
+pub struct UiBatch {
+    pub texture_path: PathBuf,
+    quads: Vec<(usize, ScreenQuad)>,
+}
+
+impl UiBatch {
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+
+    pub fn vertex_components() -> VertexComponents {
+        ScreenQuad::vertex_components()
+    }
+
+    pub fn to_vertex_buffer(&self, screen: &ScreenSpace) -> VertexBuffer {
+        let mut vertices = VertexBuffer::new(Self::vertex_components(), self.quads.len() * 4);
+        self.fill_vertex_buffer(screen, &mut vertices);
+        vertices
+    }
+
+    // `vertices` must have room for `len()` quads.
+    pub fn fill_vertex_buffer(&self, screen: &ScreenSpace, vertices: &mut VertexBuffer) {
+        for (i, (order, quad)) in self.quads.iter().enumerate() {
+            quad.fill_vertex_buffer_ordered(screen, vertices, i, *order);
+        }
+    }
+
+    pub fn indices(&self) -> Vec<u32> {
+        ScreenQuad::indices(self.quads.len())
+    }
+}
+
+// Collects the 2D elements of a frame, in drawing order, grouped by
+// texture. Positions are in the pixels of `screen`, which may be a virtual
+// resolution stretched to the window.
+pub struct UiLayer {
+    screen: ScreenSpace,
+    batches: Vec<UiBatch>,
+    quad_count: usize,
+}
+
+impl UiLayer {
+    pub fn new(screen: ScreenSpace) -> Self {
+        UiLayer {
+            screen,
+            batches: vec![],
+            quad_count: 0,
+        }
+    }
+
+    pub fn screen(&self) -> &ScreenSpace {
+        &self.screen
+    }
+
+    pub fn batches(&self) -> &[UiBatch] {
+        &self.batches
+    }
+
+    pub fn clear(&mut self) {
+        self.batches.clear();
+        self.quad_count = 0;
+    }
+
+    // A solid rectangle, using the embedded white texture.
+    pub fn draw_rect(&mut self, position: &Vec2, size: &Vec2, color: &Vec3) {
+        let quad = ScreenQuad {
+            position: *position,
+            size: *size,
+            rect: (Vec2::new(0., 0.), Vec2::new(1., 1.)),
+            color: *color,
+        };
+        self.push(Path::new(""), vec![quad]);
+    }
+
+    pub fn draw_image<P: AsRef<Path>>(
+        &mut self,
+        texture_path: P,
+        position: &Vec2,
+        size: &Vec2,
+        rect: (Vec2, Vec2),
+        color: &Vec3,
+    ) {
+        let quad = ScreenQuad {
+            position: *position,
+            size: *size,
+            rect,
+            color: *color,
+        };
+        self.push(texture_path.as_ref(), vec![quad]);
+    }
+
+    pub fn draw_panel<P: AsRef<Path>>(
+        &mut self,
+        texture_path: P,
+        nine_slice: &NineSlice,
+        position: &Vec2,
+        size: &Vec2,
+        color: &Vec3,
+    ) {
+        let quads = nine_slice.quads(position, size, color);
+        self.push(texture_path.as_ref(), quads);
+    }
+
+    pub fn draw_text(
+        &mut self,
+        font: &BitmapFont,
+        text: &str,
+        position: &Vec2,
+        max_width: Option<f32>,
+        color: &Vec3,
+    ) {
+        let glyphs = layout_text(font, text, max_width);
+        let quads = text_quads(font, &glyphs, position, color);
+        self.push(font.texture_path(), quads);
+    }
+
+    fn push(&mut self, texture_path: &Path, quads: Vec<ScreenQuad>) {
+        let index = match self
+            .batches
+            .iter()
+            .position(|b| b.texture_path == texture_path)
+        {
+            Some(index) => index,
+            None => {
+                self.batches.push(UiBatch {
+                    texture_path: texture_path.to_path_buf(),
+                    quads: vec![],
+                });
+                self.batches.len() - 1
+            }
+        };
+
+        for quad in quads {
+            self.batches[index].quads.push((self.quad_count, quad));
+            self.quad_count += 1;
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use super::overlay::UiLayer;
+use super::strings::StringTable;
+use super::text::BitmapFont;
+use crate::config::read_key_values;
+use radiance::math::{Vec2, Vec3};
+use std::error::Error;
+use std::path::Path;
+
+const ROW_SPACING: f32 = 1.5;
+const VALUE_COLUMN: f32 = 240.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    NextPage,
+    PreviousPage,
+    Confirm,
+    Cancel,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuEvent {
+    None,
+    // A choice changed but isn't applied yet; callers preview it live, e.g.
+    // brightness or UI scale
+    Changed { key: String, value: String },
+    Applied,
+    Reverted,
+    Closed,
+}
+
+// A config key with a fixed list of values, cycled with left and right
+#[derive(Debug, Clone)]
+pub struct SettingsOption {
+    pub key: String,
+    // Looked up in the string table, as are the page titles
+    pub label: String,
+    pub choices: Vec<String>,
+    selected: usize,
+    applied: usize,
+}
+
+impl SettingsOption {
+    pub fn new(key: &str, label: &str, choices: &[&str], default: usize) -> Self {
+        let default = default.min(choices.len().saturating_sub(1));
+        SettingsOption {
+            key: key.to_owned(),
+            label: label.to_owned(),
+            choices: choices.iter().map(|c| c.to_string()).collect(),
+            selected: default,
+            applied: default,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.choices[self.selected]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SettingsPage {
+    pub title: String,
+    pub options: Vec<SettingsOption>,
+}
+
+// The settings screen, navigated with a controller or the keyboard. Changes
+// take effect for preview right away and are kept or undone as a whole with
+// Confirm or Cancel. The applied values are written as a key = value file
+// that the individual settings readers share, e.g. `profile` for
+// `GraphicsSettings` and `locale` for `Locale`.
+pub struct SettingsMenu {
+    pages: Vec<SettingsPage>,
+    page: usize,
+    row: usize,
+}
+
+impl SettingsMenu {
+    pub fn new(pages: Vec<SettingsPage>) -> Self {
+        SettingsMenu {
+            pages,
+            page: 0,
+            row: 0,
+        }
+    }
+
+    pub fn with_default_pages() -> Self {
+        let volume = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10"];
+        SettingsMenu::new(vec![
+            SettingsPage {
+                title: "settings.graphics".to_owned(),
+                options: vec![
+                    SettingsOption::new("profile", "settings.profile", &["normal", "low"], 0),
+                    SettingsOption::new(
+                        "brightness",
+                        "settings.brightness",
+                        &["80", "90", "100", "110", "120"],
+                        2,
+                    ),
+                    SettingsOption::new("ui_scale", "settings.ui_scale", &["100", "125", "150"], 0),
+                ],
+            },
+            SettingsPage {
+                title: "settings.audio".to_owned(),
+                options: vec![
+                    SettingsOption::new("music_volume", "settings.music_volume", &volume, 8),
+                    SettingsOption::new("sound_volume", "settings.sound_volume", &volume, 8),
+                ],
+            },
+            SettingsPage {
+                title: "settings.controls".to_owned(),
+                options: vec![SettingsOption::new(
+                    "vibration",
+                    "settings.vibration",
+                    &["on", "off"],
+                    0,
+                )],
+            },
+            SettingsPage {
+                title: "settings.gameplay".to_owned(),
+                options: vec![
+                    SettingsOption::new("locale", "settings.language", &["zh-CN", "en"], 0),
+                    SettingsOption::new(
+                        "text_speed",
+                        "settings.text_speed",
+                        &["slow", "normal", "fast"],
+                        1,
+                    ),
+                ],
+            },
+        ])
+    }
+
+    // Sets the applied values from a key = value file. Unknown keys are
+    // left for other readers; unknown values are reported.
+    pub fn load_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Box<dyn Error>> {
+        read_key_values(path, |key, value| match self.option_mut(key) {
+            Some(option) => match option.choices.iter().position(|c| c == value) {
+                Some(index) => {
+                    option.selected = index;
+                    option.applied = index;
+                    true
+                }
+                None => false,
+            },
+            None => true,
+        })
+    }
+
+    // Replaces the menu's keys in the file and keeps every other line
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let existing = std::fs::read_to_string(&path).unwrap_or_default();
+        let mut text = String::new();
+        for line in existing.lines() {
+            let key = line.splitn(2, '=').next().unwrap_or("").trim();
+            if line.trim().starts_with('#') || self.option(key).is_none() {
+                text.push_str(line);
+                text.push('\n');
+            }
+        }
+
+        for option in self.pages.iter().flat_map(|p| &p.options) {
+            text.push_str(&format!(
+                "{} = {}\n",
+                option.key, option.choices[option.applied]
+            ));
+        }
+
+        std::fs::write(path, text)
+    }
+
+    pub fn pages(&self) -> &[SettingsPage] {
+        &self.pages
+    }
+
+    pub fn current_page(&self) -> usize {
+        self.page
+    }
+
+    pub fn current_row(&self) -> usize {
+        self.row
+    }
+
+    // The previewed value, which may not be applied yet
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.option(key).map(|o| o.value())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.pages
+            .iter()
+            .flat_map(|p| &p.options)
+            .any(|o| o.selected != o.applied)
+    }
+
+    pub fn handle(&mut self, input: MenuInput) -> MenuEvent {
+        let page_count = self.pages.len();
+        let row_count = self
+            .pages
+            .get(self.page)
+            .map(|p| p.options.len())
+            .unwrap_or(0);
+        match input {
+            MenuInput::Up if row_count > 0 => self.row = (self.row + row_count - 1) % row_count,
+            MenuInput::Down if row_count > 0 => self.row = (self.row + 1) % row_count,
+            MenuInput::NextPage if page_count > 0 => {
+                self.page = (self.page + 1) % page_count;
+                self.row = 0;
+            }
+            MenuInput::PreviousPage if page_count > 0 => {
+                self.page = (self.page + page_count - 1) % page_count;
+                self.row = 0;
+            }
+            MenuInput::Left | MenuInput::Right if row_count > 0 => {
+                let option = &mut self.pages[self.page].options[self.row];
+                let count = option.choices.len();
+                option.selected = if input == MenuInput::Left {
+                    (option.selected + count - 1) % count
+                } else {
+                    (option.selected + 1) % count
+                };
+
+                return MenuEvent::Changed {
+                    key: option.key.clone(),
+                    value: option.value().to_owned(),
+                };
+            }
+            MenuInput::Confirm => {
+                for option in self.pages.iter_mut().flat_map(|p| &mut p.options) {
+                    option.applied = option.selected;
+                }
+
+                return MenuEvent::Applied;
+            }
+            MenuInput::Cancel if self.is_dirty() => {
+                for option in self.pages.iter_mut().flat_map(|p| &mut p.options) {
+                    option.selected = option.applied;
+                }
+
+                return MenuEvent::Reverted;
+            }
+            MenuInput::Cancel => return MenuEvent::Closed,
+            _ => (),
+        }
+
+        MenuEvent::None
+    }
+
+    // The current page's title and options, with the selected row
+    // highlighted
+    pub fn draw(
+        &self,
+        layer: &mut UiLayer,
+        font: &BitmapFont,
+        strings: &StringTable,
+        position: &Vec2,
+    ) {
+        let page = match self.pages.get(self.page) {
+            Some(page) => page,
+            None => return,
+        };
+
+        let white = Vec3::new(1., 1., 1.);
+        let highlight = Vec3::new(1., 0.85, 0.4);
+        let line_height = font.line_height() * ROW_SPACING;
+        layer.draw_text(font, strings.get(&page.title), position, None, &white);
+        for (i, option) in page.options.iter().enumerate() {
+            let color = if i == self.row { &highlight } else { &white };
+            let y = position.y + line_height * (i + 1) as f32;
+            layer.draw_text(
+                font,
+                strings.get(&option.label),
+                &Vec2::new(position.x, y),
+                None,
+                color,
+            );
+            layer.draw_text(
+                font,
+                &format!("< {} >", option.value()),
+                &Vec2::new(position.x + VALUE_COLUMN, y),
+                None,
+                color,
+            );
+        }
+    }
+
+    fn option(&self, key: &str) -> Option<&SettingsOption> {
+        self.pages
+            .iter()
+            .flat_map(|p| &p.options)
+            .find(|o| o.key == key)
+    }
+
+    fn option_mut(&mut self, key: &str) -> Option<&mut SettingsOption> {
+        self.pages
+            .iter_mut()
+            .flat_map(|p| &mut p.options)
+            .find(|o| o.key == key)
+    }
+}
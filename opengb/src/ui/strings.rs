@@ -0,0 +1,108 @@
+use crate::config::key_value_lines;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+static EN_STRINGS: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/embed/strings/en.txt"));
+static ZH_CN_STRINGS: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/embed/strings/zh-CN.txt"
+));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    ZhCn,
+}
+
+impl Locale {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "en" | "en-us" => Some(Locale::En),
+            "zh-cn" | "zh" => Some(Locale::ZhCn),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::ZhCn => "zh-CN",
+        }
+    }
+
+    // Reads `locale = en|zh-CN` from a key = value config file, defaulting
+    // to Simplified Chinese like the original game
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(&path)?;
+        let mut locale = Locale::ZhCn;
+        for line in key_value_lines(&text).filter(|l| l.key == "locale") {
+            let value = line.value.unwrap_or("");
+            locale = Locale::parse(value).ok_or_else(|| format!("unknown locale {:?}", value))?;
+        }
+
+        Ok(locale)
+    }
+}
+
+// The engine's own UI strings, as opposed to the text in the game data.
+// Keys missing from the selected locale fall back to English, then to the
+// key itself so that they stand out on screen.
+pub struct StringTable {
+    locale: Locale,
+    strings: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+impl StringTable {
+    pub fn new(locale: Locale) -> Self {
+        let text = match locale {
+            Locale::En => EN_STRINGS,
+            Locale::ZhCn => ZH_CN_STRINGS,
+        };
+
+        StringTable {
+            locale,
+            strings: parse_strings(text),
+            fallback: parse_strings(EN_STRINGS),
+        }
+    }
+
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    // Overrides or adds strings from a `key = text` file, e.g. a community
+    // translation
+    pub fn load_overrides<P: AsRef<Path>>(&mut self, path: P) -> Result<usize, Box<dyn Error>> {
+        let strings = parse_strings(&std::fs::read_to_string(path)?);
+        let count = strings.len();
+        self.strings.extend(strings);
+        Ok(count)
+    }
+
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .map(|s| s.as_str())
+            .unwrap_or(key)
+    }
+
+    // Fills `{name}` placeholders; unknown ones are left as they are
+    pub fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let mut text = self.get(key).to_owned();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+
+        text
+    }
+}
+
+fn parse_strings(text: &str) -> HashMap<String, String> {
+    key_value_lines(text)
+        .filter_map(|l| l.value.map(|text| (l.key.to_owned(), text.to_owned())))
+        .collect()
+}
@@ -0,0 +1,208 @@
+use super::ScreenQuad;
+use radiance::math::{Vec2, Vec3};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    pub rect: (Vec2, Vec2),
+    pub advance: f32,
+}
+
+// A font prerendered into a grid of equally sized cells, one glyph per cell
+// in the order of `charset`. Wide (CJK) glyphs take a whole cell; narrow
+// ones take its left half, as in the usual GB2312 bitmap fonts.
+pub struct BitmapFont {
+    texture_path: PathBuf,
+    glyphs: HashMap<char, Glyph>,
+    cell_width: f32,
+    cell_height: f32,
+    fallback: Option<char>,
+}
+
+impl BitmapFont {
+    pub fn new<P: AsRef<Path>>(
+        texture_path: P,
+        atlas_size: (u32, u32),
+        cell_size: (u32, u32),
+        charset: &str,
+    ) -> Self {
+        let columns = (atlas_size.0 / cell_size.0.max(1)).max(1);
+        let cell_width = cell_size.0 as f32;
+        let cell_height = cell_size.1 as f32;
+        let glyphs = charset
+            .chars()
+            .filter(|c| !c.is_control())
+            .enumerate()
+            .map(|(i, c)| {
+                let x = (i as u32 % columns) as f32 * cell_width;
+                let y = (i as u32 / columns) as f32 * cell_height;
+                let advance = if is_wide(c) {
+                    cell_width
+                } else {
+                    cell_width / 2.
+                };
+                let rect = (
+                    Vec2::new(x / atlas_size.0 as f32, y / atlas_size.1 as f32),
+                    Vec2::new(
+                        (x + advance) / atlas_size.0 as f32,
+                        (y + cell_height) / atlas_size.1 as f32,
+                    ),
+                );
+
+                (c, Glyph { rect, advance })
+            })
+            .collect();
+
+        BitmapFont {
+            texture_path: texture_path.as_ref().to_path_buf(),
+            glyphs,
+            cell_width,
+            cell_height,
+            fallback: None,
+        }
+    }
+
+    // Drawn in place of characters missing from the charset.
+    pub fn with_fallback(mut self, c: char) -> Self {
+        self.fallback = Some(c);
+        self
+    }
+
+    pub fn texture_path(&self) -> &Path {
+        &self.texture_path
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.cell_height
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs
+            .get(&c)
+            .or_else(|| self.fallback.and_then(|f| self.glyphs.get(&f)))
+    }
+
+    // Of the widest line, without wrapping
+    pub fn text_width(&self, text: &str) -> f32 {
+        text.lines()
+            .map(|line| line.chars().map(|c| self.advance(c)).sum::<f32>())
+            .fold(0., f32::max)
+    }
+
+    fn advance(&self, c: char) -> f32 {
+        match self.glyph(c) {
+            Some(g) => g.advance,
+            None if c == ' ' => self.cell_width / 2.,
+            None => 0.,
+        }
+    }
+}
+
+pub fn is_wide(c: char) -> bool {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6 => true,
+        _ => false,
+    }
+}
+
+// Punctuation that must not start a line.
+fn is_closing_punctuation(c: char) -> bool {
+    "，。、；：！？）」』》〉】…—,.;:!?)".contains(c)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PositionedGlyph {
+    pub c: char,
+    pub position: Vec2,
+}
+
+// Lays `text` out from the origin, wrapping lines at `max_width` pixels.
+// Lines may break between any two wide characters, but words of narrow
+// characters are only broken at spaces unless they are wider than a line.
+pub fn layout_text(font: &BitmapFont, text: &str, max_width: Option<f32>) -> Vec<PositionedGlyph> {
+    let max_width = max_width.unwrap_or(std::f32::MAX);
+    let mut glyphs = vec![];
+    let mut x = 0.;
+    let mut y = 0.;
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            x = 0.;
+            y += font.line_height();
+            i += 1;
+            continue;
+        }
+
+        // A word of narrow characters, or a single wide one
+        let end = if is_wide(c) || c == ' ' {
+            i + 1
+        } else {
+            chars[i..]
+                .iter()
+                .position(|&c| is_wide(c) || c == ' ' || c == '\n')
+                .map(|p| i + p)
+                .unwrap_or_else(|| chars.len())
+        };
+
+        let width: f32 = chars[i..end].iter().map(|&c| font.advance(c)).sum();
+        if x > 0. && x + width > max_width && !is_closing_punctuation(c) {
+            x = 0.;
+            y += font.line_height();
+            if c == ' ' {
+                i += 1;
+                continue;
+            }
+        }
+
+        for &c in &chars[i..end] {
+            let advance = font.advance(c);
+            if x > 0. && x + advance > max_width && width > max_width {
+                x = 0.;
+                y += font.line_height();
+            }
+
+            if c != ' ' && font.glyph(c).is_some() {
+                glyphs.push(PositionedGlyph {
+                    c,
+                    position: Vec2::new(x, y),
+                });
+            }
+
+            x += advance;
+        }
+
+        i = end;
+    }
+
+    glyphs
+}
+
+// Turns laid out glyphs into quads for `ScreenQuad::fill_vertex_buffer`,
+// offset by `origin` and tinted by `color`.
+pub fn text_quads(
+    font: &BitmapFont,
+    glyphs: &[PositionedGlyph],
+    origin: &Vec2,
+    color: &Vec3,
+) -> Vec<ScreenQuad> {
+    glyphs
+        .iter()
+        .filter_map(|g| {
+            font.glyph(g.c).map(|glyph| ScreenQuad {
+                position: Vec2::new(origin.x + g.position.x, origin.y + g.position.y),
+                size: Vec2::new(glyph.advance, font.line_height()),
+                rect: glyph.rect,
+                color: *color,
+            })
+        })
+        .collect()
+}
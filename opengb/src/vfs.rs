@@ -0,0 +1,335 @@
+use crate::cpk::{normalize, CpkArchive};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+enum MountSource {
+    Dir(PathBuf),
+    // With the normalized directory its files appear under
+    Archive(CpkArchive, String),
+}
+
+struct Mount {
+    source: MountSource,
+    priority: i32,
+}
+
+impl Mount {
+    fn path(&self) -> &Path {
+        match &self.source {
+            MountSource::Dir(dir) => dir,
+            MountSource::Archive(archive, _) => archive.path(),
+        }
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        match &self.source {
+            MountSource::Dir(dir) => find_case_insensitive(dir, path).is_some(),
+            MountSource::Archive(archive, mount_point) => archive_path(mount_point, path)
+                .map(|path| archive.contains(&path))
+                .unwrap_or(false),
+        }
+    }
+
+    fn file_names(&self) -> Vec<String> {
+        match &self.source {
+            MountSource::Dir(dir) => {
+                let mut names = vec![];
+                list_files(dir, "", &mut names);
+                names
+            }
+            MountSource::Archive(archive, mount_point) if mount_point.is_empty() => {
+                archive.file_names().to_vec()
+            }
+            MountSource::Archive(archive, mount_point) => archive
+                .file_names()
+                .iter()
+                .map(|name| format!("{}/{}", mount_point, name))
+                .collect(),
+        }
+    }
+}
+
+// Resolves game paths against a list of mounted directories and CPK
+// archives. Game data refers to files case-insensitively and with either
+// separator (`scene\Q01\...`), so lookups match path components ignoring
+// ASCII case. Mounts with a higher priority shadow lower ones, and among
+// equal priorities later mounts shadow earlier ones, letting mods and
+// patches override the base data.
+pub struct Vfs {
+    mounts: Vec<Mount>,
+    index_dir: Option<PathBuf>,
+    // Every mounted file to its mount, and its path on disk for directory
+    // mounts. Built by `build_index` and dropped when the mounts change.
+    index: Option<HashMap<String, (usize, Option<PathBuf>)>>,
+}
+
+impl Vfs {
+    pub fn new() -> Self {
+        Vfs {
+            mounts: vec![],
+            index_dir: None,
+            index: None,
+        }
+    }
+
+    // Archives mounted from now on keep their table in this directory, see
+    // `CpkArchive::load_with_index`
+    pub fn set_index_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        self.index_dir = Some(dir.as_ref().to_path_buf());
+    }
+
+    pub fn mount<P: AsRef<Path>>(&mut self, dir: P) {
+        self.mount_with_priority(dir, 0);
+    }
+
+    pub fn mount_with_priority<P: AsRef<Path>>(&mut self, dir: P, priority: i32) {
+        self.add_mount(Mount {
+            source: MountSource::Dir(dir.as_ref().to_path_buf()),
+            priority,
+        });
+    }
+
+    // The archive's files appear under `mount_point`, e.g. `scene/Q01` for
+    // the files of scene/Q01.cpk. An empty mount point puts them at the top.
+    pub fn mount_archive<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        mount_point: &str,
+        priority: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        let archive = match &self.index_dir {
+            Some(dir) => {
+                std::fs::create_dir_all(dir)?;
+                CpkArchive::load_with_index(&path, dir.join(index_file_name(path.as_ref())))?
+            }
+            None => CpkArchive::load_from_file(path)?,
+        };
+        self.add_mount(Mount {
+            source: MountSource::Archive(archive, normalize(mount_point)),
+            priority,
+        });
+
+        Ok(())
+    }
+
+    // Keeps the mounts ordered from the highest priority down
+    fn add_mount(&mut self, mount: Mount) {
+        let index = self
+            .mounts
+            .iter()
+            .position(|m| m.priority <= mount.priority)
+            .unwrap_or(self.mounts.len());
+        self.mounts.insert(index, mount);
+        self.index = None;
+    }
+
+    // Walks every mount once so that lookups no longer touch the file
+    // system. Files added to mounted directories afterwards aren't seen
+    // until the index is built again.
+    pub fn build_index(&mut self) {
+        let mut index = HashMap::new();
+        for (i, mount) in self.mounts.iter().enumerate() {
+            for name in mount.file_names() {
+                let on_disk = match &mount.source {
+                    MountSource::Dir(dir) => Some(dir.join(&name)),
+                    MountSource::Archive(..) => None,
+                };
+                index.entry(normalize(&name)).or_insert((i, on_disk));
+            }
+        }
+
+        self.index = Some(index);
+    }
+
+    fn find(&self, path: &str) -> Option<(&Mount, Option<PathBuf>)> {
+        if let Some(index) = &self.index {
+            let (i, on_disk) = index.get(&normalize(path))?;
+            return Some((&self.mounts[*i], on_disk.clone()));
+        }
+
+        let mount = self.mounts.iter().find(|m| m.contains(path))?;
+        let on_disk = match &mount.source {
+            MountSource::Dir(dir) => find_case_insensitive(dir, path),
+            MountSource::Archive(..) => None,
+        };
+        Some((mount, on_disk))
+    }
+
+    // In resolution order
+    pub fn mounts(&self) -> Vec<&Path> {
+        self.mounts.iter().map(|m| m.path()).collect()
+    }
+
+    // Paths that exist on disk as given are returned unchanged, so tools can
+    // accept both game paths and plain file paths. Files that resolve to an
+    // archive have no path of their own and give None; use `read` instead.
+    pub fn resolve(&self, path: &str) -> Option<PathBuf> {
+        if Path::new(path).is_file() {
+            return Some(PathBuf::from(path));
+        }
+
+        self.find(path)?.1
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        Path::new(path).is_file() || self.find(path).is_some()
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        if Path::new(path).is_file() {
+            return Ok(std::fs::read(path)?);
+        }
+
+        let (mount, on_disk) = self
+            .find(path)
+            .ok_or_else(|| format!("{} not found in any mount", path))?;
+        match (&mount.source, on_disk) {
+            (MountSource::Archive(archive, mount_point), _) => {
+                let path = archive_path(mount_point, path)
+                    .ok_or_else(|| format!("{} is outside {}", path, mount_point))?;
+                archive.read(&path)
+            }
+            (MountSource::Dir(_), Some(on_disk)) => Ok(std::fs::read(on_disk)?),
+            (MountSource::Dir(_), None) => Err(format!("{} not found in any mount", path).into()),
+        }
+    }
+
+    // Every file in any mount, sorted and listed once however many mounts
+    // provide it. Directories are walked, as for `override_report`.
+    pub fn file_names(&self) -> Vec<String> {
+        let mut names = BTreeMap::new();
+        for mount in &self.mounts {
+            for name in mount.file_names() {
+                names.entry(name.to_lowercase()).or_insert(name);
+            }
+        }
+
+        names.into_iter().map(|(_, name)| name).collect()
+    }
+
+    // Lists the files provided by more than one mount, with the mount each
+    // is read from. Every mounted directory is walked, so this is meant for
+    // tools rather than for every startup.
+    pub fn override_report(&self) -> OverrideReport {
+        let mut providers: BTreeMap<String, (String, Vec<&Path>)> = BTreeMap::new();
+        for mount in &self.mounts {
+            for name in mount.file_names() {
+                providers
+                    .entry(name.to_lowercase())
+                    .or_insert_with(|| (name, vec![]))
+                    .1
+                    .push(mount.path());
+            }
+        }
+
+        let overrides = providers
+            .into_iter()
+            .filter(|(_, (_, mounts))| mounts.len() > 1)
+            .map(|(_, (name, mounts))| FileOverride {
+                path: name,
+                winner: mounts[0].to_path_buf(),
+                shadowed: mounts[1..].iter().map(|p| p.to_path_buf()).collect(),
+            })
+            .collect();
+
+        OverrideReport { overrides }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileOverride {
+    pub path: String,
+    pub winner: PathBuf,
+    pub shadowed: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OverrideReport {
+    pub overrides: Vec<FileOverride>,
+}
+
+impl std::fmt::Display for OverrideReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{} overridden files", self.overrides.len())?;
+        for o in &self.overrides {
+            writeln!(f, "{}: {:?} over {:?}", o.path, o.winner, o.shadowed)?;
+        }
+
+        Ok(())
+    }
+}
+
+// The name inside an archive mounted at `mount_point`, or None when `path`
+// lies outside of it
+fn archive_path(mount_point: &str, path: &str) -> Option<String> {
+    let path = normalize(path);
+    if mount_point.is_empty() {
+        return Some(path);
+    }
+
+    if path.starts_with(mount_point) && path[mount_point.len()..].starts_with('/') {
+        Some(path[mount_point.len() + 1..].to_string())
+    } else {
+        None
+    }
+}
+
+// Keeps archives with the same file name in different places apart
+fn index_file_name(archive: &Path) -> String {
+    let path = archive.to_string_lossy();
+    let name: String = path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '_' })
+        .collect();
+    format!("{}.idx", name.trim_start_matches('_'))
+}
+
+fn list_files(dir: &Path, prefix: &str, names: &mut Vec<String>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        let path = entry.path();
+        if path.is_dir() {
+            list_files(&path, &format!("{}/", name), names);
+        } else {
+            names.push(name);
+        }
+    }
+}
+
+fn find_case_insensitive(root: &Path, path: &str) -> Option<PathBuf> {
+    let mut current = root.to_path_buf();
+    for component in path.split(|c| c == '/' || c == '\\') {
+        if component.is_empty() || component == "." {
+            continue;
+        }
+
+        let exact = current.join(component);
+        if exact.exists() {
+            current = exact;
+            continue;
+        }
+
+        current = std::fs::read_dir(&current)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .find(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .eq_ignore_ascii_case(component)
+            })?
+            .path();
+    }
+
+    if current.is_file() {
+        Some(current)
+    } else {
+        None
+    }
+}
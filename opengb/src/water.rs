@@ -0,0 +1,140 @@
+use crate::config::read_key_values;
+use crate::shader_registry::set_spec_constants;
+use radiance::math::Vec2;
+use radiance::rendering::{VertexBuffer, VertexComponents};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+const WATER_ALPHA_ID: u32 = 5;
+const WATER_DISTORTION_ID: u32 = 6;
+
+// How many ripples span one texture, and how many ripple cycles pass by per
+// second
+const RIPPLE_SCALE: f32 = 4.;
+const RIPPLE_SPEED: f32 = 0.25;
+
+// Lakes and rivers, drawn semi-transparent with rippling texture
+// coordinates. The ripples are sampled with TEXCOORD2, which the surface
+// rewrites every frame along with the scrolled TEXCOORD.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WaterSurface {
+    pub alpha: f32,
+    // In texture sizes
+    pub distortion: f32,
+    // In texture sizes per second
+    pub scroll: [f32; 2],
+}
+
+impl WaterSurface {
+    pub fn new() -> Self {
+        WaterSurface {
+            alpha: 0.7,
+            distortion: 0.02,
+            scroll: [0.02, 0.01],
+        }
+    }
+
+    // Parses `<alpha> <distortion> [<scroll u> <scroll v>]`
+    pub fn parse(value: &str) -> Option<Self> {
+        let values: Result<Vec<f32>, _> = value.split_whitespace().map(|v| v.parse()).collect();
+        let values = values.ok()?;
+        let mut surface = WaterSurface::new();
+        match values.len() {
+            2 | 4 => {
+                surface.alpha = values[0];
+                surface.distortion = values[1];
+                if values.len() == 4 {
+                    surface.scroll = [values[2], values[3]];
+                }
+
+                Some(surface)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn vertex_components() -> VertexComponents {
+        VertexComponents::POSITION | VertexComponents::TEXCOORD | VertexComponents::TEXCOORD2
+    }
+
+    // Appended to the water shader's name, as for
+    // `FogParams::shader_name_suffix`. The scroll is done on the vertices
+    // and doesn't change the shader.
+    pub fn shader_name_suffix(&self) -> String {
+        format!(
+            "_{:08x}_{:08x}",
+            self.alpha.to_bits(),
+            self.distortion.to_bits()
+        )
+    }
+
+    // Sets the water constants of water.frag
+    pub fn apply_to_shader(&self, frag_src: &[u8]) -> Vec<u8> {
+        set_spec_constants(
+            frag_src,
+            &[
+                (WATER_ALPHA_ID, self.alpha.to_bits()),
+                (WATER_DISTORTION_ID, self.distortion.to_bits()),
+            ],
+        )
+    }
+
+    pub fn fill_vertex_buffer(
+        &self,
+        vertices: &mut VertexBuffer,
+        base_tex_coords: &[Vec2],
+        time: f32,
+    ) {
+        let scroll = Vec2::new(
+            (self.scroll[0] * time).fract(),
+            (self.scroll[1] * time).fract(),
+        );
+        let ripple = (RIPPLE_SPEED * time).fract();
+        for (i, tex_coord) in base_tex_coords.iter().enumerate() {
+            vertices.set_component(i, VertexComponents::TEXCOORD, |t: &mut Vec2| {
+                *t = Vec2::new(tex_coord.x + scroll.x, tex_coord.y + scroll.y);
+            });
+            vertices.set_component(i, VertexComponents::TEXCOORD2, |t: &mut Vec2| {
+                *t = Vec2::new(
+                    tex_coord.x * RIPPLE_SCALE + ripple,
+                    tex_coord.y * RIPPLE_SCALE + ripple,
+                );
+            });
+        }
+    }
+}
+
+// Maps texture names to water surfaces, one `texture_name = surface` pair
+// per line as accepted by `WaterSurface::parse`. Blank lines and lines
+// starting with '#' are ignored.
+pub struct WaterSurfaces {
+    surfaces: HashMap<String, WaterSurface>,
+}
+
+impl WaterSurfaces {
+    pub fn new() -> Self {
+        WaterSurfaces {
+            surfaces: HashMap::new(),
+        }
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut surfaces = WaterSurfaces::new();
+        read_key_values(path, |name, value| {
+            WaterSurface::parse(value)
+                .map(|surface| surfaces.insert(name, surface))
+                .is_some()
+        })?;
+
+        Ok(surfaces)
+    }
+
+    pub fn insert(&mut self, texture_name: &str, surface: WaterSurface) {
+        self.surfaces.insert(texture_name.to_lowercase(), surface);
+    }
+
+    pub fn for_texture(&self, texture_name: &str) -> Option<&WaterSurface> {
+        self.surfaces.get(&texture_name.to_lowercase())
+    }
+}
@@ -0,0 +1,74 @@
+/*
+ * C interface to the opengb model loaders and CPK reader, exported by the opengb_bindings
+ * library when built with its ffi feature.
+ */
+#ifndef OPENGB_H
+#define OPENGB_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * A triangle mesh using a single material. Positions and normals hold 3
+ * floats per vertex, texture coordinates 2. Texture coordinates have their
+ * origin at the top left.
+ *
+ * texture_name is the diffuse texture and tex_coords are its coordinates.
+ * tex_coords2 holds the lightmap coordinates of lightmapped POL meshes.
+ * normals and tex_coords2 are NULL when the mesh doesn't have them.
+ */
+typedef struct OpengbMesh {
+    char *texture_name;
+    uint32_t vertex_count;
+    float *positions;
+    float *normals;
+    float *tex_coords;
+    float *tex_coords2;
+    uint32_t index_count;
+    uint32_t *indices;
+} OpengbMesh;
+
+typedef struct OpengbModel {
+    uint32_t mesh_count;
+    OpengbMesh *meshes;
+} OpengbModel;
+
+/* Return NULL if the file can't be parsed. Animated formats only keep their
+ * first frame. */
+OpengbModel *opengb_load_pol(const char *path);
+OpengbModel *opengb_load_cvd(const char *path);
+OpengbModel *opengb_load_mv3(const char *path);
+
+OpengbModel *opengb_load_pol_from_memory(const uint8_t *data, size_t len);
+OpengbModel *opengb_load_cvd_from_memory(const uint8_t *data, size_t len);
+OpengbModel *opengb_load_mv3_from_memory(const uint8_t *data, size_t len);
+
+void opengb_free_model(OpengbModel *model);
+
+/* A CPK archive. The file names are in the archive's own case, with '/'
+ * separators, and stay valid until the archive is closed. Lookups by name
+ * ignore ASCII case. */
+typedef struct OpengbCpk OpengbCpk;
+
+/* Returns NULL if the archive can't be opened. */
+OpengbCpk *opengb_cpk_open(const char *path);
+uint32_t opengb_cpk_file_count(const OpengbCpk *cpk);
+const char *opengb_cpk_file_name(const OpengbCpk *cpk, uint32_t index);
+
+/* Unpacks a file, storing its size in len. Returns NULL if the file is not
+ * in the archive or can't be unpacked. The buffer must be released with
+ * opengb_free_buffer, e.g. after passing it to a *_from_memory loader. */
+uint8_t *opengb_cpk_read(const OpengbCpk *cpk, const char *name, size_t *len);
+void opengb_free_buffer(uint8_t *data, size_t len);
+
+void opengb_cpk_close(OpengbCpk *cpk);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
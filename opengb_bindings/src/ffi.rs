@@ -0,0 +1,171 @@
+use opengb::ffi::{free_buffer, free_c_model, into_buffer, into_c_model, OpengbCpk, OpengbModel};
+use opengb::loaders::cvdloader::{cvd_load_from_bytes, cvd_load_from_file};
+use opengb::loaders::mv3loader::{mv3_load_from_bytes, mv3_load_from_file};
+use opengb::loaders::polloader::{pol_load_from_bytes, pol_load_from_file};
+use opengb::model::{cvd_to_flat_meshes, mv3_to_flat_meshes, pol_to_flat_meshes, FlatMesh};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::ptr;
+
+// All loaders return null if the file can't be parsed. The returned model
+// must be released with `opengb_free_model`.
+#[no_mangle]
+pub unsafe extern "C" fn opengb_load_pol(path: *const c_char) -> *mut OpengbModel {
+    load(path, |p| {
+        pol_load_from_file(p).map(|pol| pol_to_flat_meshes(&pol))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn opengb_load_cvd(path: *const c_char) -> *mut OpengbModel {
+    load(path, |p| {
+        cvd_load_from_file(p).map(|cvd| cvd_to_flat_meshes(&cvd))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn opengb_load_mv3(path: *const c_char) -> *mut OpengbModel {
+    load(path, |p| {
+        mv3_load_from_file(p).map(|mv3| mv3_to_flat_meshes(&mv3))
+    })
+}
+
+// Parse files already in memory, e.g. read from an archive.
+#[no_mangle]
+pub unsafe extern "C" fn opengb_load_pol_from_memory(
+    data: *const u8,
+    len: usize,
+) -> *mut OpengbModel {
+    load_from_memory(data, len, |d| {
+        pol_load_from_bytes(d).map(|pol| pol_to_flat_meshes(&pol))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn opengb_load_cvd_from_memory(
+    data: *const u8,
+    len: usize,
+) -> *mut OpengbModel {
+    load_from_memory(data, len, |d| {
+        cvd_load_from_bytes(d).map(|cvd| cvd_to_flat_meshes(&cvd))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn opengb_load_mv3_from_memory(
+    data: *const u8,
+    len: usize,
+) -> *mut OpengbModel {
+    load_from_memory(data, len, |d| {
+        mv3_load_from_bytes(d).map(|mv3| mv3_to_flat_meshes(&mv3))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn opengb_free_model(model: *mut OpengbModel) {
+    free_c_model(model);
+}
+
+// Returns null if the archive can't be opened. File names are valid until
+// the archive is closed with `opengb_cpk_close`.
+#[no_mangle]
+pub unsafe extern "C" fn opengb_cpk_open(path: *const c_char) -> *mut OpengbCpk {
+    let path = match c_str(path) {
+        Some(path) => path,
+        None => return ptr::null_mut(),
+    };
+
+    match OpengbCpk::open(path) {
+        Ok(cpk) => Box::into_raw(Box::new(cpk)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn opengb_cpk_file_count(cpk: *const OpengbCpk) -> u32 {
+    cpk.as_ref().map(|cpk| cpk.file_count()).unwrap_or(0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn opengb_cpk_file_name(cpk: *const OpengbCpk, index: u32) -> *const c_char {
+    cpk.as_ref()
+        .map(|cpk| cpk.file_name(index))
+        .unwrap_or(ptr::null())
+}
+
+// Unpacks a file into a buffer to pass to the loaders' from_memory
+// variants. Returns null if the file isn't in the archive or can't be
+// unpacked. The buffer must be released with `opengb_free_buffer`.
+#[no_mangle]
+pub unsafe extern "C" fn opengb_cpk_read(
+    cpk: *const OpengbCpk,
+    name: *const c_char,
+    len: *mut usize,
+) -> *mut u8 {
+    let (cpk, name, len) = match (cpk.as_ref(), c_str(name), len.as_mut()) {
+        (Some(cpk), Some(name), Some(len)) => (cpk, name, len),
+        _ => return ptr::null_mut(),
+    };
+
+    // Corrupt archives can make the decompressor panic
+    match std::panic::catch_unwind(AssertUnwindSafe(|| cpk.read(name).ok())) {
+        Ok(Some(data)) => into_buffer(data, len),
+        _ => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn opengb_free_buffer(data: *mut u8, len: usize) {
+    free_buffer(data, len);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn opengb_cpk_close(cpk: *mut OpengbCpk) {
+    if !cpk.is_null() {
+        drop(Box::from_raw(cpk));
+    }
+}
+
+unsafe fn c_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(s).to_str().ok()
+}
+
+unsafe fn load<F>(path: *const c_char, loader: F) -> *mut OpengbModel
+where
+    F: FnOnce(&Path) -> Result<Vec<FlatMesh>, Box<dyn std::error::Error>>,
+{
+    let path = match c_str(path) {
+        Some(path) => path.to_owned(),
+        None => return ptr::null_mut(),
+    };
+
+    // The loaders panic on some malformed files, which must not unwind
+    // into C code.
+    let meshes = std::panic::catch_unwind(AssertUnwindSafe(move || loader(Path::new(&path)).ok()));
+    match meshes {
+        Ok(Some(meshes)) => into_c_model(meshes),
+        _ => ptr::null_mut(),
+    }
+}
+
+unsafe fn load_from_memory<F>(data: *const u8, len: usize, loader: F) -> *mut OpengbModel
+where
+    F: FnOnce(&[u8]) -> Result<Vec<FlatMesh>, Box<dyn std::error::Error>>,
+{
+    if data.is_null() {
+        return ptr::null_mut();
+    }
+
+    let data = std::slice::from_raw_parts(data, len);
+    let meshes = std::panic::catch_unwind(AssertUnwindSafe(move || loader(data).ok()));
+    match meshes {
+        Ok(Some(meshes)) => into_c_model(meshes),
+        _ => ptr::null_mut(),
+    }
+}
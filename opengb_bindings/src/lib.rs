@@ -0,0 +1,6 @@
+// The C and Python bindings of opengb, kept out of opengb itself so that
+// crates depending on it don't build a shared library as well.
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
@@ -0,0 +1,144 @@
+use opengb::export::gltf::{
+    cvd_to_gltf_nodes, gltf_write_to_file, mv3_to_gltf_nodes, pol_to_gltf_nodes,
+};
+use opengb::export::obj::obj_write_to_file;
+use opengb::ffi::BindingMesh;
+use opengb::loaders::cvdloader::cvd_load_from_file;
+use opengb::loaders::mv3loader::mv3_load_from_file;
+use opengb::loaders::polloader::pol_load_from_file;
+use opengb::model::{cvd_to_flat_meshes, mv3_to_flat_meshes, pol_to_flat_meshes, FlatMesh};
+use pyo3::exceptions::IOError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use pyo3::wrap_pyfunction;
+use std::error::Error;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+
+// The loaders panic on some malformed files; turn that into an exception
+// rather than taking the interpreter down.
+fn load<T, F: FnOnce() -> Result<T, Box<dyn Error>>>(path: &str, loader: F) -> PyResult<T> {
+    match std::panic::catch_unwind(AssertUnwindSafe(loader)) {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(PyErr::new::<IOError, _>(format!("{}: {}", path, e))),
+        Err(_) => Err(PyErr::new::<IOError, _>(format!(
+            "{}: malformed file",
+            path
+        ))),
+    }
+}
+
+// The same view of a mesh as the C interface gives, see `BindingMesh`
+fn meshes_to_py(py: Python, meshes: Vec<FlatMesh>) -> PyResult<Vec<PyObject>> {
+    let mut objects = vec![];
+    for mesh in meshes.into_iter().map(BindingMesh::from) {
+        let tuple3 = |v: &Vec<[f32; 3]>| -> Vec<(f32, f32, f32)> {
+            v.iter().map(|p| (p[0], p[1], p[2])).collect()
+        };
+        let tuple2 =
+            |v: &Vec<[f32; 2]>| -> Vec<(f32, f32)> { v.iter().map(|t| (t[0], t[1])).collect() };
+
+        let dict = PyDict::new(py);
+        dict.set_item("texture_name", &mesh.texture_name)?;
+        dict.set_item("positions", tuple3(&mesh.positions))?;
+        dict.set_item("normals", mesh.normals.as_ref().map(tuple3))?;
+        dict.set_item("tex_coords", tuple2(&mesh.tex_coords))?;
+        dict.set_item("tex_coords2", mesh.tex_coords2.as_ref().map(tuple2))?;
+        dict.set_item("indices", &mesh.indices)?;
+        objects.push(dict.to_object(py));
+    }
+
+    Ok(objects)
+}
+
+// Each returns a list of meshes as dicts, see `model::FlatMesh`.
+#[pyfunction]
+fn load_pol(py: Python, path: &str) -> PyResult<Vec<PyObject>> {
+    let pol = load(path, || pol_load_from_file(path))?;
+    meshes_to_py(py, pol_to_flat_meshes(&pol))
+}
+
+#[pyfunction]
+fn load_cvd(py: Python, path: &str) -> PyResult<Vec<PyObject>> {
+    let cvd = load(path, || cvd_load_from_file(path))?;
+    meshes_to_py(py, cvd_to_flat_meshes(&cvd))
+}
+
+#[pyfunction]
+fn load_mv3(py: Python, path: &str) -> PyResult<Vec<PyObject>> {
+    let mv3 = load(path, || mv3_load_from_file(path))?;
+    meshes_to_py(py, mv3_to_flat_meshes(&mv3))
+}
+
+// Writes a POL, CVD or MV3 model as glTF, with `<stem>.bin` next to
+// `output`. MV3 frames become morph targets. Textures are referenced by
+// `export::exported_texture_name` but not written.
+#[pyfunction]
+fn write_gltf(path: &str, output: &str) -> PyResult<()> {
+    let name = Path::new(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let nodes = load(path, || match extension(path).as_str() {
+        "pol" => Ok(pol_to_gltf_nodes(&pol_load_from_file(path)?, &name)),
+        "cvd" => Ok(cvd_to_gltf_nodes(&cvd_load_from_file(path)?, &name)),
+        "mv3" => Ok(mv3_to_gltf_nodes(&mv3_load_from_file(path)?, &name)),
+        _ => Err("unsupported file type".into()),
+    })?;
+    gltf_write_to_file(&nodes, output)
+        .map_err(|e| PyErr::new::<IOError, _>(format!("{}: {}", output, e)))
+}
+
+// Writes a POL, CVD or MV3 model as OBJ, with `<stem>.mtl` next to `output`.
+// Animated models are written at their first frame.
+#[pyfunction]
+fn write_obj(path: &str, output: &str) -> PyResult<()> {
+    let meshes = load(path, || match extension(path).as_str() {
+        "pol" => Ok(pol_to_flat_meshes(&pol_load_from_file(path)?)),
+        "cvd" => Ok(cvd_to_flat_meshes(&cvd_load_from_file(path)?)),
+        "mv3" => Ok(mv3_to_flat_meshes(&mv3_load_from_file(path)?)),
+        _ => Err("unsupported file type".into()),
+    })?;
+    obj_write_to_file(&meshes, output)
+        .map_err(|e| PyErr::new::<IOError, _>(format!("{}: {}", output, e)))
+}
+
+fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+// The fields of a POL file whose meaning is still unknown, one dict per
+// material, for statistics across the game data.
+#[pyfunction]
+fn pol_unknown_fields(py: Python, path: &str) -> PyResult<Vec<PyObject>> {
+    let pol = load(path, || pol_load_from_file(path))?;
+    let mut objects = vec![];
+    for (i, mesh) in pol.meshes.iter().enumerate() {
+        for material in &mesh.material_info {
+            let dict = PyDict::new(py);
+            dict.set_item("mesh", i)?;
+            dict.set_item("some_flag", pol.some_flag)?;
+            dict.set_item("unknown_dw0", material.unknown_dw0)?;
+            dict.set_item("unknown_68", PyBytes::new(py, &material.unknown_68))?;
+            dict.set_item("unknown_float", material.unknown_float)?;
+            objects.push(dict.to_object(py));
+        }
+    }
+
+    Ok(objects)
+}
+
+// Named after the library, which Python looks up as PyInit_<file name>
+#[pymodule]
+fn opengb_bindings(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_wrapped(wrap_pyfunction!(load_pol))?;
+    m.add_wrapped(wrap_pyfunction!(load_cvd))?;
+    m.add_wrapped(wrap_pyfunction!(load_mv3))?;
+    m.add_wrapped(wrap_pyfunction!(write_gltf))?;
+    m.add_wrapped(wrap_pyfunction!(write_obj))?;
+    m.add_wrapped(wrap_pyfunction!(pol_unknown_fields))?;
+    Ok(())
+}
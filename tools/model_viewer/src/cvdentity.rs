@@ -1,18 +1,29 @@
+use super::playback::PlaybackControls;
+use opengb::animation::{AnimationLoopMode, Keyframe, KeyframeAnimation};
+use opengb::geometry::{remap_indices, Aabb};
 use opengb::loaders::cvdloader::*;
+use opengb::material::{create_lit_material, create_simple_material};
 use radiance::math::{Vec2, Vec3};
-use radiance::rendering::{RenderObject, SimpleMaterial, VertexBuffer, VertexComponents};
+use radiance::rendering::{RenderObject, VertexBuffer, VertexComponents};
 use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
 use std::path::PathBuf;
 
 pub struct CvdModelEntity {
     texture_path: PathBuf,
-    vertices: VertexBuffer,
+    vertices: Option<VertexBuffer>,
     indices: Vec<u32>,
+    translation_track: Vec<Keyframe<Vec3>>,
+    // Kept for stepping after the track moves into the animation
+    timestamps: Vec<f32>,
+    translation: Vec3,
+    lit: bool,
+    bounds: Aabb,
     id: u32,
+    playback: PlaybackControls,
 }
 
 impl CvdModelEntity {
-    pub fn new(all_vertices: &Vec<CvdVertex>, material: &CvdMaterial, path: &str, id: u32) -> Self {
+    pub fn new(model: &CvdModel, material: &CvdMaterial, path: &str, id: u32, lit: bool, playback: PlaybackControls) -> Self {
         let dds_name = material.texture_name.split_terminator('.')
                 .next()
                 .unwrap()
@@ -25,31 +36,17 @@ impl CvdModelEntity {
             texture_path.push(&material.texture_name);
         }
 
-        let components = VertexComponents::POSITION /*| VertexComponents::NORMAL*/ | VertexComponents::TEXCOORD;
-
-        let mut index_map = std::collections::HashMap::new();
-        let mut reversed_index = vec![];
-        let mut get_new_index = |index: u16| -> u32 {
-            if index_map.contains_key(&index) {
-                index_map[&index]
-            } else {
-                let new_index = reversed_index.len() as u32;
-                reversed_index.push(index as usize);
-                index_map.insert(index, new_index);
-                new_index
-            }
+        let components = if lit {
+            VertexComponents::POSITION | VertexComponents::NORMAL | VertexComponents::TEXCOORD
+        } else {
+            VertexComponents::POSITION | VertexComponents::TEXCOORD
         };
 
-        let mut indices: Vec<u32> = vec![];
-        for t in &material.triangles {
-            indices.push(get_new_index(t.indices[0]));
-            indices.push(get_new_index(t.indices[1]));
-            indices.push(get_new_index(t.indices[2]));
-        }
+        let (indices, reversed_index) = remap_indices(material.triangles.iter().map(|t| &t.indices));
 
         let mut vertices = VertexBuffer::new(components, reversed_index.len());
         for i in 0..reversed_index.len() {
-            let vert = &all_vertices[reversed_index[i]];
+            let vert = &model.mesh.frames[0][reversed_index[i]];
             vertices.set_data(
                 i,
                 Some(&Vec3::new(
@@ -57,18 +54,60 @@ impl CvdModelEntity {
                     vert.position.y,
                     vert.position.z,
                 )),
-                None,
+                Some(&vert.normal),
                 Some(&Vec2::new(vert.tex_coord.x, vert.tex_coord.y)),
                 None,
             );
         }
 
+        let bounds = Aabb::from_points(
+            reversed_index
+                .iter()
+                .map(|&i| &model.mesh.frames[0][i].position),
+        );
+
+        let timestamps = model.position_keyframes.iter().map(|k| k.timestamp).collect();
+        let translation_track = model
+            .position_keyframes
+            .iter()
+            .map(|k| Keyframe {
+                timestamp: k.timestamp,
+                value: k.position,
+            })
+            .collect();
+
         println!("indices: {:?}", indices);
         CvdModelEntity {
             texture_path,
-            vertices,
+            vertices: Some(vertices),
             indices,
+            translation_track,
+            timestamps,
+            translation: Vec3::new(0., 0., 0.),
+            lit,
+            bounds,
             id,
+            playback,
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    fn apply_animation<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let translation = match entity.get_component::<KeyframeAnimation>() {
+            Some(animation) => animation.translation(),
+            None => None,
+        };
+
+        if let Some(translation) = translation {
+            entity.transform_mut().translate_local(&Vec3::new(
+                translation.x - self.translation.x,
+                translation.y - self.translation.y,
+                translation.z - self.translation.z,
+            ));
+            self.translation = translation;
         }
     }
 }
@@ -76,10 +115,25 @@ impl CvdModelEntity {
 impl EntityCallbacks for CvdModelEntity {
     fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
         entity.add_component(RenderObject::new_with_data(
-            self.vertices.clone(),
-            self.indices.clone(),
-            Box::new(SimpleMaterial::new(&self.texture_path))
+            self.vertices.take().unwrap(),
+            std::mem::take(&mut self.indices),
+            if self.lit {
+                Box::new(create_lit_material(&self.texture_path))
+            } else {
+                Box::new(create_simple_material(&self.texture_path))
+            },
         ));
+        entity.add_component(self.bounds);
+        let duration = self
+            .translation_track
+            .last()
+            .map(|k| k.timestamp)
+            .unwrap_or(0.);
+        let mut animation = KeyframeAnimation::new(duration, AnimationLoopMode::Loop)
+            .with_translation_track(std::mem::take(&mut self.translation_track));
+        self.playback.start(&mut animation, &self.timestamps);
+        entity.add_component(animation);
+        self.apply_animation(entity);
         println!("id {}", self.id);
         println!("transform {}", entity.transform().matrix());
     }
@@ -91,5 +145,12 @@ impl EntityCallbacks for CvdModelEntity {
             &Vec3::new(0., 1., 0.),
             -0.2 * delta_sec * std::f32::consts::PI,
         );
+
+        if let Some(animation) = entity.get_component_mut::<KeyframeAnimation>() {
+            self.playback
+                .update(animation, &self.timestamps, delta_sec);
+        }
+
+        self.apply_animation(entity);
     }
 }
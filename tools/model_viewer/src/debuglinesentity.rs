@@ -0,0 +1,29 @@
+use opengb::debug_draw::DebugLines;
+use opengb::material::create_vertex_color_material;
+use radiance::rendering::RenderObject;
+use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
+use std::path::PathBuf;
+
+// Draws bounding boxes and normals over a model, placed like the model
+pub struct DebugLinesEntity {
+    lines: DebugLines,
+}
+
+impl DebugLinesEntity {
+    pub fn new(lines: DebugLines) -> Self {
+        DebugLinesEntity { lines }
+    }
+}
+
+impl EntityCallbacks for DebugLinesEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let (vertices, indices) = self.lines.build();
+
+        // An empty path gives a white texture, so only the line colors show
+        entity.add_component(RenderObject::new_with_data(
+            vertices,
+            indices,
+            Box::new(create_vertex_color_material(&PathBuf::new())),
+        ));
+    }
+}
@@ -0,0 +1,75 @@
+use opengb::geometry::Aabb;
+use opengb::material::create_simple_material;
+use opengb::model::FlatMesh;
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::{Material, RenderObject, VertexBuffer, VertexComponents};
+use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
+use std::path::PathBuf;
+
+// Draws meshes from formats added through plugins
+pub struct FlatMeshEntity {
+    vertices: Option<VertexBuffer>,
+    indices: Vec<u32>,
+    material: Option<Box<dyn Material>>,
+    bounds: Aabb,
+}
+
+impl FlatMeshEntity {
+    pub fn new(
+        mesh: &FlatMesh,
+        texture_paths: &[PathBuf],
+        material: Option<Box<dyn Material>>,
+    ) -> Self {
+        let mut vertices = VertexBuffer::new(
+            VertexComponents::POSITION | VertexComponents::TEXCOORD,
+            mesh.positions.len(),
+        );
+        for (i, (p, t)) in mesh.positions.iter().zip(mesh.diffuse_tex_coords()).enumerate() {
+            vertices.set_data(
+                i,
+                Some(&Vec3::new(p[0], p[1], p[2])),
+                None,
+                Some(&Vec2::new(t[0], t[1])),
+                None,
+            );
+        }
+
+        let positions: Vec<Vec3> = mesh
+            .positions
+            .iter()
+            .map(|p| Vec3::new(p[0], p[1], p[2]))
+            .collect();
+        let bounds = Aabb::from_points(&positions);
+        // Texture paths follow the mesh's texture names, diffuse last
+        let material = material.or_else(|| {
+            texture_paths
+                .last()
+                .map(|p| Box::new(create_simple_material(p)) as Box<dyn Material>)
+        });
+
+        FlatMeshEntity {
+            vertices: Some(vertices),
+            indices: mesh.indices.clone(),
+            material,
+            bounds,
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+impl EntityCallbacks for FlatMeshEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        if let Some(material) = self.material.take() {
+            entity.add_component(RenderObject::new_with_data(
+                self.vertices.take().unwrap(),
+                std::mem::take(&mut self.indices),
+                material,
+            ));
+        }
+
+        entity.add_component(self.bounds);
+    }
+}
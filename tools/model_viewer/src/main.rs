@@ -1,16 +1,41 @@
 mod mv3entity;
+mod options;
+mod overlayentity;
+mod particleentity;
+mod playback;
+mod playlist;
 mod polentity;
+mod roleentity;
 mod cvdentity;
+mod debuglinesentity;
+mod flatmeshentity;
 mod scene;
+mod skyentity;
+mod spriteentity;
 
 use nfd::Response;
+use opengb::diagnostics::{FrameStats, PerformanceReport};
+use opengb::plugins::PluginRegistry;
+#[cfg(debug_assertions)]
+use opengb::shader_reload::{ShaderWatcher, SHADER_SOURCE_DIR};
+use options::ViewerOptions;
+use playlist::ModelPlaylist;
 use radiance::application;
 use radiance::application::utils::FpsCounter;
 use radiance::scene::CoreScene;
+use std::cell::Cell;
+use std::rc::Rc;
 
 struct ApplicationCallbacks {
-    path: String,
+    playlist: ModelPlaylist,
+    cycle_timer: f32,
+    options: ViewerOptions,
     fps_counter: FpsCounter,
+    frame_stats: Option<FrameStats>,
+    #[cfg(debug_assertions)]
+    shader_watcher: ShaderWatcher,
+    #[cfg(debug_assertions)]
+    shader_poll_timer: f32,
 }
 
 impl application::ApplicationCallbacks for ApplicationCallbacks {
@@ -18,10 +43,7 @@ impl application::ApplicationCallbacks for ApplicationCallbacks {
         &mut self,
         app: &mut application::Application<T>,
     ) {
-        app.engine_mut()
-            .load_scene(CoreScene::new(scene::ModelViewerScene {
-                path: self.path.clone(),
-            }));
+        self.load_scene(app);
     }
 
     fn on_updated<T: application::ApplicationCallbacks>(
@@ -30,32 +52,144 @@ impl application::ApplicationCallbacks for ApplicationCallbacks {
         delta_sec: f32,
     ) {
         let fps = self.fps_counter.update_fps(delta_sec);
-        let title = format!("Model Viewer - OpenPAL3 Tools - FPS: {}", fps);
+        let title = format!(
+            "Model Viewer - OpenPAL3 Tools - [{}/{}] {} - FPS: {}",
+            self.playlist.position() + 1,
+            self.playlist.len(),
+            self.playlist.current(),
+            fps
+        );
         app.set_title(&title);
+
+        if let Some(frame_stats) = self.frame_stats.as_mut() {
+            if let Some(hitch) = frame_stats.update(delta_sec) {
+                println!("{}", hitch);
+            }
+
+            if frame_stats.frame_count() % 600 == 0 {
+                println!("{}", frame_stats.summary());
+            }
+        }
+
+        if let Some(cycle_sec) = self.options.cycle_sec {
+            self.cycle_timer += delta_sec;
+            if self.cycle_timer >= cycle_sec {
+                self.cycle_timer = 0.;
+                if !self.next_model(app) {
+                    println!("Reached the end of the playlist");
+                    self.options.cycle_sec = None;
+                }
+            }
+        }
+
+        // Reloading the scene recreates every material with the new shaders
+        #[cfg(debug_assertions)]
+        {
+            self.shader_poll_timer += delta_sec;
+            if self.shader_poll_timer >= 1. {
+                self.shader_poll_timer = 0.;
+                if self.shader_watcher.poll() {
+                    self.load_scene(app);
+                }
+            }
+        }
     }
 }
 
 impl ApplicationCallbacks {
-    pub fn new(path: String) -> Self {
+    pub fn new(playlist: ModelPlaylist, options: ViewerOptions) -> Self {
         ApplicationCallbacks {
-            path,
+            playlist,
+            cycle_timer: 0.,
             fps_counter: FpsCounter::new(),
+            frame_stats: if options.diagnostics {
+                Some(FrameStats::new(600, 2.5))
+            } else {
+                None
+            },
+            options,
+            #[cfg(debug_assertions)]
+            shader_watcher: ShaderWatcher::new(SHADER_SOURCE_DIR),
+            #[cfg(debug_assertions)]
+            shader_poll_timer: 0.,
+        }
+    }
+
+    // Returns false if the model failed to load
+    fn load_scene<T: application::ApplicationCallbacks>(
+        &self,
+        app: &mut application::Application<T>,
+    ) -> bool {
+        let load_failed = Rc::new(Cell::new(false));
+        app.engine_mut()
+            .load_scene(CoreScene::new(scene::ModelViewerScene {
+                path: self.playlist.current().to_owned(),
+                options: self.options.clone(),
+                plugins: PluginRegistry::with_builtin(),
+                playback_status: Rc::new(Cell::new(None)),
+                load_failed: load_failed.clone(),
+            }));
+        !load_failed.get()
+    }
+
+    // Skips models that fail to load. Scenes replaced this way aren't
+    // guaranteed to free their GPU resources, so the playlist is only
+    // walked once. Returns false at its end.
+    fn next_model<T: application::ApplicationCallbacks>(
+        &mut self,
+        app: &mut application::Application<T>,
+    ) -> bool {
+        while self.playlist.next().is_some() {
+            if self.load_scene(app) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+// The report covers the whole run, so it is written once, when the viewer
+// closes
+impl Drop for ApplicationCallbacks {
+    fn drop(&mut self) {
+        let (path, frame_stats) = match (&self.options.perf_report, &self.frame_stats) {
+            (Some(path), Some(frame_stats)) => (path, frame_stats),
+            _ => return,
+        };
+
+        let mut report = PerformanceReport::new();
+        report.add_frame_stats(frame_stats);
+        report.add_section("options", &format!("{:#?}", self.options));
+        match report.write_to_file(path) {
+            Ok(()) => println!("Wrote the performance report to {:?}", path),
+            Err(e) => println!("Unable to write the performance report: {}", e),
         }
     }
 }
 
 fn main() {
-    let result = nfd::open_file_dialog(Some("mv3,pol,cvd"), None).unwrap_or_else(|e| {
+    let options = ViewerOptions::from_args();
+    opengb::material::set_max_texture_size(options.graphics.max_texture_size);
+    opengb::texture_cache::set_texture_cache_dir(options.texture_cache.clone());
+    opengb::texture_cache::set_texture_memory_budget(
+        options.graphics.memory_budgets.texture_cache,
+        options.graphics.memory_budgets.eviction_policy,
+    );
+    opengb::material::set_uv_checker(options.uv_checker);
+    let result = nfd::open_file_dialog(Some("mv3,pol,cvd,scene"), None).unwrap_or_else(|e| {
         panic!(e);
     });
 
-    let path = match result {
-        Response::Okay(file_path) => file_path,
-        Response::OkayMultiple(files) => String::from(&files[0]),
+    // A single file brings in the rest of its directory
+    let playlist = match result {
+        Response::Okay(file_path) => ModelPlaylist::from_directory(&file_path),
+        Response::OkayMultiple(files) => ModelPlaylist::from_files(files),
         Response::Cancel => std::process::exit(0),
     };
 
-    let mut application = application::Application::new(ApplicationCallbacks::new(path));
+    let mut application =
+        application::Application::new(ApplicationCallbacks::new(playlist, options));
     application.initialize();
     application.run();
 }
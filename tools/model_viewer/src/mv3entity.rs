@@ -1,27 +1,35 @@
+use super::playback::PlaybackControls;
+use opengb::animation::{find_keyframes, AnimationLoopMode, KeyframeAnimation};
+use opengb::geometry::Aabb;
 use opengb::loaders::mv3loader::*;
+use opengb::material::create_simple_material;
+use opengb::model::{MV3_POSITION_SCALE, MV3_TICKS_PER_SECOND};
 use radiance::math::{Vec2, Vec3};
-use radiance::rendering::{RenderObject, SimpleMaterial, VertexBuffer, VertexComponents};
+use radiance::rendering::{RenderObject, VertexBuffer, VertexComponents};
 use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
 use std::collections::HashMap;
+use std::error::Error;
 use std::path::PathBuf;
 
-pub struct Mv3ModelEntity {
-    texture_path: PathBuf,
-    vertices: Vec<VertexBuffer>,
-    indices: Vec<u32>,
-    anim_timestamps: Vec<u32>,
-    last_anim_time: u32,
+// The frames of the first model of an MV3 file as vertex buffers, sharing
+// one index buffer
+pub struct Mv3Clip {
+    pub texture_path: PathBuf,
+    pub vertices: Vec<VertexBuffer>,
+    pub indices: Vec<u32>,
+    pub anim_timestamps: Vec<f32>,
+    pub bounds: Aabb,
 }
 
-impl Mv3ModelEntity {
-    pub fn new(path: &String) -> Self {
-        let mv3file = mv3_load_from_file(&path).unwrap();
+impl Mv3Clip {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mv3file = mv3_load_from_file(&path)?;
         let model: &Mv3Model = &mv3file.models[0];
         let mesh: &Mv3Mesh = &model.meshes[0];
 
         let mut texture_path = PathBuf::from(path);
         texture_path.pop();
-        texture_path.push(std::str::from_utf8(&mv3file.textures[0].names[0]).unwrap());
+        texture_path.push(std::str::from_utf8(&mv3file.textures[0].names[0])?);
 
         let hash =
             |index, texcoord_index| index as u32 * model.texcoord_count + texcoord_index as u32;
@@ -40,9 +48,9 @@ impl Mv3ModelEntity {
                             let frame = &model.frames[k];
                             vertices_data[k].push((
                                 Vec3::new(
-                                    frame.vertices[i as usize].x as f32 * 0.01562,
-                                    frame.vertices[i as usize].y as f32 * 0.01562,
-                                    frame.vertices[i as usize].z as f32 * 0.01562,
+                                    frame.vertices[i as usize].x as f32 * MV3_POSITION_SCALE,
+                                    frame.vertices[i as usize].y as f32 * MV3_POSITION_SCALE,
+                                    frame.vertices[i as usize].z as f32 * MV3_POSITION_SCALE,
                                 ),
                                 Vec2::new(
                                     model.texcoords[j as usize].u,
@@ -60,6 +68,8 @@ impl Mv3ModelEntity {
             }
         }
 
+        let bounds = Aabb::from_points(vertices_data.iter().flatten().map(|(p, _)| p));
+
         let mut vertices: Vec<VertexBuffer> =
             Vec::<VertexBuffer>::with_capacity(model.frame_count as usize);
         for i in 0..model.frame_count as usize {
@@ -80,25 +90,79 @@ impl Mv3ModelEntity {
             }
         }
 
-        let anim_timestamps = model.frames.iter().map(|f| f.timestamp).collect();
+        let anim_timestamps = model
+            .frames
+            .iter()
+            .map(|f| f.timestamp as f32 / MV3_TICKS_PER_SECOND)
+            .collect();
 
-        Mv3ModelEntity {
+        Ok(Mv3Clip {
             texture_path,
             anim_timestamps,
-            last_anim_time: 0,
+            bounds,
             vertices,
             indices,
+        })
+    }
+
+    pub fn duration(&self) -> f32 {
+        *self.anim_timestamps.last().unwrap()
+    }
+
+    // Blends the two frames around `time` into `vertices`
+    pub fn fill_vertices(&self, time: f32, vertices: &mut VertexBuffer) {
+        let (frame_index, next_frame_index, percentile) =
+            find_keyframes(&self.anim_timestamps, time);
+        let vertex_buffer = self.vertices.get(frame_index).unwrap();
+        let next_vertex_buffer = self.vertices.get(next_frame_index).unwrap();
+
+        for i in 0..vertex_buffer.count() {
+            let position = vertex_buffer.position(i).unwrap();
+            let next_position = next_vertex_buffer.position(i).unwrap();
+            let tex_coord = vertex_buffer.tex_coord(i).unwrap();
+
+            vertices.set_component(i, VertexComponents::POSITION, |p: &mut Vec3| {
+                p.x = position.x * (1. - percentile) + next_position.x * percentile;
+                p.y = position.y * (1. - percentile) + next_position.y * percentile;
+                p.z = position.z * (1. - percentile) + next_position.z * percentile;
+            });
+            vertices.set_component(i, VertexComponents::TEXCOORD, |t: &mut Vec2| {
+                t.x = tex_coord.x;
+                t.y = tex_coord.y;
+            });
         }
     }
 }
 
+pub struct Mv3ModelEntity {
+    clip: Mv3Clip,
+    playback: PlaybackControls,
+}
+
+impl Mv3ModelEntity {
+    pub fn new(path: &str, playback: PlaybackControls) -> Result<Self, Box<dyn Error>> {
+        Ok(Mv3ModelEntity {
+            clip: Mv3Clip::load(path)?,
+            playback,
+        })
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.clip.bounds
+    }
+}
+
 impl EntityCallbacks for Mv3ModelEntity {
     fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
         entity.add_component(RenderObject::new_host_dynamic_with_data(
-            self.vertices[0].clone(),
-            self.indices.clone(),
-            Box::new(SimpleMaterial::new(&self.texture_path)),
+            self.clip.vertices[0].clone(),
+            std::mem::take(&mut self.clip.indices),
+            Box::new(create_simple_material(&self.clip.texture_path)),
         ));
+        entity.add_component(self.clip.bounds);
+        let mut animation = KeyframeAnimation::new(self.clip.duration(), AnimationLoopMode::Loop);
+        self.playback.start(&mut animation, &self.clip.anim_timestamps);
+        entity.add_component(animation);
     }
 
     fn on_updating<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>, delta_sec: f32) {
@@ -107,43 +171,19 @@ impl EntityCallbacks for Mv3ModelEntity {
             -0.2 * delta_sec * std::f32::consts::PI,
         );
 
-        let anim_time = ((delta_sec * 4580.) as u32 + self.last_anim_time)
-            % self.anim_timestamps.last().unwrap();
-
-        let frame_index = self
-            .anim_timestamps
-            .iter()
-            .position(|&t| t > anim_time)
-            .unwrap_or(0)
-            - 1;
-        let next_frame_index = (frame_index + 1) % self.anim_timestamps.len();
-        let percentile = (anim_time - self.anim_timestamps[frame_index]) as f32
-            / (self.anim_timestamps[next_frame_index] - self.anim_timestamps[frame_index]) as f32;
+        let anim_time = {
+            let animation = entity.get_component_mut::<KeyframeAnimation>().unwrap();
+            self.playback
+                .update(animation, &self.clip.anim_timestamps, delta_sec);
+            animation.time()
+        };
 
+        let clip = &self.clip;
         entity
             .get_component_mut::<RenderObject>()
             .unwrap()
             .update_vertices(&|vertices: &mut VertexBuffer| {
-                let vertex_buffer = self.vertices.get(frame_index).unwrap();
-                let next_vertex_buffer = self.vertices.get(next_frame_index).unwrap();
-
-                for i in 0..self.vertices.get(frame_index).unwrap().count() {
-                    let position = vertex_buffer.position(i).unwrap();
-                    let next_position = next_vertex_buffer.position(i).unwrap();
-                    let tex_coord = vertex_buffer.tex_coord(i).unwrap();
-
-                    vertices.set_component(i, VertexComponents::POSITION, |p: &mut Vec3| {
-                        p.x = position.x * (1. - percentile) + next_position.x * percentile;
-                        p.y = position.y * (1. - percentile) + next_position.y * percentile;
-                        p.z = position.z * (1. - percentile) + next_position.z * percentile;
-                    });
-                    vertices.set_component(i, VertexComponents::TEXCOORD, |t: &mut Vec2| {
-                        t.x = tex_coord.x;
-                        t.y = tex_coord.y;
-                    });
-                }
+                clip.fill_vertices(anim_time, vertices);
             });
-
-        self.last_anim_time = anim_time;
     }
 }
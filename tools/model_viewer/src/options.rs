@@ -0,0 +1,261 @@
+use crate::playback::PlaybackOptions;
+use opengb::cache::MemoryBudgets;
+use opengb::fog::FogParams;
+use opengb::material::LightMapMode;
+use opengb::role::RoleCommand;
+use opengb::settings::{GraphicsProfile, GraphicsSettings};
+use opengb::sprite::SpriteAtlas;
+use radiance::math::Vec3;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct ViewerOptions {
+    pub lit: bool,
+    pub lightmap_mode: LightMapMode,
+    pub diagnostics: bool,
+    pub shader_overrides: Option<PathBuf>,
+    pub sky_texture: Option<PathBuf>,
+    pub sprite: Option<(PathBuf, SpriteAtlas)>,
+    pub particle_texture: Option<PathBuf>,
+    pub graphics: GraphicsSettings,
+    pub perf_report: Option<PathBuf>,
+    pub fog: FogParams,
+    pub texture_animations: Option<PathBuf>,
+    pub water: Option<PathBuf>,
+    pub material_overrides: Option<PathBuf>,
+    pub export_materials: Option<PathBuf>,
+    pub isolate: Option<String>,
+    pub export_scene: Option<PathBuf>,
+    pub cycle_sec: Option<f32>,
+    pub playback: PlaybackOptions,
+    pub texture_cache: Option<PathBuf>,
+    pub show_bounds: bool,
+    pub show_normals: bool,
+    pub uv_checker: bool,
+    pub inspect: bool,
+    pub hidden_meshes: Vec<usize>,
+    pub role: Option<PathBuf>,
+    pub role_route: Vec<RoleCommand>,
+    pub role_interpolation: bool,
+}
+
+impl ViewerOptions {
+    pub fn from_args() -> Self {
+        let mut options = ViewerOptions {
+            lit: false,
+            lightmap_mode: LightMapMode::Combined,
+            diagnostics: false,
+            shader_overrides: None,
+            sky_texture: None,
+            sprite: None,
+            particle_texture: None,
+            graphics: GraphicsSettings::for_profile(GraphicsProfile::Normal),
+            perf_report: None,
+            fog: FogParams::none(),
+            texture_animations: None,
+            water: None,
+            material_overrides: None,
+            export_materials: None,
+            isolate: None,
+            export_scene: None,
+            cycle_sec: None,
+            playback: PlaybackOptions::new(),
+            texture_cache: None,
+            show_bounds: false,
+            show_normals: false,
+            uv_checker: false,
+            inspect: false,
+            hidden_meshes: vec![],
+            role: None,
+            role_route: vec![],
+            role_interpolation: true,
+        };
+
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                "--lit" => options.lit = true,
+                "--lightmap-only" => options.lightmap_mode = LightMapMode::LightMapOnly,
+                "--diffuse-only" => options.lightmap_mode = LightMapMode::DiffuseOnly,
+                "--diagnostics" => options.diagnostics = true,
+                _ if arg.starts_with("--shader-overrides=") => {
+                    options.shader_overrides =
+                        Some(PathBuf::from(&arg["--shader-overrides=".len()..]))
+                }
+                _ if arg.starts_with("--sky=") => {
+                    options.sky_texture = Some(PathBuf::from(&arg["--sky=".len()..]))
+                }
+                _ if arg.starts_with("--sprite=") => {
+                    options.sprite = parse_sprite(&arg["--sprite=".len()..]);
+                    if options.sprite.is_none() {
+                        println!("Expected --sprite=<columns>x<rows>:<texture>");
+                    }
+                }
+                _ if arg.starts_with("--particles=") => {
+                    options.particle_texture = Some(PathBuf::from(&arg["--particles=".len()..]))
+                }
+                "--low" => options.graphics = GraphicsSettings::for_profile(GraphicsProfile::Low),
+                _ if arg.starts_with("--graphics-config=") => {
+                    let path = &arg["--graphics-config=".len()..];
+                    match GraphicsSettings::load_from_file(path) {
+                        Ok(graphics) => options.graphics = graphics,
+                        Err(e) => println!("Unable to load graphics config {}: {}", path, e),
+                    }
+                }
+                // Replaces the budgets of the graphics profile, so it goes
+                // after --low or --graphics-config
+                _ if arg.starts_with("--memory-budgets=") => {
+                    let path = &arg["--memory-budgets=".len()..];
+                    match MemoryBudgets::load_from_file(path) {
+                        Ok(budgets) => options.graphics.memory_budgets = budgets,
+                        Err(e) => println!("Unable to load memory budgets {}: {}", path, e),
+                    }
+                }
+                // Needs the frame stats collected with --diagnostics
+                _ if arg.starts_with("--perf-report=") => {
+                    options.diagnostics = true;
+                    options.perf_report = Some(PathBuf::from(&arg["--perf-report=".len()..]))
+                }
+                _ if arg.starts_with("--fog=") => {
+                    let path = &arg["--fog=".len()..];
+                    match FogParams::load_from_file(path) {
+                        Ok(fog) => options.fog = fog,
+                        Err(e) => println!("Unable to load fog {}: {}", path, e),
+                    }
+                }
+                _ if arg.starts_with("--texture-animations=") => {
+                    options.texture_animations =
+                        Some(PathBuf::from(&arg["--texture-animations=".len()..]))
+                }
+                _ if arg.starts_with("--water=") => {
+                    options.water = Some(PathBuf::from(&arg["--water=".len()..]))
+                }
+                _ if arg.starts_with("--material-overrides=") => {
+                    options.material_overrides =
+                        Some(PathBuf::from(&arg["--material-overrides=".len()..]))
+                }
+                // Writes the current textures of every sub-mesh as a
+                // starting point for --material-overrides
+                _ if arg.starts_with("--export-materials=") => {
+                    options.export_materials =
+                        Some(PathBuf::from(&arg["--export-materials=".len()..]))
+                }
+                // Only draws the sub-meshes with this diffuse texture
+                _ if arg.starts_with("--isolate=") => {
+                    options.isolate = Some(arg["--isolate=".len()..].to_lowercase())
+                }
+                // Writes where each model went, to be opened again as a
+                // .scene file
+                _ if arg.starts_with("--export-scene=") => {
+                    options.export_scene = Some(PathBuf::from(&arg["--export-scene=".len()..]))
+                }
+                // Moves on to the next model in the directory every so often,
+                // stopping at the last one
+                _ if arg.starts_with("--cycle=") => {
+                    options.cycle_sec = arg["--cycle=".len()..].parse().ok();
+                    if options.cycle_sec.is_none() {
+                        println!("Expected --cycle=<seconds>");
+                    }
+                }
+                // Keeps decoded textures for the next launch
+                _ if arg.starts_with("--texture-cache=") => {
+                    options.texture_cache = Some(PathBuf::from(&arg["--texture-cache=".len()..]))
+                }
+                // Animation playback of mv3 and cvd models
+                "--paused" => options.playback.paused = true,
+                _ if arg.starts_with("--speed=") => {
+                    match arg["--speed=".len()..].parse() {
+                        Ok(speed) => options.playback.speed = speed,
+                        Err(_) => println!("Expected --speed=<factor>"),
+                    }
+                }
+                _ if arg.starts_with("--frame=") => {
+                    options.playback.paused = true;
+                    options.playback.frame = arg["--frame=".len()..].parse().ok();
+                    if options.playback.frame.is_none() {
+                        println!("Expected --frame=<keyframe>");
+                    }
+                }
+                _ if arg.starts_with("--step=") => {
+                    options.playback.paused = true;
+                    options.playback.step_sec = arg["--step=".len()..].parse().ok();
+                    if options.playback.step_sec.is_none() {
+                        println!("Expected --step=<seconds>");
+                    }
+                }
+                // Debug views for checking loader output
+                "--show-bounds" => options.show_bounds = true,
+                "--show-normals" => options.show_normals = true,
+                "--uv-checker" => options.uv_checker = true,
+                // Prints the parsed file structure, numbering the sub-meshes
+                // for --hide-mesh
+                "--inspect" => options.inspect = true,
+                _ if arg.starts_with("--hide-mesh=") => {
+                    for index in arg["--hide-mesh=".len()..].split(',') {
+                        match index.trim().parse() {
+                            Ok(index) => options.hidden_meshes.push(index),
+                            Err(_) => println!("Expected --hide-mesh=<index>[,<index>]..."),
+                        }
+                    }
+                }
+                // A role directory with C01, C02 and C03 actions, walking
+                // the route given with --role-route
+                _ if arg.starts_with("--role=") => {
+                    options.role = Some(PathBuf::from(&arg["--role=".len()..]))
+                }
+                _ if arg.starts_with("--role-route=") => {
+                    options.role_route = parse_route(&arg["--role-route=".len()..]);
+                    if options.role_route.is_empty() {
+                        println!("Expected --role-route=<x>,<z>[,run][;<x>,<z>[,run]]...");
+                    }
+                }
+                // Draws the role at its latest movement step, which
+                // stutters when the frame rate isn't a multiple of it
+                "--no-role-interpolation" => options.role_interpolation = false,
+                _ => println!("Unknown argument {}", arg),
+            }
+        }
+
+        if !options.graphics.lit_materials {
+            options.lit = false;
+        }
+
+        if !options.graphics.lightmaps {
+            options.lightmap_mode = LightMapMode::DiffuseOnly;
+        }
+
+        options
+    }
+}
+
+fn parse_sprite(value: &str) -> Option<(PathBuf, SpriteAtlas)> {
+    let mut parts = value.splitn(2, ':');
+    let mut grid = parts.next()?.splitn(2, 'x');
+    let columns = grid.next()?.parse().ok()?;
+    let rows = grid.next()?.parse().ok()?;
+    let texture = PathBuf::from(parts.next()?);
+    Some((texture, SpriteAtlas::new(columns, rows)))
+}
+
+// Points on the ground relative to where the role starts, each walked to,
+// or run to when followed by `run`
+fn parse_route(value: &str) -> Vec<RoleCommand> {
+    let mut route = vec![];
+    for point in value.split(';') {
+        let parts: Vec<&str> = point.split(',').map(|p| p.trim()).collect();
+        let (x, z, run) = match parts.as_slice() {
+            [x, z] => (x.parse(), z.parse(), false),
+            [x, z, "run"] => (x.parse(), z.parse(), true),
+            _ => return vec![],
+        };
+
+        match (x, z) {
+            (Ok(x), Ok(z)) => route.push(RoleCommand::MoveTo {
+                target: Vec3::new(x, 0., z),
+                run,
+            }),
+            _ => return vec![],
+        }
+    }
+
+    route
+}
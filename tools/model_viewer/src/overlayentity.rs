@@ -0,0 +1,148 @@
+use opengb::cache::CacheReport;
+use opengb::material::create_screen_material;
+use opengb::texture_cache::texture_memory_report;
+use opengb::ui::overlay::UiLayer;
+use opengb::ui::ScreenSpace;
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::{RenderObject, VertexBuffer};
+use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
+use std::collections::VecDeque;
+
+const FRAME_COUNT: usize = 120;
+const BAR_WIDTH: f32 = 2.;
+const GRAPH_HEIGHT: f32 = 60.;
+// Frames taking this long fill the whole graph height
+const GRAPH_MAX_SEC: f32 = 1. / 20.;
+const BUDGET_BAR_WIDTH: f32 = 200.;
+const BUDGET_BAR_HEIGHT: f32 = 8.;
+
+// Draws the recent frame times as a bar graph in the top left corner.
+pub struct FrameGraphEntity {
+    frame_times: VecDeque<f32>,
+}
+
+impl FrameGraphEntity {
+    pub fn new() -> Self {
+        FrameGraphEntity {
+            frame_times: std::iter::repeat(0.).take(FRAME_COUNT).collect(),
+        }
+    }
+
+    fn build_layer(&self) -> UiLayer {
+        let mut layer = UiLayer::new(ScreenSpace::new(800., 600.));
+        layer.draw_rect(
+            &Vec2::new(8., 8.),
+            &Vec2::new(FRAME_COUNT as f32 * BAR_WIDTH + 8., GRAPH_HEIGHT + 8.),
+            &Vec3::new(0.1, 0.1, 0.1),
+        );
+
+        for (i, &delta_sec) in self.frame_times.iter().enumerate() {
+            let height = (delta_sec / GRAPH_MAX_SEC).min(1.) * GRAPH_HEIGHT;
+            let color = if delta_sec > 1. / 30. {
+                Vec3::new(0.9, 0.2, 0.2)
+            } else {
+                Vec3::new(0.2, 0.9, 0.2)
+            };
+
+            layer.draw_rect(
+                &Vec2::new(12. + i as f32 * BAR_WIDTH, 12. + GRAPH_HEIGHT - height),
+                &Vec2::new(BAR_WIDTH, height),
+                &color,
+            );
+        }
+
+        layer
+    }
+}
+
+impl EntityCallbacks for FrameGraphEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let layer = self.build_layer();
+        let batch = &layer.batches()[0];
+        entity.add_component(RenderObject::new_host_dynamic_with_data(
+            batch.to_vertex_buffer(layer.screen()),
+            batch.indices(),
+            Box::new(create_screen_material(&batch.texture_path)),
+        ));
+    }
+
+    fn on_updating<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>, delta_sec: f32) {
+        self.frame_times.pop_front();
+        self.frame_times.push_back(delta_sec);
+
+        let layer = self.build_layer();
+        entity
+            .get_component_mut::<RenderObject>()
+            .unwrap()
+            .update_vertices(&|vertices: &mut VertexBuffer| {
+                layer.batches()[0].fill_vertex_buffer(layer.screen(), vertices);
+            });
+    }
+}
+
+// Shows the texture cache usage as an amber bar in the top right corner once
+// the cache runs over its budget, and prints its report when that happens.
+pub struct BudgetWarningEntity {
+    warned: bool,
+}
+
+impl BudgetWarningEntity {
+    pub fn new() -> Self {
+        BudgetWarningEntity { warned: false }
+    }
+
+    // Always the same two rects, zero-sized while there is nothing to show,
+    // so that the vertex buffer keeps its size
+    fn build_layer(report: Option<&CacheReport>) -> UiLayer {
+        let mut layer = UiLayer::new(ScreenSpace::new(800., 600.));
+        let position = Vec2::new(800. - BUDGET_BAR_WIDTH - 12., 12.);
+        let (background, usage) = match report.filter(|r| r.needs_warning()) {
+            Some(report) => {
+                let fraction = report.usage as f32 / report.budget.max(1) as f32;
+                (
+                    Vec2::new(BUDGET_BAR_WIDTH + 4., BUDGET_BAR_HEIGHT + 4.),
+                    Vec2::new(fraction.min(1.) * BUDGET_BAR_WIDTH, BUDGET_BAR_HEIGHT),
+                )
+            }
+            None => (Vec2::new(0., 0.), Vec2::new(0., 0.)),
+        };
+
+        layer.draw_rect(
+            &Vec2::new(position.x - 2., position.y - 2.),
+            &background,
+            &Vec3::new(0.1, 0.1, 0.1),
+        );
+        layer.draw_rect(&position, &usage, &Vec3::new(0.95, 0.65, 0.1));
+        layer
+    }
+}
+
+impl EntityCallbacks for BudgetWarningEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let layer = Self::build_layer(None);
+        let batch = &layer.batches()[0];
+        entity.add_component(RenderObject::new_host_dynamic_with_data(
+            batch.to_vertex_buffer(layer.screen()),
+            batch.indices(),
+            Box::new(create_screen_material(&batch.texture_path)),
+        ));
+    }
+
+    fn on_updating<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>, _delta_sec: f32) {
+        let report = texture_memory_report();
+        if let Some(report) = report.as_ref().filter(|r| r.needs_warning()) {
+            if !self.warned {
+                println!("Over the memory budget: {}", report);
+                self.warned = true;
+            }
+        }
+
+        let layer = Self::build_layer(report.as_ref());
+        entity
+            .get_component_mut::<RenderObject>()
+            .unwrap()
+            .update_vertices(&|vertices: &mut VertexBuffer| {
+                layer.batches()[0].fill_vertex_buffer(layer.screen(), vertices);
+            });
+    }
+}
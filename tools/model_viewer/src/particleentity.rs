@@ -0,0 +1,58 @@
+use opengb::material::create_particle_material;
+use opengb::particles::{EmitterDesc, ParticleEmitter};
+use radiance::math::Vec3;
+use radiance::rendering::{RenderObject, VertexBuffer};
+use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
+use std::path::PathBuf;
+
+pub struct ParticleEntity {
+    texture_path: PathBuf,
+    emitter: ParticleEmitter,
+}
+
+impl ParticleEntity {
+    pub fn new(texture_path: PathBuf) -> Self {
+        let desc = EmitterDesc {
+            acceleration: Vec3::new(0., -20., 0.),
+            start_color: Vec3::new(1., 0.8, 0.4),
+            end_color: Vec3::new(0.4, 0.1, 0.),
+            ..EmitterDesc::default()
+        };
+
+        ParticleEntity::with_desc(texture_path, desc)
+    }
+
+    pub fn with_desc(texture_path: PathBuf, desc: EmitterDesc) -> Self {
+        ParticleEntity {
+            texture_path,
+            emitter: ParticleEmitter::new(desc, 0x2545f491),
+        }
+    }
+}
+
+impl EntityCallbacks for ParticleEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let mut vertices = VertexBuffer::new(
+            ParticleEmitter::vertex_components(),
+            self.emitter.desc().max_particles * 4,
+        );
+        self.emitter.fill_vertex_buffer(&mut vertices);
+
+        entity.add_component(RenderObject::new_host_dynamic_with_data(
+            vertices,
+            self.emitter.indices(),
+            Box::new(create_particle_material(&self.texture_path)),
+        ));
+    }
+
+    fn on_updating<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>, delta_sec: f32) {
+        self.emitter.update(delta_sec);
+        let emitter = &self.emitter;
+        entity
+            .get_component_mut::<RenderObject>()
+            .unwrap()
+            .update_vertices(&|vertices: &mut VertexBuffer| {
+                emitter.fill_vertex_buffer(vertices);
+            });
+    }
+}
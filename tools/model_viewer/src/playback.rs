@@ -0,0 +1,190 @@
+use opengb::animation::{keyframe_index, KeyframeAnimation};
+use opengb::material::create_screen_material;
+use opengb::ui::overlay::UiLayer;
+use opengb::ui::ScreenSpace;
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::{RenderObject, VertexBuffer};
+use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
+use std::cell::Cell;
+use std::rc::Rc;
+
+const SCRUBBER_WIDTH: f32 = 600.;
+const SCRUBBER_HEIGHT: f32 = 12.;
+
+#[derive(Debug, Clone)]
+pub struct PlaybackOptions {
+    pub paused: bool,
+    pub speed: f32,
+    // Keyframe to start from
+    pub frame: Option<usize>,
+    // Steps one keyframe forward every so many seconds while paused
+    pub step_sec: Option<f32>,
+}
+
+impl PlaybackOptions {
+    pub fn new() -> Self {
+        PlaybackOptions {
+            paused: false,
+            speed: 1.,
+            frame: None,
+            step_sec: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackStatus {
+    pub frame: usize,
+    pub frame_count: usize,
+    pub time: f32,
+    pub duration: f32,
+}
+
+// Applies the playback options to an animated model and reports where its
+// timeline is, for the scrubber and the console.
+pub struct PlaybackControls {
+    options: PlaybackOptions,
+    status: Rc<Cell<Option<PlaybackStatus>>>,
+    step_timer: f32,
+    last_frame: Option<usize>,
+}
+
+impl PlaybackControls {
+    pub fn new(options: PlaybackOptions, status: Rc<Cell<Option<PlaybackStatus>>>) -> Self {
+        PlaybackControls {
+            options,
+            status,
+            step_timer: 0.,
+            last_frame: None,
+        }
+    }
+
+    pub fn start(&mut self, animation: &mut KeyframeAnimation, timestamps: &[f32]) {
+        animation.set_speed(self.options.speed);
+        if let Some(frame) = self.options.frame {
+            if let Some(&time) = timestamps.get(frame) {
+                animation.set_time(time);
+            } else {
+                println!("Frame {} is out of {} keyframes", frame, timestamps.len());
+            }
+        }
+
+        if self.options.paused {
+            animation.pause();
+        }
+
+        self.report(animation, timestamps);
+    }
+
+    pub fn update(
+        &mut self,
+        animation: &mut KeyframeAnimation,
+        timestamps: &[f32],
+        delta_sec: f32,
+    ) {
+        if let Some(step_sec) = self.options.step_sec {
+            if !animation.is_playing() {
+                self.step_timer += delta_sec;
+                if self.step_timer >= step_sec {
+                    self.step_timer = 0.;
+                    animation.step_keyframes(timestamps, 1);
+                }
+            }
+        }
+
+        animation.update(delta_sec);
+        self.report(animation, timestamps);
+    }
+
+    fn report(&mut self, animation: &KeyframeAnimation, timestamps: &[f32]) {
+        let status = PlaybackStatus {
+            frame: keyframe_index(timestamps, animation.time()),
+            frame_count: timestamps.len(),
+            time: animation.time(),
+            duration: animation.duration(),
+        };
+
+        // Printed only while paused, where frames change one at a time
+        if !animation.is_playing() && self.last_frame != Some(status.frame) {
+            println!(
+                "frame {}/{} at {:.3}s of {:.3}s",
+                status.frame + 1,
+                status.frame_count,
+                status.time,
+                status.duration
+            );
+        }
+
+        self.last_frame = Some(status.frame);
+        self.status.set(Some(status));
+    }
+}
+
+// Draws the timeline of the animated model along the bottom of the screen,
+// marking the keyframe being shown.
+pub struct ScrubberEntity {
+    status: Rc<Cell<Option<PlaybackStatus>>>,
+}
+
+impl ScrubberEntity {
+    pub fn new(status: Rc<Cell<Option<PlaybackStatus>>>) -> Self {
+        ScrubberEntity { status }
+    }
+
+    // Always the same three rects, so the vertex buffer keeps its size
+    fn build_layer(&self) -> UiLayer {
+        let mut layer = UiLayer::new(ScreenSpace::new(800., 600.));
+        let origin = Vec2::new(100., 570.);
+        let (progress, frame_progress) = match self.status.get() {
+            Some(status) if status.duration > 0. && status.frame_count > 1 => (
+                status.time / status.duration,
+                status.frame as f32 / (status.frame_count - 1) as f32,
+            ),
+            _ => (0., 0.),
+        };
+
+        layer.draw_rect(
+            &origin,
+            &Vec2::new(SCRUBBER_WIDTH, SCRUBBER_HEIGHT),
+            &Vec3::new(0.1, 0.1, 0.1),
+        );
+        layer.draw_rect(
+            &origin,
+            &Vec2::new(SCRUBBER_WIDTH * progress, SCRUBBER_HEIGHT),
+            &Vec3::new(0.2, 0.6, 0.9),
+        );
+        // Marks the keyframe being shown
+        layer.draw_rect(
+            &Vec2::new(
+                origin.x + SCRUBBER_WIDTH * frame_progress - 1.,
+                origin.y - 4.,
+            ),
+            &Vec2::new(2., SCRUBBER_HEIGHT + 8.),
+            &Vec3::new(1., 0.85, 0.4),
+        );
+
+        layer
+    }
+}
+
+impl EntityCallbacks for ScrubberEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let layer = self.build_layer();
+        let batch = &layer.batches()[0];
+        entity.add_component(RenderObject::new_host_dynamic_with_data(
+            batch.to_vertex_buffer(layer.screen()),
+            batch.indices(),
+            Box::new(create_screen_material(&batch.texture_path)),
+        ));
+    }
+
+    fn on_updating<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>, _delta_sec: f32) {
+        let layer = self.build_layer();
+        entity
+            .get_component_mut::<RenderObject>()
+            .unwrap()
+            .update_vertices(&|vertices: &mut VertexBuffer| {
+                layer.batches()[0].fill_vertex_buffer(layer.screen(), vertices);
+            });
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+const MODEL_EXTENSIONS: &[&str] = &["mv3", "pol", "cvd", "scene"];
+
+// The models the viewer can step through without being relaunched
+pub struct ModelPlaylist {
+    paths: Vec<String>,
+    index: usize,
+}
+
+impl ModelPlaylist {
+    pub fn from_files(paths: Vec<String>) -> Self {
+        ModelPlaylist { paths, index: 0 }
+    }
+
+    // Every model next to `path`, sorted by name, starting at `path`
+    pub fn from_directory(path: &str) -> Self {
+        let dir = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+        let mut paths: Vec<String> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| is_model(p))
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect(),
+            Err(e) => {
+                println!("Unable to list {:?}: {}", dir, e);
+                vec![]
+            }
+        };
+
+        paths.sort();
+        let index = match paths.iter().position(|p| Path::new(p) == Path::new(path)) {
+            Some(index) => index,
+            None => {
+                paths.insert(0, path.to_owned());
+                0
+            }
+        };
+
+        ModelPlaylist { paths, index }
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    pub fn current(&self) -> &str {
+        &self.paths[self.index]
+    }
+
+    // None at the end; the playlist doesn't wrap around
+    pub fn next(&mut self) -> Option<&str> {
+        if self.index + 1 < self.paths.len() {
+            self.index += 1;
+            Some(self.current())
+        } else {
+            None
+        }
+    }
+}
+
+fn is_model(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .map(|e| {
+                let extension = e.to_string_lossy().to_lowercase();
+                MODEL_EXTENSIONS.contains(&extension.as_str())
+            })
+            .unwrap_or(false)
+}
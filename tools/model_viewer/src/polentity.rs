@@ -1,20 +1,50 @@
+use super::options::ViewerOptions;
+use opengb::fog::FogParams;
+use opengb::geometry::{remap_indices, Aabb};
 use opengb::loaders::polloader::*;
-use opengb::material::LightMapMaterial;
+use opengb::material::{
+    create_lit_material, create_simple_material, create_vertex_color_material, create_water_material, CustomMaterial,
+    CustomShader, LightMapMaterial, LightMapMode,
+};
+use opengb::material_overrides::MaterialOverride;
+use opengb::shader_registry::ShaderRegistry;
+use opengb::texture_animation::TextureAnimation;
+use opengb::water::WaterSurface;
 use radiance::math::{Vec2, Vec3};
-use radiance::rendering::{RenderObject, SimpleMaterial, VertexBuffer, VertexComponents};
+use radiance::rendering::{Material, RenderObject, VertexBuffer, VertexComponents};
 use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
 use std::path::PathBuf;
 
 pub struct PolModelEntity {
     texture_paths: Vec<PathBuf>,
-    vertices: VertexBuffer,
+    vertices: Option<VertexBuffer>,
     indices: Vec<u32>,
+    lit: bool,
+    vertex_colors: bool,
+    lightmap_mode: LightMapMode,
+    bounds: Aabb,
+    custom_shader: Option<CustomShader>,
+    fog: FogParams,
+    texture_animation: Option<(TextureAnimation, VertexComponents, Vec<Vec2>)>,
+    water: Option<(WaterSurface, Vec<Vec2>)>,
+    animation_time: f32,
     // pol: PolFile,
 }
 
 impl PolModelEntity {
-    pub fn new(all_vertices: &Vec<PolVertex>, material: &PolMaterialInfo, path: &str) -> Self {
-        let texture_paths: Vec<PathBuf> = material
+    // All parts must use the same textures. They are merged into a single
+    // vertex buffer so that they are drawn with one call.
+    pub fn new(
+        parts: &[(&PolMesh, &PolMaterialInfo)],
+        path: &str,
+        options: &ViewerOptions,
+        shader_override: Option<(&ShaderRegistry, &str)>,
+        texture_animation: Option<TextureAnimation>,
+        water: Option<WaterSurface>,
+        material_override: Option<MaterialOverride>,
+    ) -> Self {
+        let mut texture_paths: Vec<PathBuf> = parts[0]
+            .1
             .texture_names
             .iter()
             .map(|name| {
@@ -36,72 +66,172 @@ impl PolModelEntity {
                     .unwrap()
             })
             .collect();
+        if let (Some(texture), Some(diffuse)) = (
+            material_override.and_then(|o| o.texture),
+            texture_paths.last_mut(),
+        ) {
+            *diffuse = texture;
+        }
 
-        let components = if texture_paths.len() == 1 {
+        let lit = options.lit
+            && water.is_none()
+            && texture_paths.len() == 1
+            && parts
+                .iter()
+                .all(|(mesh, _)| mesh.vertex_type.has(PolVertexComponents::NORMAL));
+        // Vertex colors take the NORMAL slot, so lit meshes go without them
+        let vertex_colors = !lit
+            && water.is_none()
+            && parts
+                .iter()
+                .any(|(mesh, _)| mesh.vertex_type.has(PolVertexComponents::DIFFUSE));
+        let mut components = if water.is_some() {
+            WaterSurface::vertex_components()
+        } else if lit {
+            VertexComponents::POSITION | VertexComponents::NORMAL | VertexComponents::TEXCOORD
+        } else if texture_paths.len() == 1 {
             VertexComponents::POSITION | VertexComponents::TEXCOORD
         } else {
             VertexComponents::POSITION | VertexComponents::TEXCOORD | VertexComponents::TEXCOORD2
         };
+        if vertex_colors {
+            components = components | VertexComponents::NORMAL;
+        }
 
-        let mut index_map = std::collections::HashMap::new();
-        let mut reversed_index = vec![];
-        let mut get_new_index = |index: u16| -> u32 {
-            if index_map.contains_key(&index) {
-                index_map[&index]
-            } else {
-                let new_index = reversed_index.len() as u32;
-                reversed_index.push(index as usize);
-                index_map.insert(index, new_index);
-                new_index
+        let custom_shader = shader_override.and_then(|(registry, name)| {
+            let shader = registry.create_shader(name, components);
+            if shader.is_none() {
+                println!("Shader variant {} is unavailable, using the default material", name);
             }
-        };
 
-        let mut indices: Vec<u32> = vec![];
-        for t in &material.triangles {
-            indices.push(get_new_index(t.indices[0]));
-            indices.push(get_new_index(t.indices[1]));
-            indices.push(get_new_index(t.indices[2]));
+            shader
+        });
+
+        let mut indices = vec![];
+        let mut remapped_parts = vec![];
+        let mut vertex_count = 0;
+        let mut bounds = Aabb::empty();
+        for (mesh, material) in parts {
+            let (part_indices, reversed_index) =
+                remap_indices(material.triangles.iter().map(|t| &t.indices));
+            indices.extend(part_indices.iter().map(|&i| i + vertex_count as u32));
+            vertex_count += reversed_index.len();
+            bounds = bounds.union(&Aabb::from_arrays(&mesh.aabb_min, &mesh.aabb_max));
+            remapped_parts.push((mesh, reversed_index));
         }
 
-        let mut vertices = VertexBuffer::new(components, reversed_index.len());
-
-        for i in 0..reversed_index.len() {
-            let vert = &all_vertices[reversed_index[i]];
-            vertices.set_data(
-                i,
-                Some(&Vec3::new(
-                    vert.position.x,
-                    vert.position.y,
-                    vert.position.z,
-                )),
-                None,
-                Some(&Vec2::new(vert.tex_coord.u, vert.tex_coord.v)),
-                vert.tex_coord2
-                    .as_ref()
-                    .and_then(|tex_coord2| Some(Vec2::new(tex_coord2.u, tex_coord2.v)))
-                    .as_ref(),
-            );
+        let mut vertices = VertexBuffer::new(components, vertex_count);
+        let mut offset = 0;
+        for (mesh, reversed_index) in remapped_parts {
+            mesh.fill_vertex_buffer(&mut vertices, offset, &reversed_index);
+            if vertex_colors {
+                mesh.fill_vertex_colors(&mut vertices, offset, &reversed_index);
+            }
+
+            offset += reversed_index.len();
         }
 
+        // The diffuse texture is the last one, sampled with TEXCOORD2 when
+        // there is a lightmap
+        let use_tex_coord2 = texture_paths.len() > 1;
+        let base_tex_coords = || -> Vec<Vec2> {
+            parts
+                .iter()
+                .flat_map(|(mesh, material)| {
+                    let (_, reversed_index) =
+                        remap_indices(material.triangles.iter().map(|t| &t.indices));
+                    reversed_index.into_iter().map(move |i| {
+                        let vertex = &mesh.vertices[i];
+                        match (&vertex.tex_coord2, use_tex_coord2) {
+                            (Some(t), true) => Vec2::new(t.u, t.v),
+                            _ => Vec2::new(vertex.tex_coord.u, vertex.tex_coord.v),
+                        }
+                    })
+                })
+                .collect()
+        };
+
+        // Water animates its texture coordinates by itself
+        let texture_animation = texture_animation
+            .filter(|_| water.is_none())
+            .map(|animation| {
+                let component = if use_tex_coord2 {
+                    VertexComponents::TEXCOORD2
+                } else {
+                    VertexComponents::TEXCOORD
+                };
+
+                (animation, component, base_tex_coords())
+            });
+
+        let water = water.map(|surface| {
+            let base_tex_coords = base_tex_coords();
+            surface.fill_vertex_buffer(&mut vertices, &base_tex_coords, 0.);
+            (surface, base_tex_coords)
+        });
+
         PolModelEntity {
             texture_paths,
-            vertices,
+            vertices: Some(vertices),
             indices,
+            lit,
+            vertex_colors,
+            lightmap_mode: options.lightmap_mode,
+            bounds,
+            custom_shader,
+            fog: options.fog,
+            texture_animation,
+            water,
+            animation_time: 0.,
         }
     }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    pub fn texture_paths(&self) -> &[PathBuf] {
+        &self.texture_paths
+    }
 }
 
 impl EntityCallbacks for PolModelEntity {
     fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
-        entity.add_component(RenderObject::new_with_data(
-            self.vertices.clone(),
-            self.indices.clone(),
-            if self.texture_paths.len() == 1 {
-                Box::new(SimpleMaterial::new(&self.texture_paths[0]))
-            } else {
-                Box::new(LightMapMaterial::new(&self.texture_paths))
-            },
-        ));
+        let material: Box<dyn Material> = if let Some(shader) = self.custom_shader.take() {
+            Box::new(
+                CustomMaterial::new("custom_material", shader, &self.texture_paths)
+                    .with_fog(&self.fog),
+            )
+        } else if let Some((surface, _)) = &self.water {
+            let diffuse = self.texture_paths.last().unwrap();
+            Box::new(create_water_material(diffuse, surface).with_fog(&self.fog))
+        } else if self.lit {
+            Box::new(create_lit_material(&self.texture_paths[0]).with_fog(&self.fog))
+        } else if self.texture_paths.len() == 1 && self.vertex_colors {
+            Box::new(create_vertex_color_material(&self.texture_paths[0]).with_fog(&self.fog))
+        } else if self.texture_paths.len() == 1 {
+            Box::new(create_simple_material(&self.texture_paths[0]).with_fog(&self.fog))
+        } else if self.vertex_colors {
+            Box::new(
+                LightMapMaterial::new_with_mode(&self.texture_paths, self.lightmap_mode)
+                    .with_vertex_colors()
+                    .with_fog(&self.fog),
+            )
+        } else {
+            Box::new(
+                LightMapMaterial::new_with_mode(&self.texture_paths, self.lightmap_mode)
+                    .with_fog(&self.fog),
+            )
+        };
+
+        let vertices = self.vertices.take().unwrap();
+        let indices = std::mem::take(&mut self.indices);
+        entity.add_component(if self.texture_animation.is_some() || self.water.is_some() {
+            RenderObject::new_host_dynamic_with_data(vertices, indices, material)
+        } else {
+            RenderObject::new_with_data(vertices, indices, material)
+        });
+        entity.add_component(self.bounds);
     }
 
     fn on_updating<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>, delta_sec: f32) {
@@ -109,5 +239,27 @@ impl EntityCallbacks for PolModelEntity {
             &Vec3::new(0., 1., 0.),
             -0.2 * delta_sec * std::f32::consts::PI,
         );
+
+        if let Some((animation, component, base_tex_coords)) = &self.texture_animation {
+            self.animation_time += delta_sec;
+            let time = self.animation_time;
+            entity
+                .get_component_mut::<RenderObject>()
+                .unwrap()
+                .update_vertices(&|vertices: &mut VertexBuffer| {
+                    animation.fill_vertex_buffer(vertices, *component, base_tex_coords, time);
+                });
+        }
+
+        if let Some((surface, base_tex_coords)) = &self.water {
+            self.animation_time += delta_sec;
+            let time = self.animation_time;
+            entity
+                .get_component_mut::<RenderObject>()
+                .unwrap()
+                .update_vertices(&|vertices: &mut VertexBuffer| {
+                    surface.fill_vertex_buffer(vertices, base_tex_coords, time);
+                });
+        }
     }
 }
@@ -0,0 +1,192 @@
+use super::mv3entity::Mv3Clip;
+use opengb::interpolation::{FixedTimestep, InterpolatedTransform};
+use opengb::material::create_simple_material;
+use opengb::role::{RoleActions, RoleCommand, RoleController, RoleState};
+use radiance::math::{Quaternion, Vec3};
+use radiance::rendering::{RenderObject, VertexBuffer};
+use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Movement runs at this rate whatever the frame rate
+const STEP_SEC: f32 = 1. / 30.;
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+// A character walking a route over and over, playing the action of each
+// movement state. The route's targets are relative to where it starts.
+// Movement is simulated in fixed steps and drawn in between the last two
+// unless interpolation is turned off.
+pub struct RoleEntity {
+    controller: RoleController,
+    clips: HashMap<RoleState, Mv3Clip>,
+    route: Vec<RoleCommand>,
+    action_time: f32,
+    timestep: FixedTimestep,
+    interpolation: bool,
+    placed_position: Vec3,
+    placed_yaw: f32,
+}
+
+impl RoleEntity {
+    // None if the role has no idle action. Other missing actions fall back
+    // to idle.
+    pub fn new(
+        dir: &Path,
+        actions: &RoleActions,
+        start: Vec3,
+        route: &[RoleCommand],
+    ) -> Option<Self> {
+        let idle_path = find_action(dir, actions.action(RoleState::Idle))?;
+        let idle = match Mv3Clip::load(&idle_path.to_string_lossy()) {
+            Ok(idle) => idle,
+            Err(e) => {
+                println!("Unable to load {:?}: {}", idle_path, e);
+                return None;
+            }
+        };
+        let vertex_count = idle.vertices[0].count();
+        let mut clips = HashMap::new();
+        for &state in &[RoleState::Walk, RoleState::Run] {
+            let path = match find_action(dir, actions.action(state)) {
+                Some(path) => path,
+                None => {
+                    println!("No {} action in {:?}", actions.action(state), dir);
+                    continue;
+                }
+            };
+
+            // Actions share the idle action's index buffer
+            match Mv3Clip::load(&path.to_string_lossy()) {
+                Ok(clip) if clip.vertices[0].count() == vertex_count => {
+                    clips.insert(state, clip);
+                }
+                Ok(_) => println!("{:?} doesn't match the idle action's mesh", path),
+                Err(e) => println!("Unable to load {:?}: {}", path, e),
+            }
+        }
+        clips.insert(RoleState::Idle, idle);
+
+        let route = route
+            .iter()
+            .map(|command| match *command {
+                RoleCommand::MoveTo { target, run } => RoleCommand::MoveTo {
+                    target: Vec3::new(target.x + start.x, start.y, target.z + start.z),
+                    run,
+                },
+                command => command,
+            })
+            .collect();
+
+        Some(RoleEntity {
+            controller: RoleController::new(start, 0.),
+            clips,
+            route,
+            action_time: 0.,
+            timestep: FixedTimestep::new(STEP_SEC, MAX_STEPS_PER_FRAME),
+            interpolation: true,
+            placed_position: Vec3::new(0., 0., 0.),
+            placed_yaw: 0.,
+        })
+    }
+
+    pub fn with_interpolation(mut self, interpolation: bool) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    fn clip(&self) -> &Mv3Clip {
+        self.clips
+            .get(&self.controller.state())
+            .unwrap_or_else(|| &self.clips[&RoleState::Idle])
+    }
+
+    // The transform only moves by steps, so the difference from what was
+    // applied last is applied
+    fn place<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>, position: Vec3, yaw: f32) {
+        entity.transform_mut().translate(&Vec3::new(
+            position.x - self.placed_position.x,
+            position.y - self.placed_position.y,
+            position.z - self.placed_position.z,
+        ));
+        entity
+            .transform_mut()
+            .rotate_local(&Vec3::new(0., 1., 0.), yaw - self.placed_yaw);
+        self.placed_position = position;
+        self.placed_yaw = yaw;
+    }
+}
+
+impl EntityCallbacks for RoleEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let idle = &self.clips[&RoleState::Idle];
+        entity.add_component(RenderObject::new_host_dynamic_with_data(
+            idle.vertices[0].clone(),
+            idle.indices.clone(),
+            Box::new(create_simple_material(&idle.texture_path)),
+        ));
+
+        let position = self.controller.position();
+        let yaw = self.controller.yaw();
+        let mut transform = InterpolatedTransform::new(position, yaw_rotation(yaw));
+        transform.set_enabled(self.interpolation);
+        entity.add_component(transform);
+        self.place(entity, position, yaw);
+    }
+
+    fn on_updating<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>, delta_sec: f32) {
+        let transform = entity.get_component_mut::<InterpolatedTransform>().unwrap();
+        for _ in 0..self.timestep.advance(delta_sec) {
+            if !self.controller.is_busy() {
+                for command in &self.route {
+                    self.controller.push(*command);
+                }
+            }
+
+            // Each action starts from its first frame
+            if self.controller.update(self.timestep.step()).is_some() {
+                self.action_time = 0.;
+            }
+
+            let yaw = self.controller.yaw();
+            transform.push(self.controller.position(), yaw_rotation(yaw));
+        }
+
+        let alpha = self.timestep.alpha();
+        let position = transform.position(alpha);
+        let yaw = rotation_yaw(&transform.rotation(alpha));
+        self.action_time += delta_sec;
+        self.place(entity, position, yaw);
+
+        let clip = self.clip();
+        let duration = clip.duration();
+        let time = if duration > 0. {
+            self.action_time % duration
+        } else {
+            0.
+        };
+        entity
+            .get_component_mut::<RenderObject>()
+            .unwrap()
+            .update_vertices(&|vertices: &mut VertexBuffer| {
+                clip.fill_vertices(time, vertices);
+            });
+    }
+}
+
+fn yaw_rotation(yaw: f32) -> Quaternion {
+    Quaternion::new(0., (yaw / 2.).sin(), 0., (yaw / 2.).cos())
+}
+
+fn rotation_yaw(rotation: &Quaternion) -> f32 {
+    2. * rotation.y.atan2(rotation.w)
+}
+
+// Game files differ in case between releases
+fn find_action(dir: &Path, action: &str) -> Option<PathBuf> {
+    let file_name = format!("{}.mv3", action).to_lowercase();
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_name().to_string_lossy().to_lowercase() == file_name)
+        .map(|e| e.path())
+}
@@ -1,65 +1,513 @@
 use super::mv3entity::Mv3ModelEntity;
 use super::polentity::PolModelEntity;
 use super::cvdentity::CvdModelEntity;
+use super::debuglinesentity::DebugLinesEntity;
+use super::flatmeshentity::FlatMeshEntity;
+use super::options::ViewerOptions;
+use super::overlayentity::{BudgetWarningEntity, FrameGraphEntity};
+use super::particleentity::ParticleEntity;
+use super::playback::{PlaybackControls, PlaybackStatus, ScrubberEntity};
+use super::roleentity::RoleEntity;
+use super::skyentity::SkyEntity;
+use super::spriteentity::SpriteEntity;
+use opengb::ambience::SceneAmbience;
 use opengb::loaders::polloader::*;
 use opengb::loaders::cvdloader::*;
+use opengb::debug_draw::DebugLines;
+use opengb::diagnostics::track_asset_load;
+use opengb::geometry::Aabb;
+use opengb::loaders::mv3loader::mv3_load_from_file;
+use opengb::material_overrides::MaterialOverrides;
+use opengb::model::{cvd_to_flat_meshes, mv3_to_flat_meshes, pol_to_flat_meshes, FlatMesh};
+use opengb::plugins::PluginRegistry;
+use opengb::role::RoleActions;
+use opengb::scene_desc::{EntityDesc, SceneDesc};
+use opengb::shader_registry::{ShaderOverrides, ShaderRegistry};
+use opengb::ui::inspector::{cvd_inspector_tree, mv3_inspector_tree, pol_inspector_tree};
+use opengb::texture_animation::TextureAnimations;
+use opengb::water::WaterSurfaces;
 use radiance::math::Vec3;
-use radiance::scene::{CoreEntity, CoreScene, Entity, SceneCallbacks};
+use radiance::scene::{CoreEntity, CoreScene, Entity, EntityCallbacks, SceneCallbacks};
+use rayon::prelude::*;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// The camera stays at the origin looking down -z, so models are moved in
+// front of it instead. They are framed for this vertical field of view.
+const FRAMING_FOV_Y: f32 = std::f32::consts::FRAC_PI_4;
 
 pub struct ModelViewerScene {
     pub path: String,
+    pub options: ViewerOptions,
+    pub plugins: PluginRegistry,
+    pub playback_status: Rc<Cell<Option<PlaybackStatus>>>,
+    // Set when a model fails to load, for the playlist to skip it
+    pub load_failed: Rc<Cell<bool>>,
 }
 
 impl SceneCallbacks for ModelViewerScene {
     fn on_loading<T: SceneCallbacks>(&mut self, scene: &mut CoreScene<T>) {
-        if self.path.to_lowercase().ends_with(".mv3") {
-            let mut entity = CoreEntity::new(Mv3ModelEntity::new(&self.path));
+        let mut placed = vec![];
+
+        // Added first so that it is drawn before everything else
+        if let Some(sky_texture) = &self.options.sky_texture {
+            scene.add_entity(CoreEntity::new(SkyEntity::new(sky_texture.clone())));
+        }
+
+        if self.path.to_lowercase().ends_with(".scene") {
+            match SceneDesc::load_from_file(&self.path) {
+                Ok(desc) => {
+                    for entity in &desc.entities {
+                        let mut options = self.options.clone();
+                        if entity.material_overrides.is_some() {
+                            options.material_overrides = entity.material_overrides.clone();
+                        }
+
+                        let path = entity.asset.to_string_lossy().into_owned();
+                        placed.extend(self.load_model(scene, &path, &options, Some(entity)));
+                    }
+                }
+                Err(e) => println!("Unable to load scene {}: {}", self.path, e),
+            }
+
+            // Sounds and lights have nothing to drive them here yet
+            match SceneAmbience::load_for_scene(&self.path) {
+                Ok(ambience) => {
+                    for emitter in &ambience.emitters {
+                        let p = emitter.position;
+                        let mut entity = CoreEntity::new(ParticleEntity::with_desc(
+                            emitter.texture.clone(),
+                            emitter.desc.clone(),
+                        ));
+                        entity.transform_mut().translate(&Vec3::new(p[0], p[1], p[2]));
+                        scene.add_entity(entity);
+                    }
+                }
+                Err(e) => println!("Unable to load the ambience for {}: {}", self.path, e),
+            }
+        } else {
+            let options = self.options.clone();
+            placed.extend(self.load_model(scene, &self.path, &options, None));
+        }
+
+        let animated = placed.iter().any(|e| {
+            let asset = e.asset.to_string_lossy().to_lowercase();
+            asset.ends_with(".mv3") || asset.ends_with(".cvd")
+        });
+        if let Some(export_path) = &self.options.export_scene {
+            if let Err(e) = (SceneDesc { entities: placed }).write_to_file(export_path) {
+                println!("Unable to export the scene {:?}: {}", export_path, e);
+            }
+        }
+
+        if let Some((texture, atlas)) = &self.options.sprite {
+            let mut entity = CoreEntity::new(SpriteEntity::new(texture.clone(), *atlas));
             entity
                 .transform_mut()
-                .translate(&Vec3::new(0., -40., -100.));
+                .translate(&Vec3::new(0., 0., -500.));
             scene.add_entity(entity);
-        } else if self.path.to_lowercase().ends_with(".pol") {
-            let pol = pol_load_from_file(&self.path).unwrap();
-            for mesh in &pol.meshes {
+        }
+
+        if let Some(texture) = &self.options.particle_texture {
+            let mut entity = CoreEntity::new(ParticleEntity::new(texture.clone()));
+            entity
+                .transform_mut()
+                .translate(&Vec3::new(0., -100., -500.));
+            scene.add_entity(entity);
+        }
+
+        if let Some(role) = &self.options.role {
+            let start = Vec3::new(0., -100., -500.);
+            match RoleEntity::new(role, &RoleActions::new(), start, &self.options.role_route) {
+                Some(entity) => scene.add_entity(CoreEntity::new(
+                    entity.with_interpolation(self.options.role_interpolation),
+                )),
+                None => println!("No idle action found in {:?}", role),
+            }
+        }
+
+        // Added last so that it is drawn over the scene
+        if animated {
+            scene.add_entity(CoreEntity::new(ScrubberEntity::new(
+                self.playback_status.clone(),
+            )));
+        }
+
+        if self.options.diagnostics {
+            scene.add_entity(CoreEntity::new(FrameGraphEntity::new()));
+        }
+
+        scene.add_entity(CoreEntity::new(BudgetWarningEntity::new()));
+    }
+}
+
+impl ModelViewerScene {
+    // Loads the model again, in a form the debug views can read
+    fn flat_meshes(&self, path: &str) -> Vec<FlatMesh> {
+        let lower = path.to_lowercase();
+        let meshes = if lower.ends_with(".pol") {
+            pol_load_from_file(path).map(|pol| pol_to_flat_meshes(&pol))
+        } else if lower.ends_with(".cvd") {
+            cvd_load_from_file(path).map(|cvd| cvd_to_flat_meshes(&cvd))
+        } else if lower.ends_with(".mv3") {
+            mv3_load_from_file(path).map(|mv3| mv3_to_flat_meshes(&mv3))
+        } else {
+            self.plugins.load_from_file(path)
+        };
+
+        meshes.unwrap_or_else(|e| {
+            println!("Unable to load {} for the debug views: {}", path, e);
+            vec![]
+        })
+    }
+
+    // The loaders still panic on some malformed files, which would take the
+    // viewer down with them
+    fn load_file<R, F: FnOnce() -> Result<R, Box<dyn Error>>>(
+        &self,
+        path: &str,
+        load: F,
+    ) -> Option<R> {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            track_asset_load(path, load).map_err(|e| e.to_string())
+        }));
+        match result.unwrap_or_else(|_| Err("the loader panicked".to_owned())) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                println!("Unable to load {}: {}", path, e);
+                self.load_failed.set(true);
+                None
+            }
+        }
+    }
+
+    fn playback_controls(&self, options: &ViewerOptions) -> PlaybackControls {
+        PlaybackControls::new(options.playback.clone(), self.playback_status.clone())
+    }
+
+    // Places the model as described by `placement`, or frames it for the
+    // camera. Returns where it went.
+    fn load_model<T: SceneCallbacks>(
+        &self,
+        scene: &mut CoreScene<T>,
+        path: &str,
+        options: &ViewerOptions,
+        placement: Option<&EntityDesc>,
+    ) -> Option<EntityDesc> {
+        let (position, rotation_y) = if path.to_lowercase().ends_with(".mv3") {
+            // Mv3ModelEntity loads the file itself, and draws all models
+            if options.inspect {
+                match mv3_load_from_file(path) {
+                    Ok(mv3) => print!("{}", mv3_inspector_tree(path, &mv3)),
+                    Err(e) => println!("Unable to inspect {}: {}", path, e),
+                }
+            }
+
+            let entity = self.load_file(path, || {
+                Mv3ModelEntity::new(path, self.playback_controls(options))
+            })?;
+            add_framed_entities(scene, vec![entity], Mv3ModelEntity::bounds, placement, options)
+        } else if path.to_lowercase().ends_with(".pol") {
+            let pol = self.load_file(path, || pol_load_from_file(path))?;
+            if options.inspect {
+                print!("{}", pol_inspector_tree(path, &pol));
+            }
+
+            let mut shader_registry = ShaderRegistry::new();
+            let shader_overrides = options.shader_overrides.as_ref().and_then(|p| {
+                ShaderOverrides::load_from_file(p)
+                    .map_err(|e| println!("Unable to load shader overrides {:?}: {}", p, e))
+                    .ok()
+            });
+            if let Some(overrides) = &shader_overrides {
+                overrides.load_shaders(&mut shader_registry);
+            }
+
+            let texture_animations = options.texture_animations.as_ref().and_then(|p| {
+                TextureAnimations::load_from_file(p)
+                    .map_err(|e| println!("Unable to load texture animations {:?}: {}", p, e))
+                    .ok()
+            });
+            let water_surfaces = options.water.as_ref().and_then(|p| {
+                WaterSurfaces::load_from_file(p)
+                    .map_err(|e| println!("Unable to load water surfaces {:?}: {}", p, e))
+                    .ok()
+            });
+            let material_overrides = options.material_overrides.as_ref().and_then(|p| {
+                MaterialOverrides::load_from_file(p)
+                    .map_err(|e| println!("Unable to load material overrides {:?}: {}", p, e))
+                    .ok()
+            });
+
+            // Group sub-meshes sharing the same textures into one entity each.
+            // The map is ordered, so entities get added sorted by material.
+            let mut batches: BTreeMap<(Vec<String>, bool), Vec<(&PolMesh, &PolMaterialInfo)>> =
+                BTreeMap::new();
+            for (i, mesh) in pol.meshes.iter().enumerate() {
+                if options.hidden_meshes.contains(&i) {
+                    continue;
+                }
+
+                let has_normal = mesh.vertex_type.has(PolVertexComponents::NORMAL);
                 for material in &mesh.material_info {
-                    let mut entity =
-                        CoreEntity::new(PolModelEntity::new(&mesh.vertices, material, &self.path));
-                    entity
-                        .transform_mut()
-                        .translate(&Vec3::new(0., -400., -1000.));
-                    scene.add_entity(entity)
+                    batches
+                        .entry((material.texture_names.clone(), has_normal))
+                        .or_insert_with(Vec::new)
+                        .push((mesh, material));
+                }
+            }
+
+            let batches: Vec<Vec<(&PolMesh, &PolMaterialInfo)>> = batches
+                .into_iter()
+                .map(|(_, parts)| parts)
+                .filter(|parts| match parts[0].1.texture_names.last() {
+                    Some(name) => {
+                        options
+                            .isolate
+                            .as_ref()
+                            .map(|isolated| isolated == &name.to_lowercase())
+                            .unwrap_or(true)
+                            && !material_overrides
+                                .as_ref()
+                                .map(|o| o.is_hidden(name))
+                                .unwrap_or(false)
+                    }
+                    None => options.isolate.is_none(),
+                })
+                .collect();
+            let shader_registry = &shader_registry;
+            let shader_overrides = &shader_overrides;
+            let texture_animations = &texture_animations;
+            let water_surfaces = &water_surfaces;
+            let material_overrides = &material_overrides;
+            let pol_entities: Vec<PolModelEntity> = batches
+                .par_iter()
+                .map(|parts| {
+                    let shader_override = shader_overrides.as_ref().and_then(|overrides| {
+                        parts[0]
+                            .1
+                            .texture_names
+                            .iter()
+                            .filter_map(|name| overrides.shader_for(name))
+                            .next()
+                            .map(|shader| (shader_registry, shader))
+                    });
+                    // Keyed by the diffuse texture, the last one
+                    let texture_animation = texture_animations.as_ref().and_then(|animations| {
+                        parts[0]
+                            .1
+                            .texture_names
+                            .last()
+                            .and_then(|name| animations.for_texture(name))
+                            .cloned()
+                    });
+                    let water = water_surfaces.as_ref().and_then(|surfaces| {
+                        parts[0]
+                            .1
+                            .texture_names
+                            .last()
+                            .and_then(|name| surfaces.for_texture(name))
+                            .cloned()
+                    });
+                    let material_override = material_overrides.as_ref().and_then(|overrides| {
+                        parts[0]
+                            .1
+                            .texture_names
+                            .last()
+                            .and_then(|name| overrides.for_texture(name))
+                            .cloned()
+                    });
+                    PolModelEntity::new(
+                        parts,
+                        path,
+                        options,
+                        shader_override,
+                        texture_animation,
+                        water,
+                        material_override,
+                    )
+                })
+                .collect();
+
+            if let Some(export_path) = &options.export_materials {
+                let mut exported = MaterialOverrides::new();
+                for (parts, pol_entity) in batches.iter().zip(&pol_entities) {
+                    if let Some(name) = parts[0].1.texture_names.last() {
+                        exported.entry(name).texture = pol_entity.texture_paths().last().cloned();
+                    }
+                }
+
+                if let Err(e) = exported.write_to_file(export_path) {
+                    println!("Unable to export materials {:?}: {}", export_path, e);
                 }
             }
-        } else if self.path.to_lowercase().ends_with(".cvd") {
-            let cvd = cvd_load_from_file(&self.path).unwrap();
+
+            add_framed_entities(scene, pol_entities, PolModelEntity::bounds, placement, options)
+        } else if path.to_lowercase().ends_with(".cvd") {
+            let cvd = self.load_file(path, || cvd_load_from_file(path))?;
             println!("cvd model count {}", cvd.model_count);
+            if options.inspect {
+                print!("{}", cvd_inspector_tree(path, &cvd));
+            }
+
+            let mut entities = vec![];
             for (i, model) in cvd.models.iter().enumerate() {
-                cvd_add_model_entity(&model, scene, &self.path, i as u32);
+                if options.hidden_meshes.contains(&i) {
+                    continue;
+                }
+
+                cvd_create_model_entities(&model, &mut entities, path, i as u32, options, &|| {
+                    self.playback_controls(options)
+                });
+            }
+
+            add_framed_entities(scene, entities, CvdModelEntity::bounds, placement, options)
+        } else if let Some(loader) = self.plugins.loader_for(Path::new(path)) {
+            let meshes = self.load_file(path, || {
+                std::fs::read(path)
+                    .map_err(|e| e.into())
+                    .and_then(|data| loader.load(&data))
+            })?;
+            let mut entities = vec![];
+            for mesh in &meshes {
+                let texture_paths: Vec<PathBuf> = mesh
+                    .texture_names
+                    .iter()
+                    .map(|name| {
+                        let mut texture_path = PathBuf::from(path);
+                        texture_path.pop();
+                        texture_path.push(name);
+                        texture_path
+                    })
+                    .collect();
+                let material = loader
+                    .material()
+                    .and_then(|name| self.plugins.create_material(name, &texture_paths));
+                entities.push(FlatMeshEntity::new(mesh, &texture_paths, material));
+            }
+
+            add_framed_entities(scene, entities, FlatMeshEntity::bounds, placement, options)
+        } else {
+            println!("Not supported file format: {}", path);
+            return None;
+        };
+
+        if options.show_normals {
+            let meshes = self.flat_meshes(path);
+            let mut lines = DebugLines::new(0.2);
+            for mesh in &meshes {
+                if let Some(normals) = &mesh.normals {
+                    let positions: Vec<Vec3> =
+                        mesh.positions.iter().map(|p| Vec3::new(p[0], p[1], p[2])).collect();
+                    let length = Aabb::from_points(&positions).radius() * 0.05;
+                    lines.add_normals(&mesh.positions, normals, length, Vec3::new(0., 1., 1.));
+                }
+            }
+
+            if lines.is_empty() {
+                println!("{} has no normals to show", path);
+            } else {
+                let translation = Vec3::new(position[0], position[1], position[2]);
+                place_entity(scene, DebugLinesEntity::new(lines), &translation, rotation_y);
             }
         }
-        else {
-            panic!("Not supported file format");
+
+        Some(EntityDesc {
+            asset: PathBuf::from(path),
+            position,
+            rotation_y,
+            material_overrides: options.material_overrides.clone(),
+        })
+    }
+}
+
+// Moves all entities of a model by the same amount, either to `placement`
+// or so that their combined bounds are framed by the camera. Returns the
+// position and the rotation around y in degrees.
+fn add_framed_entities<T: SceneCallbacks, E: EntityCallbacks + 'static, F: Fn(&E) -> Aabb>(
+    scene: &mut CoreScene<T>,
+    entities: Vec<E>,
+    bounds: F,
+    placement: Option<&EntityDesc>,
+    options: &ViewerOptions,
+) -> ([f32; 3], f32) {
+    let (translation, rotation_y) = match placement {
+        Some(p) => (Vec3::new(p.position[0], p.position[1], p.position[2]), p.rotation_y),
+        None => {
+            let total = entities
+                .iter()
+                .fold(Aabb::empty(), |total, e| total.union(&bounds(e)));
+            let translation = if total.is_empty() {
+                Vec3::new(0., 0., -500.)
+            } else {
+                total.framing_translation(FRAMING_FOV_Y)
+            };
+
+            (translation, 0.)
         }
+    };
+
+    let mut lines = DebugLines::new(0.5);
+    for e in entities {
+        if options.show_bounds {
+            lines.add_aabb(&bounds(&e), Vec3::new(1., 1., 0.));
+        }
+
+        place_entity(scene, e, &translation, rotation_y);
     }
+
+    if !lines.is_empty() {
+        place_entity(scene, DebugLinesEntity::new(lines), &translation, rotation_y);
+    }
+
+    ([translation.x, translation.y, translation.z], rotation_y)
 }
 
-fn cvd_add_model_entity<T: SceneCallbacks>(model: &CvdModel, scene: &mut CoreScene<T>, path: &str, id: u32) {
-    println!("frame count {}", model.mesh.frame_count);
-    for material in &model.mesh.materials {
-        let mut entity =
-            CoreEntity::new(CvdModelEntity::new(&model.mesh.frames[0], material, path, id));
-        println!("position0: {:?}", &model.position_keyframes[0].position);
+fn place_entity<T: SceneCallbacks, E: EntityCallbacks + 'static>(
+    scene: &mut CoreScene<T>,
+    e: E,
+    translation: &Vec3,
+    rotation_y: f32,
+) {
+    let mut entity = CoreEntity::new(e);
+    entity.transform_mut().translate(translation);
+    if rotation_y != 0. {
         entity
             .transform_mut()
-            .translate_local(&Vec3::new(0., -40., -1000.))
-            .translate_local(&model.position_keyframes[0].position);
-        scene.add_entity(entity);
+            .rotate_local(&Vec3::new(0., 1., 0.), rotation_y.to_radians());
+    }
+
+    scene.add_entity(entity);
+}
+
+fn cvd_create_model_entities(
+    model: &CvdModel,
+    entities: &mut Vec<CvdModelEntity>,
+    path: &str,
+    id: u32,
+    options: &ViewerOptions,
+    controls: &dyn Fn() -> PlaybackControls,
+) {
+    println!("frame count {}", model.mesh.frame_count);
+    for material in &model.mesh.materials {
+        entities.push(CvdModelEntity::new(
+            model,
+            material,
+            path,
+            id,
+            options.lit,
+            controls(),
+        ));
     }
 
     if let Some(children) = &model.children {
         println!("cvd children count: {}", children.len());
         for child in children {
-            cvd_add_model_entity(child, scene, path, id);
+            cvd_create_model_entities(child, entities, path, id, options, controls);
         }
     }
 }
@@ -0,0 +1,38 @@
+use opengb::geometry::sky_dome;
+use opengb::material::create_sky_material;
+use radiance::rendering::{RenderObject, VertexBuffer, VertexComponents};
+use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
+use std::path::PathBuf;
+
+// Has to stay inside the far plane, and outside every model in the scene
+// as the dome is still depth tested.
+const SKY_RADIUS: f32 = 5000.;
+
+pub struct SkyEntity {
+    texture_path: PathBuf,
+}
+
+impl SkyEntity {
+    pub fn new(texture_path: PathBuf) -> Self {
+        SkyEntity { texture_path }
+    }
+}
+
+impl EntityCallbacks for SkyEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let (positions, tex_coords, indices) = sky_dome(SKY_RADIUS, 16, 32);
+        let mut vertices = VertexBuffer::new(
+            VertexComponents::POSITION | VertexComponents::TEXCOORD,
+            positions.len(),
+        );
+        for (i, (position, tex_coord)) in positions.iter().zip(&tex_coords).enumerate() {
+            vertices.set_data(i, Some(position), None, Some(tex_coord), None);
+        }
+
+        entity.add_component(RenderObject::new_with_data(
+            vertices,
+            indices,
+            Box::new(create_sky_material(&self.texture_path)),
+        ));
+    }
+}
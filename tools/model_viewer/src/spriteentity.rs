@@ -0,0 +1,59 @@
+use opengb::material::create_billboard_material;
+use opengb::sprite::{Billboard, SpriteAnimation, SpriteAtlas};
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::{RenderObject, VertexBuffer};
+use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
+use std::path::PathBuf;
+
+const SPRITE_FPS: f32 = 12.;
+
+pub struct SpriteEntity {
+    texture_path: PathBuf,
+    atlas: SpriteAtlas,
+    billboard: Billboard,
+}
+
+impl SpriteEntity {
+    pub fn new(texture_path: PathBuf, atlas: SpriteAtlas) -> Self {
+        SpriteEntity {
+            texture_path,
+            atlas,
+            billboard: Billboard {
+                center: Vec3::new(0., 0., 0.),
+                size: Vec2::new(100., 100.),
+            },
+        }
+    }
+}
+
+impl EntityCallbacks for SpriteEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let mut vertices = VertexBuffer::new(Billboard::vertex_components(), 4);
+        self.billboard
+            .fill_vertex_buffer(&mut vertices, 0, self.atlas.frame_rect(0));
+
+        entity.add_component(RenderObject::new_host_dynamic_with_data(
+            vertices,
+            Billboard::indices(1),
+            Box::new(create_billboard_material(&self.texture_path)),
+        ));
+        entity.add_component(SpriteAnimation::new(self.atlas, SPRITE_FPS, true));
+    }
+
+    fn on_updating<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>, delta_sec: f32) {
+        let frame = entity
+            .get_component_mut::<SpriteAnimation>()
+            .unwrap()
+            .update(delta_sec);
+
+        if let Some(frame) = frame {
+            let rect = self.atlas.frame_rect(frame);
+            entity
+                .get_component_mut::<RenderObject>()
+                .unwrap()
+                .update_vertices(&|vertices: &mut VertexBuffer| {
+                    Billboard::set_frame(vertices, 0, rect);
+                });
+        }
+    }
+}
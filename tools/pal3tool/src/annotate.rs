@@ -0,0 +1,495 @@
+use crate::export::extension;
+use opengb::vfs::Vfs;
+use std::error::Error;
+
+const HEX_ROW: usize = 16;
+
+// annotate <file>... [--items=<n>]
+//
+// Walks a POL, CVD or MV3 file the way the loaders read it and prints
+// every field with its offset. Fields without a known meaning are shown
+// as hex. Only the first few items of long lists are printed; --items
+// changes how many. Bytes left after the last field are dumped at the
+// end, as they usually mean a block was misread.
+pub fn run(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut items = 3;
+    let mut files = vec![];
+    for arg in args {
+        if arg.starts_with("--items=") {
+            items = arg["--items=".len()..].parse()?;
+        } else {
+            files.push(arg);
+        }
+    }
+
+    if files.is_empty() {
+        return Err("annotate: no file given".into());
+    }
+
+    for file in files {
+        println!("{}", file);
+        let data = vfs.read(file)?;
+        let mut annotator = Annotator::new(&data, items);
+        let result = match extension(file).as_str() {
+            "pol" => annotate_pol(&mut annotator),
+            "cvd" => annotate_cvd(&mut annotator),
+            "mv3" => annotate_mv3(&mut annotator),
+            _ => Err(format!("annotate: unsupported file type {}", file).into()),
+        };
+        result?;
+
+        let remaining = annotator.remaining();
+        if remaining > 0 {
+            annotator.hex("trailing bytes", remaining)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Reads little endian values from the start of a file, printing each one
+// with its offset and nested by the blocks it is in
+struct Annotator<'a> {
+    data: &'a [u8],
+    offset: usize,
+    depth: usize,
+    items: usize,
+    // Inside the items of a list that aren't printed
+    muted: usize,
+}
+
+impl<'a> Annotator<'a> {
+    fn new(data: &'a [u8], items: usize) -> Self {
+        Annotator {
+            data,
+            offset: 0,
+            depth: 0,
+            items,
+            muted: 0,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    fn print(&self, offset: usize, text: &str) {
+        if self.muted == 0 {
+            println!("{:08x}  {}{}", offset, "  ".repeat(self.depth), text);
+        }
+    }
+
+    fn take(&mut self, name: &str, len: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        if len > self.remaining() {
+            return Err(format!(
+                "{:#x}: {} needs {} bytes, {} are left",
+                self.offset,
+                name,
+                len,
+                self.remaining()
+            )
+            .into());
+        }
+
+        let bytes = &self.data[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self, name: &str) -> Result<u8, Box<dyn Error>> {
+        let offset = self.offset;
+        let value = self.take(name, 1)?[0];
+        self.print(offset, &format!("{} = {} ({:#04x})", name, value, value));
+        Ok(value)
+    }
+
+    fn u32(&mut self, name: &str) -> Result<u32, Box<dyn Error>> {
+        let offset = self.offset;
+        let bytes = self.take(name, 4)?;
+        let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        self.print(offset, &format!("{} = {} ({:#010x})", name, value, value));
+        Ok(value)
+    }
+
+    // With the raw bits, as some fields read as floats are really packed
+    // integers or colors
+    fn f32s(&mut self, name: &str, count: usize) -> Result<Vec<f32>, Box<dyn Error>> {
+        let offset = self.offset;
+        let bytes = self.take(name, count * 4)?;
+        let values: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        let text: Vec<String> = values
+            .iter()
+            .map(|v| format!("{} [{:08x}]", v, v.to_bits()))
+            .collect();
+        self.print(offset, &format!("{} = {}", name, text.join(", ")));
+        Ok(values)
+    }
+
+    fn u16s(&mut self, name: &str, count: usize) -> Result<Vec<u16>, Box<dyn Error>> {
+        let offset = self.offset;
+        let bytes = self.take(name, count * 2)?;
+        let values: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        self.print(offset, &format!("{} = {:?}", name, values));
+        Ok(values)
+    }
+
+    fn i16s(&mut self, name: &str, count: usize) -> Result<Vec<i16>, Box<dyn Error>> {
+        let offset = self.offset;
+        let bytes = self.take(name, count * 2)?;
+        let values: Vec<i16> = bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        self.print(offset, &format!("{} = {:?}", name, values));
+        Ok(values)
+    }
+
+    // A zero terminated string in a fixed size field. The game's strings
+    // are GBK, so only ASCII is shown as is.
+    fn string(&mut self, name: &str, len: usize) -> Result<(), Box<dyn Error>> {
+        let offset = self.offset;
+        let bytes = self.take(name, len)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(len);
+        let text: String = bytes[..end].iter().map(|&b| printable(b)).collect();
+        let padding = if bytes[end..].iter().any(|&b| b != 0) {
+            ", with data after the terminator"
+        } else {
+            ""
+        };
+        self.print(
+            offset,
+            &format!("{} = \"{}\" ({} bytes{})", name, text, len, padding),
+        );
+        Ok(())
+    }
+
+    fn hex(&mut self, name: &str, len: usize) -> Result<(), Box<dyn Error>> {
+        let offset = self.offset;
+        let bytes = self.take(name, len)?;
+        self.print(offset, &format!("{}: {} bytes", name, len));
+        for (i, row) in bytes.chunks(HEX_ROW).enumerate() {
+            let hex: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+            let text: String = row.iter().map(|&b| printable(b)).collect();
+            self.print(
+                offset + i * HEX_ROW,
+                &format!("  {:<48}  {}", hex.join(" "), text),
+            );
+        }
+
+        Ok(())
+    }
+
+    // Decodes the u32 just read
+    fn flags(&self, value: u32, names: &[(u32, &str)]) {
+        let mut decoded: Vec<String> = names
+            .iter()
+            .filter(|(bit, _)| value & bit != 0)
+            .map(|(_, name)| name.to_string())
+            .collect();
+        let unknown = names.iter().fold(value, |rest, (bit, _)| rest & !bit);
+        if unknown != 0 {
+            decoded.push(format!("{:#x}", unknown));
+        }
+
+        self.print(self.offset - 4, &format!("  = {}", decoded.join(" | ")));
+    }
+
+    fn block<F>(&mut self, label: &str, f: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnOnce(&mut Self) -> Result<(), Box<dyn Error>>,
+    {
+        self.print(self.offset, label);
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    // Prints the first `items` entries and skips over the rest
+    fn list<F>(&mut self, name: &str, count: u32, mut f: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(&mut Self, u32) -> Result<(), Box<dyn Error>>,
+    {
+        let start = self.offset;
+        for i in 0..count {
+            if i as usize == self.items {
+                self.muted += 1;
+            }
+
+            let result = self.block(&format!("{} {}", name, i), |a| f(a, i));
+            if result.is_err() && i as usize >= self.items {
+                self.muted -= 1;
+            }
+            result?;
+        }
+
+        if count as usize > self.items {
+            self.muted -= 1;
+            self.print(
+                start,
+                &format!(
+                    "... {} more of {} {}s, {} bytes in all",
+                    count as usize - self.items,
+                    count,
+                    name,
+                    self.offset - start
+                ),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn printable(b: u8) -> char {
+    if b.is_ascii_graphic() || b == b' ' {
+        b as char
+    } else {
+        '.'
+    }
+}
+
+fn annotate_pol(a: &mut Annotator) -> Result<(), Box<dyn Error>> {
+    a.string("magic", 4)?;
+    let version = a.u32("some_flag (version)")?;
+    let mesh_count = a.u32("mesh_count")?;
+    a.list("geom_node_desc", mesh_count, |a, _| a.hex("unknown", 52))?;
+
+    // Only newer files have node matrices
+    if version > 100 {
+        let count = a.u32("unknown_count")?;
+        a.list("unknown_data", count, |a, _| {
+            a.hex("unknown", 32)?;
+            for row in 0..4 {
+                a.f32s(&format!("matrix row {}", row), 4)?;
+            }
+            a.u32("unknown2")?;
+            let len = a.u32("str_len")?;
+            a.string("ddd_str", len as usize)
+        })?;
+    }
+
+    a.list("mesh", mesh_count, |a, _| {
+        a.f32s("aabb_min", 3)?;
+        a.f32s("aabb_max", 3)?;
+        let vertex_type = a.u32("vertex_type")?;
+        a.flags(
+            vertex_type,
+            &[
+                (0x1, "POSITION"),
+                (0x2, "NORMAL"),
+                (0x4, "DIFFUSE"),
+                (0x8, "UNKNOWN8"),
+                (0x10, "TEXCOORD"),
+                (0x20, "TEXCOORD2"),
+                (0x40, "UNKNOWN40"),
+                (0x80, "UNKNOWN80"),
+                (0x100, "UNKNOWN100"),
+            ],
+        );
+        let vertex_count = a.u32("vertex_count")?;
+        a.list("vertex", vertex_count, |a, _| {
+            let has = |bit: u32| vertex_type & bit != 0;
+            a.f32s("position", 3)?;
+            if has(0x2) {
+                a.f32s("normal", 3)?;
+            }
+            if has(0x4) {
+                a.u32("diffuse (D3DCOLOR)")?;
+            }
+            if has(0x8) {
+                a.f32s("unknown8", 1)?;
+            }
+            a.f32s("tex_coord", 2)?;
+            if has(0x20) {
+                a.f32s("tex_coord2", 2)?;
+            }
+            if has(0x40) {
+                a.f32s("unknown40", 2)?;
+            }
+            if has(0x80) {
+                a.f32s("unknown80", 2)?;
+            }
+            if has(0x100) {
+                a.f32s("unknown100", 4)?;
+            }
+            Ok(())
+        })?;
+
+        let material_count = a.u32("material_info_count")?;
+        a.list("material", material_count, |a, _| {
+            a.u32("unknown_dw0")?;
+            a.hex("unknown_68", 64)?;
+            a.f32s("unknown_float", 1)?;
+            let texture_count = a.u32("texture_count")?;
+            for i in 0..texture_count {
+                a.string(&format!("texture_name {}", i), 64)?;
+            }
+            a.u32("unknown2")?;
+            a.u32("unknown3")?;
+            a.u32("unknown4")?;
+            let triangle_count = a.u32("triangle_count")?;
+            a.list("triangle", triangle_count, |a, _| {
+                a.u16s("indices", 3).map(|_| ())
+            })
+        })
+    })
+}
+
+fn annotate_cvd(a: &mut Annotator) -> Result<(), Box<dyn Error>> {
+    let magic = a.take("magic", 4)?;
+    a.print(
+        0,
+        &format!("magic = \"{}\"", String::from_utf8_lossy(magic)),
+    );
+    // Only "cvds" files have the extra block after each material's
+    // triangles
+    let has_extra_block = magic == b"cvds";
+    let model_count = a.u32("model_count")?;
+    a.list("model", model_count, |a, _| {
+        annotate_cvd_model(a, has_extra_block)
+    })
+}
+
+fn annotate_cvd_model(a: &mut Annotator, has_extra_block: bool) -> Result<(), Box<dyn Error>> {
+    if a.u8("unknown_byte")? == 0 {
+        a.print(a.offset, "empty model, the loader stops here");
+        return Ok(());
+    }
+
+    let count = a.u32("position_keyframe_count")?;
+    if count > 0 {
+        a.u8("unknown_byte")?;
+        a.list("position_keyframe", count, |a, _| {
+            a.f32s("timestamp", 1)?;
+            a.f32s("unknown1", 1)?;
+            a.f32s("position", 3)?;
+            a.f32s("unknown2-7", 6).map(|_| ())
+        })?;
+    }
+
+    // Skipped by the loader, likely rotation and scale keyframes
+    for &(name, dwords) in &[("unknown_vec11", 11), ("unknown_vec15", 15)] {
+        let count = a.u32(&format!("{} count", name))?;
+        if count > 0 {
+            a.u8("unknown_byte")?;
+            a.list(name, count, |a, _| a.f32s("data", dwords).map(|_| ()))?;
+        }
+    }
+
+    a.f32s("unknown_dword", 1)?;
+    let frame_count = a.u32("frame_count")?;
+    let vertex_count = a.u32("vertex_count")?;
+    a.list("frame", frame_count, |a, _| {
+        a.list("vertex", vertex_count, |a, _| {
+            a.f32s("tex_coord", 2)?;
+            a.f32s("normal", 3)?;
+            a.f32s("position", 3).map(|_| ())
+        })
+    })?;
+    a.f32s("unknown_data (per frame)", frame_count as usize)?;
+
+    let material_count = a.u32("material_count")?;
+    a.list("material", material_count, |a, _| {
+        a.u8("unknown_byte")?;
+        for i in 1..5 {
+            a.u32(&format!("color{}", i))?;
+        }
+        a.f32s("unknown_float2", 1)?;
+        a.string("texture_name", 64)?;
+        let triangle_count = a.u32("triangle_count")?;
+        a.list("triangle", triangle_count, |a, _| {
+            a.u16s("indices", 3).map(|_| ())
+        })?;
+
+        if has_extra_block {
+            let count = a.u32("unknown_data2_count")?;
+            a.list("unknown_data2 dword", count, |a, _| {
+                a.u32("value").map(|_| ())
+            })?;
+            a.list("unknown_data2 entry", count, |a, _| a.hex("data", 20))?;
+        }
+
+        Ok(())
+    })?;
+
+    for row in 0..4 {
+        a.f32s(&format!("matrix row {}", row), 4)?;
+    }
+
+    let children_count = a.u32("children_count")?;
+    a.list("child", children_count, |a, _| {
+        annotate_cvd_model(a, has_extra_block)
+    })
+}
+
+fn annotate_mv3(a: &mut Annotator) -> Result<(), Box<dyn Error>> {
+    a.string("magic", 4)?;
+    a.u32("unknown_dw")?;
+    a.u32("unknown_dw2")?;
+    let texture_count = a.u32("texture_count")?;
+    let unknown_data_count = a.u32("unknown_data_count")?;
+    let model_count = a.u32("model_count")?;
+    let action_count = a.u32("action_count")?;
+    a.list("action", action_count, |a, _| a.hex("action_desc", 20))?;
+
+    // Skipped by the loader
+    a.list("unknown_data", unknown_data_count, |a, _| {
+        a.hex("unknown", 64)?;
+        a.u32("unknown2")?;
+        let count = a.u32("count")?;
+        a.list("entry", count, |a, _| a.hex("data", 68))
+    })?;
+
+    a.list("texture", texture_count, |a, _| {
+        a.hex("unknown", 68)?;
+        for i in 0..4 {
+            let len = a.u32(&format!("name {} length", i))?;
+            a.string(&format!("name {}", i), len as usize)?;
+        }
+        Ok(())
+    })?;
+
+    a.list("model", model_count, |a, _| {
+        a.hex("unknown", 64)?;
+        let vertex_per_frame = a.u32("vertex_per_frame")?;
+        a.f32s("aabb_min", 3)?;
+        a.f32s("aabb_max", 3)?;
+        let frame_count = a.u32("frame_count")?;
+        a.list("frame", frame_count, |a, _| {
+            a.u32("timestamp")?;
+            a.list("vertex", vertex_per_frame, |a, _| {
+                a.i16s("position (fixed point)", 3)?;
+                let normal = a.take("normal", 2)?;
+                a.print(
+                    a.offset - 2,
+                    &format!("normal phi = {}, theta = {}", normal[0] as i8, normal[1]),
+                );
+                Ok(())
+            })
+        })?;
+
+        let texcoord_count = a.u32("texcoord_count")?;
+        a.list("texcoord", texcoord_count, |a, _| {
+            a.f32s("uv", 2).map(|_| ())
+        })?;
+
+        let mesh_count = a.u32("mesh_count")?;
+        a.list("mesh", mesh_count, |a, _| {
+            a.u32("unknown")?;
+            let triangle_count = a.u32("triangle_count")?;
+            a.list("triangle", triangle_count, |a, _| {
+                a.u16s("indices", 3)?;
+                a.u16s("texcoord_indices", 3).map(|_| ())
+            })?;
+            let count = a.u32("unknown_data_count")?;
+            a.list("unknown_data", count, |a, _| a.u16s("u v", 2).map(|_| ()))
+        })
+    })
+}
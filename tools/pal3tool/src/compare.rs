@@ -0,0 +1,458 @@
+use super::cpk::wildcard_match;
+use opengb::config::key_value_lines;
+use opengb::loaders::cvdloader::*;
+use opengb::loaders::mv3loader::*;
+use opengb::loaders::polloader::*;
+use opengb::vfs::Vfs;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::process::Command;
+
+// Relative, so that large coordinates don't need more digits than the
+// reference prints
+const TOLERANCE: f32 = 1e-4;
+const MAX_REPORTED_DIFFERENCES: usize = 10;
+
+// The loaded structure flattened to `key = value` lines, with keys like
+// `meshes.0.vertices.12.unknown40`. Unknown fields are included, as they
+// are what comparing against another implementation is meant to pin down.
+type Dump = BTreeMap<String, String>;
+
+pub fn run_dump(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.is_empty() {
+        return Err("dump: no file given".into());
+    }
+
+    for arg in args {
+        let dump = dump_file(&vfs.read(arg)?, arg)?;
+        for (key, value) in &dump {
+            println!("{} = {}", key, value);
+        }
+    }
+
+    Ok(())
+}
+
+// Runs the reference dumper on every matching model and diffs its output
+// against ours. `{}` in the command is replaced by the path of the model,
+// which is otherwise appended. The reference has to print the same keys;
+// usually that takes a small wrapper script around an existing tool. Keys
+// only one side prints are counted but not reported, so that a reference
+// covering part of a format still helps.
+pub fn run(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let command = match args.first() {
+        Some(command) => command,
+        None => return Err("compare: no reference command given".into()),
+    };
+    let pattern = args.get(1).map(|p| p.to_lowercase());
+
+    let temp_dir = std::env::temp_dir().join("pal3tool-compare");
+    std::fs::create_dir_all(&temp_dir)?;
+
+    let (mut matching, mut differing, mut failed) = (0, 0, 0);
+    for name in vfs.file_names() {
+        let lower = name.to_lowercase();
+        let supported =
+            lower.ends_with(".pol") || lower.ends_with(".cvd") || lower.ends_with(".mv3");
+        let matches = pattern
+            .as_ref()
+            .map(|p| wildcard_match(p, &lower))
+            .unwrap_or(true);
+        if !supported || !matches {
+            continue;
+        }
+
+        match compare_file(vfs, &name, command, &temp_dir) {
+            Ok(differences) if differences.is_empty() => matching += 1,
+            Ok(differences) => {
+                differing += 1;
+                println!("{}: {} differences", name, differences.len());
+                for difference in differences.iter().take(MAX_REPORTED_DIFFERENCES) {
+                    println!("    {}", difference);
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{}: {}", name, e);
+            }
+        }
+    }
+
+    println!(
+        "{} matching, {} differing, {} failed",
+        matching, differing, failed
+    );
+    if differing > 0 || failed > 0 {
+        return Err("compare: the loaders disagree with the reference".into());
+    }
+
+    Ok(())
+}
+
+fn compare_file(
+    vfs: &Vfs,
+    name: &str,
+    command: &str,
+    temp_dir: &Path,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let data = vfs.read(name)?;
+    let ours = dump_file(&data, name)?;
+
+    // The file may come from an archive, so the reference gets a copy
+    let file_name = Path::new(name).file_name().ok_or("no file name")?;
+    let path = temp_dir.join(file_name);
+    std::fs::write(&path, &data)?;
+    let reference = run_reference(command, &path);
+    std::fs::remove_file(&path)?;
+    let reference = reference?;
+
+    let mut differences = vec![];
+    let mut unmatched = 0;
+    for (key, value) in &reference {
+        match ours.get(key) {
+            Some(ours) if values_match(ours, value) => (),
+            Some(ours) => differences.push(format!("{}: {} (reference {})", key, ours, value)),
+            None => unmatched += 1,
+        }
+    }
+
+    let shared = reference.len() - unmatched;
+    if shared == 0 {
+        return Err("the reference printed none of our keys".into());
+    }
+
+    if unmatched > 0 || ours.len() > shared {
+        println!(
+            "{}: {} keys compared, {} only in the reference, {} only ours",
+            name,
+            shared,
+            unmatched,
+            ours.len() - shared
+        );
+    }
+
+    Ok(differences)
+}
+
+fn run_reference(command: &str, path: &Path) -> Result<Dump, Box<dyn Error>> {
+    let path = path.to_string_lossy();
+    let mut words: Vec<String> = command.split_whitespace().map(|w| w.to_string()).collect();
+    if words.is_empty() {
+        return Err("empty reference command".into());
+    }
+
+    if words.iter().any(|w| w.contains("{}")) {
+        for word in &mut words {
+            *word = word.replace("{}", &path);
+        }
+    } else {
+        words.push(path.into_owned());
+    }
+
+    let output = Command::new(&words[0]).args(&words[1..]).output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "the reference failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    let mut dump = Dump::new();
+    for line in key_value_lines(&String::from_utf8_lossy(&output.stdout)) {
+        if !line.key.is_empty() {
+            dump.insert(line.key.to_string(), line.value.unwrap_or("").to_string());
+        }
+    }
+
+    Ok(dump)
+}
+
+// Numbers are compared with a tolerance, everything else as text
+fn values_match(a: &str, b: &str) -> bool {
+    let a_words: Vec<&str> = a.split_whitespace().collect();
+    let b_words: Vec<&str> = b.split_whitespace().collect();
+    a_words.len() == b_words.len()
+        && a_words
+            .iter()
+            .zip(&b_words)
+            .all(|(a, b)| match (a.parse::<f32>(), b.parse::<f32>()) {
+                (Ok(a), Ok(b)) => (a - b).abs() <= TOLERANCE * a.abs().max(b.abs()).max(1.),
+                _ => a == b,
+            })
+}
+
+fn dump_file(data: &[u8], name: &str) -> Result<Dump, Box<dyn Error>> {
+    let dump = || -> Result<Dump, Box<dyn Error>> {
+        let mut dump = Dump::new();
+        let lower = name.to_lowercase();
+        if lower.ends_with(".pol") {
+            dump_pol(&mut dump, &pol_load_from_bytes(data)?);
+        } else if lower.ends_with(".cvd") {
+            dump_cvd(&mut dump, &cvd_load_from_bytes(data)?);
+        } else if lower.ends_with(".mv3") {
+            dump_mv3(&mut dump, &mv3_load_from_bytes(data)?);
+        } else {
+            return Err(format!("{}: unsupported file type", name).into());
+        }
+
+        Ok(dump)
+    };
+
+    // The loaders still panic on some malformed files
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| dump().map_err(|e| e.to_string())));
+    result
+        .unwrap_or_else(|_| Err("the loader panicked".to_owned()))
+        .map_err(|e| e.into())
+}
+
+fn put<T: ToString>(dump: &mut Dump, key: String, value: T) {
+    dump.insert(key, value.to_string());
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn dump_pol(dump: &mut Dump, pol: &PolFile) {
+    put(dump, "some_flag".to_string(), pol.some_flag);
+    put(dump, "mesh_count".to_string(), pol.mesh_count);
+    put(dump, "unknown_count".to_string(), pol.unknown_count);
+    for (i, desc) in pol.geom_node_descs.iter().enumerate() {
+        put(
+            dump,
+            format!("geom_node_descs.{}.unknown", i),
+            join(&desc.unknown),
+        );
+    }
+
+    for (i, data) in pol.unknown_data.iter().enumerate() {
+        let prefix = format!("unknown_data.{}", i);
+        put(dump, format!("{}.unknown", prefix), join(&data.unknown));
+        put(dump, format!("{}.unknown2", prefix), data.unknown2);
+        put(
+            dump,
+            format!("{}.ddd_str", prefix),
+            String::from_utf8_lossy(&data.ddd_str).trim_end_matches('\0'),
+        );
+    }
+
+    for (i, mesh) in pol.meshes.iter().enumerate() {
+        let prefix = format!("meshes.{}", i);
+        put(dump, format!("{}.aabb_min", prefix), join(&mesh.aabb_min));
+        put(dump, format!("{}.aabb_max", prefix), join(&mesh.aabb_max));
+        put(dump, format!("{}.vertex_count", prefix), mesh.vertex_count);
+        for (j, v) in mesh.vertices.iter().enumerate() {
+            let prefix = format!("{}.vertices.{}", prefix, j);
+            let p = &v.position;
+            put(dump, format!("{}.position", prefix), join(&[p.x, p.y, p.z]));
+            let t = &v.tex_coord;
+            put(dump, format!("{}.tex_coord", prefix), join(&[t.u, t.v]));
+            if let Some(n) = &v.normal {
+                put(dump, format!("{}.normal", prefix), join(n));
+            }
+            if let Some(u) = &v.unknown4 {
+                put(dump, format!("{}.unknown4", prefix), join(u));
+            }
+            if let Some(u) = &v.unknown8 {
+                put(dump, format!("{}.unknown8", prefix), join(u));
+            }
+            if let Some(t) = &v.tex_coord2 {
+                put(dump, format!("{}.tex_coord2", prefix), join(&[t.u, t.v]));
+            }
+            if let Some(u) = &v.unknown40 {
+                put(dump, format!("{}.unknown40", prefix), join(u));
+            }
+            if let Some(u) = &v.unknown80 {
+                put(dump, format!("{}.unknown80", prefix), join(u));
+            }
+            if let Some(u) = &v.unknown100 {
+                put(dump, format!("{}.unknown100", prefix), join(u));
+            }
+        }
+
+        for (j, m) in mesh.material_info.iter().enumerate() {
+            let prefix = format!("{}.materials.{}", prefix, j);
+            put(dump, format!("{}.unknown_dw0", prefix), m.unknown_dw0);
+            put(dump, format!("{}.unknown_68", prefix), join(&m.unknown_68));
+            put(dump, format!("{}.unknown_float", prefix), m.unknown_float);
+            put(
+                dump,
+                format!("{}.texture_names", prefix),
+                m.texture_names.join(" "),
+            );
+            put(dump, format!("{}.unknown2", prefix), m.unknown2);
+            put(dump, format!("{}.unknown3", prefix), m.unknown3);
+            put(dump, format!("{}.unknown4", prefix), m.unknown4);
+            put(dump, format!("{}.triangle_count", prefix), m.triangle_count);
+        }
+    }
+}
+
+fn dump_cvd(dump: &mut Dump, cvd: &CvdFile) {
+    put(dump, "model_count".to_string(), cvd.model_count);
+    for (i, model) in cvd.models.iter().enumerate() {
+        dump_cvd_model(dump, &format!("models.{}", i), model);
+    }
+}
+
+fn dump_cvd_model(dump: &mut Dump, prefix: &str, model: &CvdModel) {
+    put(dump, format!("{}.unknown_byte", prefix), model.unknown_byte);
+    put(
+        dump,
+        format!("{}.unknown_dword", prefix),
+        model.unknown_dword,
+    );
+    for (i, k) in model.position_keyframes.iter().enumerate() {
+        let prefix = format!("{}.position_keyframes.{}", prefix, i);
+        put(dump, format!("{}.timestamp", prefix), k.timestamp);
+        put(dump, format!("{}.unknown1", prefix), k.unknown1);
+        let p = &k.position;
+        put(dump, format!("{}.position", prefix), join(&[p.x, p.y, p.z]));
+        put(
+            dump,
+            format!("{}.unknown2_7", prefix),
+            join(&[
+                k.unknown2, k.unknown3, k.unknown4, k.unknown5, k.unknown6, k.unknown7,
+            ]),
+        );
+    }
+
+    let mesh = &model.mesh;
+    put(
+        dump,
+        format!("{}.mesh.frame_count", prefix),
+        mesh.frame_count,
+    );
+    put(
+        dump,
+        format!("{}.mesh.vertex_count", prefix),
+        mesh.vertex_count,
+    );
+    put(
+        dump,
+        format!("{}.mesh.unknown_data", prefix),
+        join(&mesh.unknown_data),
+    );
+    for (i, frame) in mesh.frames.iter().enumerate() {
+        for (j, v) in frame.iter().enumerate() {
+            let prefix = format!("{}.mesh.frames.{}.{}", prefix, i, j);
+            let (p, n, t) = (&v.position, &v.normal, &v.tex_coord);
+            put(dump, format!("{}.position", prefix), join(&[p.x, p.y, p.z]));
+            put(dump, format!("{}.normal", prefix), join(&[n.x, n.y, n.z]));
+            put(dump, format!("{}.tex_coord", prefix), join(&[t.x, t.y]));
+        }
+    }
+
+    for (i, m) in mesh.materials.iter().enumerate() {
+        let prefix = format!("{}.mesh.materials.{}", prefix, i);
+        put(dump, format!("{}.unknown_byte", prefix), m.unknown_byte);
+        put(
+            dump,
+            format!("{}.colors", prefix),
+            join(&[m.color1, m.color2, m.color3, m.color4]),
+        );
+        put(dump, format!("{}.texture_name", prefix), &m.texture_name);
+        put(dump, format!("{}.triangle_count", prefix), m.triangle_count);
+    }
+
+    if let Some(children) = &model.children {
+        for (i, child) in children.iter().enumerate() {
+            dump_cvd_model(dump, &format!("{}.children.{}", prefix, i), child);
+        }
+    }
+}
+
+fn dump_mv3(dump: &mut Dump, mv3: &Mv3File) {
+    put(dump, "unknown_dw".to_string(), mv3.unknown_dw);
+    put(dump, "unknown_dw2".to_string(), mv3.unknown_dw2);
+    put(dump, "texture_count".to_string(), mv3.texture_count);
+    put(dump, "model_count".to_string(), mv3.model_count);
+    put(dump, "action_count".to_string(), mv3.action_count);
+    for (i, action) in mv3.action_desc.iter().enumerate() {
+        put(dump, format!("action_desc.{}", i), join(action));
+    }
+
+    for (i, data) in mv3.unknown_data.iter().enumerate() {
+        put(dump, format!("unknown_data.{}", i), join(data));
+    }
+
+    for (i, texture) in mv3.textures.iter().enumerate() {
+        put(
+            dump,
+            format!("textures.{}.unknown", i),
+            join(&texture.unknown),
+        );
+        let names: Vec<String> = texture
+            .names
+            .iter()
+            .map(|n| {
+                String::from_utf8_lossy(n)
+                    .trim_end_matches('\0')
+                    .to_string()
+            })
+            .collect();
+        put(dump, format!("textures.{}.names", i), names.join(" "));
+    }
+
+    for (i, model) in mv3.models.iter().enumerate() {
+        let prefix = format!("models.{}", i);
+        put(dump, format!("{}.unknown", prefix), join(&model.unknown));
+        put(
+            dump,
+            format!("{}.vertex_per_frame", prefix),
+            model.vertex_per_frame,
+        );
+        put(dump, format!("{}.aabb_min", prefix), join(&model.aabb_min));
+        put(dump, format!("{}.aabb_max", prefix), join(&model.aabb_max));
+        put(dump, format!("{}.frame_count", prefix), model.frame_count);
+        for (j, frame) in model.frames.iter().enumerate() {
+            put(
+                dump,
+                format!("{}.frames.{}.timestamp", prefix, j),
+                frame.timestamp,
+            );
+            for (k, v) in frame.vertices.iter().enumerate() {
+                put(
+                    dump,
+                    format!("{}.frames.{}.{}", prefix, j, k),
+                    join(&[
+                        v.x as i32,
+                        v.y as i32,
+                        v.z as i32,
+                        v.normal_phi as i32,
+                        v.normal_theta as i32,
+                    ]),
+                );
+            }
+        }
+
+        put(
+            dump,
+            format!("{}.texcoord_count", prefix),
+            model.texcoord_count,
+        );
+        for (j, mesh) in model.meshes.iter().enumerate() {
+            let prefix = format!("{}.meshes.{}", prefix, j);
+            put(dump, format!("{}.unknown", prefix), mesh.unknown);
+            put(
+                dump,
+                format!("{}.triangle_count", prefix),
+                mesh.triangle_count,
+            );
+            let unknown: Vec<u16> = mesh
+                .unknown_data
+                .iter()
+                .flat_map(|d| vec![d.u, d.v])
+                .collect();
+            put(dump, format!("{}.unknown_data", prefix), join(&unknown));
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::cpk::wildcard_match;
+use crate::export::{extension, model_to_gltf, model_to_obj};
+use opengb::export::{export_texture, exported_texture_name};
+use opengb::vfs::Vfs;
+use rayon::prelude::*;
+use std::error::Error;
+use std::panic::AssertUnwindSafe;
+use std::path::{Component, Path, PathBuf};
+
+const MODEL_EXTENSIONS: &[&str] = &["pol", "cvd", "mv3"];
+const TEXTURE_EXTENSIONS: &[&str] = &["dds", "tga", "bmp"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ModelFormat {
+    Gltf,
+    Obj,
+}
+
+// convert <output dir> [--format=gltf|obj] [--jobs=<n>] [pattern]
+//
+// Converts every mounted model and texture matching the pattern, keeping
+// the directory layout so that converted models find their textures next
+// to them. A report of the failures is written to the output directory.
+pub fn run(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut output = None;
+    let mut format = ModelFormat::Gltf;
+    let mut jobs = 0;
+    let mut pattern = "*".to_owned();
+    for arg in args {
+        match arg.as_str() {
+            "--format=gltf" => format = ModelFormat::Gltf,
+            "--format=obj" => format = ModelFormat::Obj,
+            _ if arg.starts_with("--jobs=") => jobs = arg["--jobs=".len()..].parse()?,
+            _ if arg.starts_with("--") => {
+                return Err(format!("convert: unknown option {}", arg).into())
+            }
+            _ if output.is_none() => output = Some(PathBuf::from(arg)),
+            _ => pattern = arg.clone(),
+        }
+    }
+
+    let output = output.ok_or("convert: expected <output dir>")?;
+    let names: Vec<String> = vfs
+        .file_names()
+        .into_iter()
+        .filter(|name| {
+            let extension = extension(name);
+            (MODEL_EXTENSIONS.contains(&extension.as_str())
+                || TEXTURE_EXTENSIONS.contains(&extension.as_str()))
+                && wildcard_match(&pattern, name)
+        })
+        .collect();
+
+    // 0 lets rayon pick one worker per core
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let failures: Vec<(String, String)> = pool.install(|| {
+        names
+            .par_iter()
+            .filter_map(|name| {
+                convert(vfs, name, &output, format)
+                    .err()
+                    .map(|e| (name.clone(), e))
+            })
+            .collect()
+    });
+
+    let mut report = format!(
+        "converted: {}\nfailed: {}\n",
+        names.len() - failures.len(),
+        failures.len()
+    );
+    for (name, error) in &failures {
+        report.push_str(&format!("{}: {}\n", name, error));
+    }
+
+    print!("{}", report);
+    std::fs::create_dir_all(&output)?;
+    std::fs::write(output.join("convert-report.txt"), report)?;
+    Ok(())
+}
+
+// Errors are returned as text, as boxed errors can't cross the workers
+fn convert(vfs: &Vfs, name: &str, output: &Path, format: ModelFormat) -> Result<(), String> {
+    let target = output_path(output, name);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let convert = || -> Result<(), Box<dyn Error>> {
+        let extension = extension(name);
+        if TEXTURE_EXTENSIONS.contains(&extension.as_str()) {
+            let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+            let png = target.with_file_name(exported_texture_name(&file_name));
+            export_texture(&vfs.read(name)?, png)
+        // OBJ has no animation, so MV3 always goes to glTF
+        } else if format == ModelFormat::Obj && extension != "mv3" {
+            model_to_obj(vfs, name, &target.with_extension("obj")).map(|_| ())
+        } else {
+            model_to_gltf(vfs, name, &target.with_extension("gltf")).map(|_| ())
+        }
+    };
+
+    // The loaders still panic on some malformed files
+    let result =
+        std::panic::catch_unwind(AssertUnwindSafe(|| convert().map_err(|e| e.to_string())));
+    result.unwrap_or_else(|_| Err("the loader panicked".to_owned()))
+}
+
+// Drops components that would escape the output directory: anything but a
+// plain name, and drive or stream names with ':'
+pub fn output_path(output: &Path, name: &str) -> PathBuf {
+    name.split(|c| c == '/' || c == '\\')
+        .filter(|c| !c.contains(':') && is_plain_name(c))
+        .fold(output.to_path_buf(), |path, c| path.join(c))
+}
+
+fn is_plain_name(component: &str) -> bool {
+    let mut components = Path::new(component).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,97 @@
+use opengb::plugins::PluginRegistry;
+use opengb::vfs::Vfs;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+
+// Columns of the matrix. SCN and NAV have no loader yet, so they always
+// count as missing, which keeps scenes needing them at "partial".
+const FORMATS: &[&str] = &["scn", "pol", "cvd", "mv3", "nav"];
+
+#[derive(Default)]
+struct FormatCoverage {
+    total: u32,
+    loaded: u32,
+}
+
+// Groups the files under `scene/<id>/` by scene and tries to load each of
+// them. A scene is complete when every one of its files loads.
+pub fn run(vfs: &Vfs, _args: &[String]) -> Result<(), Box<dyn Error>> {
+    let plugins = PluginRegistry::with_builtin();
+    let mut scenes: BTreeMap<String, Vec<FormatCoverage>> = BTreeMap::new();
+    for name in vfs.file_names() {
+        let mut components = name.split('/');
+        match (components.next(), components.next(), components.next()) {
+            (Some(root), Some(scene), Some(_)) if root.eq_ignore_ascii_case("scene") => {
+                let extension = Path::new(&name)
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let column = match FORMATS.iter().position(|f| *f == extension) {
+                    Some(column) => column,
+                    None => continue,
+                };
+
+                let coverage = scenes
+                    .entry(scene.to_uppercase())
+                    .or_insert_with(|| FORMATS.iter().map(|_| FormatCoverage::default()).collect());
+                coverage[column].total += 1;
+                if loads(vfs, &plugins, &name) {
+                    coverage[column].loaded += 1;
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    if scenes.is_empty() {
+        return Err("coverage: no files under scene/ in the mounts".into());
+    }
+
+    print!("{:<12}", "scene");
+    for format in FORMATS {
+        print!("{:>10}", format);
+    }
+    println!("  status");
+
+    let mut complete = 0;
+    for (scene, coverage) in &scenes {
+        print!("{:<12}", scene);
+        for c in coverage {
+            print!("{:>10}", format!("{}/{}", c.loaded, c.total));
+        }
+
+        let is_complete = coverage.iter().all(|c| c.loaded == c.total);
+        if is_complete {
+            complete += 1;
+        }
+
+        println!("  {}", if is_complete { "complete" } else { "partial" });
+    }
+
+    println!(
+        "{}/{} scenes complete ({:.1}%)",
+        complete,
+        scenes.len(),
+        complete as f32 * 100. / scenes.len() as f32
+    );
+    Ok(())
+}
+
+fn loads(vfs: &Vfs, plugins: &PluginRegistry, name: &str) -> bool {
+    let loader = match plugins.loader_for(Path::new(name)) {
+        Some(loader) => loader,
+        None => return false,
+    };
+
+    let data = match vfs.read(name) {
+        Ok(data) => data,
+        Err(_) => return false,
+    };
+
+    // The loaders still panic on some malformed files
+    std::panic::catch_unwind(AssertUnwindSafe(|| loader.load(&data)))
+        .map(|result| result.is_ok())
+        .unwrap_or(false)
+}
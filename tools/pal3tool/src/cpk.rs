@@ -0,0 +1,175 @@
+use crate::convert::output_path;
+use opengb::cpk::CpkArchive;
+use opengb::vfs::Vfs;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// cpk-list <archive> [pattern]
+pub fn run_list(_vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (archive, pattern) = match args {
+        [archive] => (archive, "*"),
+        [archive, pattern] => (archive, pattern.as_str()),
+        _ => return Err("cpk-list: expected <archive> [pattern]".into()),
+    };
+
+    let archive = CpkArchive::load_from_file(archive)?;
+    for name in archive
+        .file_names()
+        .iter()
+        .filter(|n| wildcard_match(pattern, n))
+    {
+        let size = archive.entry(name).map(|e| e.origin_size).unwrap_or(0);
+        println!("{:>10} {}", size, name);
+    }
+
+    Ok(())
+}
+
+// cpk-search <dir or archive> <pattern>, looking through every archive
+// below a directory
+pub fn run_search(_vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (root, pattern) = match args {
+        [root, pattern] => (root, pattern),
+        _ => return Err("cpk-search: expected <dir or archive> <pattern>".into()),
+    };
+
+    for path in find_archives(Path::new(root)) {
+        let archive = match CpkArchive::load_from_file(&path) {
+            Ok(archive) => archive,
+            Err(e) => {
+                println!("Unable to open {:?}: {}", path, e);
+                continue;
+            }
+        };
+
+        for name in archive
+            .file_names()
+            .iter()
+            .filter(|n| wildcard_match(pattern, n))
+        {
+            println!("{}: {}", path.display(), name);
+        }
+    }
+
+    Ok(())
+}
+
+// cpk-extract <archive> <output dir> [pattern]
+// cpk-extract --all <game dir> <output dir>
+//
+// With --all, each archive is unpacked into the directory it would have
+// in the install, named after the archive, e.g. `basedata/basedata.cpk`
+// goes to `<output>/basedata/basedata/`.
+pub fn run_extract(_vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args {
+        [all, root, output] if all == "--all" => {
+            let root = Path::new(root);
+            let mut failed = 0;
+            for path in find_archives(root) {
+                let relative = path.strip_prefix(root).unwrap_or(&path).with_extension("");
+                println!("{}", path.display());
+                match extract(&path, &Path::new(output).join(relative), "*") {
+                    Ok(count) => println!("    {} files", count),
+                    Err(e) => {
+                        println!("    {}", e);
+                        failed += 1;
+                    }
+                }
+            }
+
+            if failed > 0 {
+                return Err(format!("cpk-extract: {} archives failed", failed).into());
+            }
+        }
+        [archive, output] => {
+            println!("{} files", extract(Path::new(archive), Path::new(output), "*")?);
+        }
+        [archive, output, pattern] => {
+            println!("{} files", extract(Path::new(archive), Path::new(output), pattern)?);
+        }
+        _ => {
+            return Err(
+                "cpk-extract: expected <archive> <output dir> [pattern] or --all <game dir> <output dir>"
+                    .into(),
+            )
+        }
+    }
+
+    Ok(())
+}
+
+fn extract(archive: &Path, output: &Path, pattern: &str) -> Result<usize, Box<dyn Error>> {
+    let archive = CpkArchive::load_from_file(archive)?;
+    let mut count = 0;
+    for name in archive
+        .file_names()
+        .iter()
+        .filter(|n| wildcard_match(pattern, n))
+    {
+        // Names come from the archive, so components escaping the output
+        // directory are dropped
+        let target = output_path(output, name);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        match archive.read(name) {
+            Ok(data) => {
+                std::fs::write(&target, data)?;
+                count += 1;
+            }
+            Err(e) => println!("Unable to extract {}: {}", name, e),
+        }
+    }
+
+    Ok(count)
+}
+
+fn find_archives(root: &Path) -> Vec<PathBuf> {
+    let mut archives = vec![];
+    if root.is_file() {
+        archives.push(root.to_path_buf());
+    } else if let Ok(entries) = std::fs::read_dir(root) {
+        let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
+            if path.is_dir() {
+                archives.extend(find_archives(&path));
+            } else if path
+                .extension()
+                .map(|e| e.eq_ignore_ascii_case("cpk"))
+                .unwrap_or(false)
+            {
+                archives.push(path);
+            }
+        }
+    }
+
+    archives
+}
+
+// `*` matches any run of characters, including '/', and `?` any single
+// one. Case is ignored, as for every game path.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,118 @@
+use opengb::export::gltf::*;
+use opengb::export::obj::obj_write_to_file;
+use opengb::export::{diffuse_textures, export_texture, exported_texture_name};
+use opengb::loaders::cvdloader::cvd_load_from_bytes;
+use opengb::loaders::mv3loader::mv3_load_from_bytes;
+use opengb::loaders::polloader::pol_load_from_bytes;
+use opengb::model::{cvd_to_flat_meshes, pol_to_flat_meshes, FlatMesh};
+use opengb::vfs::Vfs;
+use std::error::Error;
+use std::path::Path;
+
+pub fn run_gltf(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (input, output) = match args {
+        [input, output] => (input, output),
+        _ => return Err("gltf: expected <file> <output.gltf>".into()),
+    };
+
+    let meshes = model_to_gltf(vfs, input, Path::new(output))?;
+    export_textures(vfs, input, &meshes, output);
+    Ok(())
+}
+
+// Returns the exported meshes, for their textures
+pub fn model_to_gltf(
+    vfs: &Vfs,
+    input: &str,
+    output: &Path,
+) -> Result<Vec<FlatMesh>, Box<dyn Error>> {
+    let data = vfs.read(input)?;
+    let name = Path::new(input)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let nodes = match extension(input).as_str() {
+        "pol" => pol_to_gltf_nodes(&pol_load_from_bytes(&data)?, &name),
+        "cvd" => cvd_to_gltf_nodes(&cvd_load_from_bytes(&data)?, &name),
+        "mv3" => mv3_to_gltf_nodes(&mv3_load_from_bytes(&data)?, &name),
+        _ => return Err(format!("gltf: unsupported file type {}", input).into()),
+    };
+
+    gltf_write_to_file(&nodes, output)?;
+    Ok(nodes
+        .iter()
+        .flat_map(|n| n.meshes_recursive())
+        .cloned()
+        .collect())
+}
+
+// Static geometry only; animated models are written at their first frame
+pub fn run_obj(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (input, output) = match args {
+        [input, output] => (input, output),
+        _ => return Err("obj: expected <file> <output.obj>".into()),
+    };
+
+    let meshes = model_to_obj(vfs, input, Path::new(output))?;
+    export_textures(vfs, input, &meshes, output);
+    Ok(())
+}
+
+pub fn model_to_obj(
+    vfs: &Vfs,
+    input: &str,
+    output: &Path,
+) -> Result<Vec<FlatMesh>, Box<dyn Error>> {
+    let data = vfs.read(input)?;
+    let meshes = match extension(input).as_str() {
+        "pol" => pol_to_flat_meshes(&pol_load_from_bytes(&data)?),
+        "cvd" => cvd_to_flat_meshes(&cvd_load_from_bytes(&data)?),
+        _ => return Err(format!("obj: unsupported file type {}", input).into()),
+    };
+
+    obj_write_to_file(&meshes, output)?;
+    Ok(meshes)
+}
+
+// Missing textures are reported but don't fail the export, as the
+// geometry is still useful.
+fn export_textures<'a, I: IntoIterator<Item = &'a FlatMesh>>(
+    vfs: &Vfs,
+    model_path: &str,
+    meshes: I,
+    output: &str,
+) {
+    let output_dir = Path::new(output).parent().unwrap_or_else(|| Path::new(""));
+    for texture in diffuse_textures(meshes) {
+        let exported = exported_texture_name(&texture);
+        let result = read_texture(vfs, model_path, &texture)
+            .and_then(|data| export_texture(&data, output_dir.join(&exported)));
+        match result {
+            Ok(()) => println!("{}", exported),
+            Err(e) => println!("Unable to export texture {}: {}", texture, e),
+        }
+    }
+}
+
+// Textures are looked up next to the model, preferring the DDS version
+// the engine loads
+pub fn read_texture(vfs: &Vfs, model_path: &str, texture: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let model_dir = match model_path.rfind(|c| c == '/' || c == '\\') {
+        Some(end) => &model_path[..end + 1],
+        None => "",
+    };
+    let dds = format!(
+        "{}{}",
+        model_dir,
+        exported_texture_name(texture).replace(".png", ".dds")
+    );
+    let original = format!("{}{}", model_dir, texture);
+    vfs.read(&dds).or_else(|_| vfs.read(&original))
+}
+
+pub fn extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
@@ -0,0 +1,108 @@
+use opengb::loaders::cvdloader::*;
+use opengb::loaders::mv3loader::*;
+use opengb::loaders::polloader::*;
+use opengb::loaders::{detect_model_format, ModelFormat};
+use opengb::vfs::Vfs;
+use std::error::Error;
+use std::path::Path;
+
+pub fn run(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.is_empty() {
+        return Err("info: no file given".into());
+    }
+
+    for arg in args {
+        println!("{}", arg);
+        let data = vfs.read(arg)?;
+        match detect_model_format(&data) {
+            Some(ModelFormat::Pol { version }) => println!("    pol version {}", version),
+            Some(ModelFormat::Cvd { revision }) => {
+                println!("    cvd revision {}", revision as char)
+            }
+            Some(ModelFormat::Mv3) => println!("    mv3"),
+            None => println!("    unknown header"),
+        }
+
+        let extension = Path::new(arg)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "pol" => print_pol(&pol_load_from_bytes(&data)?),
+            "cvd" => print_cvd(&cvd_load_from_bytes(&data)?),
+            "mv3" => print_mv3(&mv3_load_from_bytes(&data)?),
+            _ => println!("    unsupported file type"),
+        }
+    }
+
+    Ok(())
+}
+
+fn print_pol(pol: &PolFile) {
+    println!("    meshes: {}", pol.meshes.len());
+    for (i, mesh) in pol.meshes.iter().enumerate() {
+        println!(
+            "    mesh {}: {} vertices, normals: {}",
+            i,
+            mesh.vertices.len(),
+            mesh.vertex_type.has(PolVertexComponents::NORMAL)
+        );
+        for material in &mesh.material_info {
+            println!(
+                "        {} triangles, textures: {:?}",
+                material.triangles.len(),
+                material.texture_names
+            );
+        }
+    }
+}
+
+fn print_cvd(cvd: &CvdFile) {
+    println!("    models: {}", cvd.models.len());
+    for model in &cvd.models {
+        print_cvd_model(model, 1);
+    }
+}
+
+fn print_cvd_model(model: &CvdModel, depth: usize) {
+    let indent = "    ".repeat(depth);
+    println!(
+        "{}model: {} frames, {} vertices, {} position keyframes",
+        indent,
+        model.mesh.frame_count,
+        model.mesh.vertex_count,
+        model.position_keyframes.len()
+    );
+    for material in &model.mesh.materials {
+        println!(
+            "{}    {} triangles, texture: {}",
+            indent,
+            material.triangles.len(),
+            material.texture_name
+        );
+    }
+
+    if let Some(children) = &model.children {
+        for child in children {
+            print_cvd_model(child, depth + 1);
+        }
+    }
+}
+
+fn print_mv3(mv3: &Mv3File) {
+    println!("    actions: {}", mv3.action_desc.len());
+    for texture in &mv3.textures {
+        for name in &texture.names {
+            println!("    texture: {}", String::from_utf8_lossy(name));
+        }
+    }
+
+    for model in &mv3.models {
+        println!(
+            "    model: {} frames, {} vertices per frame, {} meshes",
+            model.frames.len(),
+            model.vertex_per_frame,
+            model.meshes.len()
+        );
+    }
+}
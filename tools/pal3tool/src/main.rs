@@ -0,0 +1,206 @@
+mod annotate;
+mod compare;
+mod convert;
+mod coverage;
+mod cpk;
+mod export;
+mod info;
+mod overrides;
+mod scenario;
+mod thumbnail;
+mod verify;
+
+use opengb::game::GameProfile;
+use opengb::vfs::Vfs;
+use std::error::Error;
+
+type CommandFn = fn(&Vfs, &[String]) -> Result<(), Box<dyn Error>>;
+
+// name, arguments, description, entry
+const COMMANDS: &[(&str, &str, &str, CommandFn)] = &[
+    (
+        "info",
+        "<file>...",
+        "Print a summary of POL, CVD and MV3 files",
+        info::run,
+    ),
+    (
+        "dump",
+        "<file>...",
+        "Print every field of POL, CVD and MV3 files as key = value lines",
+        compare::run_dump,
+    ),
+    (
+        "annotate",
+        "<file>... [--items=<n>]",
+        "Print the layout of POL, CVD and MV3 files with offsets, flags and hex of unknown fields",
+        annotate::run,
+    ),
+    (
+        "compare",
+        "<reference command> [pattern]",
+        "Diff the dump of every mounted model against a reference dumper",
+        compare::run,
+    ),
+    (
+        "convert",
+        "<output dir> [--format=gltf|obj] [--jobs=<n>] [pattern]",
+        "Convert every mounted model to glTF or OBJ and every texture to png",
+        convert::run,
+    ),
+    (
+        "thumbnail",
+        "<output dir> [--size=<px>] [--golden=<dir>] [--jobs=<n>] [pattern]",
+        "Render a png of every mounted model without a GPU, optionally diffing golden images",
+        thumbnail::run,
+    ),
+    (
+        "cpk-list",
+        "<archive> [pattern]",
+        "List the files in a CPK archive, optionally matching a * and ? pattern",
+        cpk::run_list,
+    ),
+    (
+        "cpk-search",
+        "<dir or archive> <pattern>",
+        "Find files matching a pattern in every CPK archive below a directory",
+        cpk::run_search,
+    ),
+    (
+        "cpk-extract",
+        "<archive> <output dir> [pattern] | --all <game dir> <output dir>",
+        "Unpack a CPK archive, or every archive of an install, keeping directories",
+        cpk::run_extract,
+    ),
+    (
+        "gltf",
+        "<file> <output.gltf>",
+        "Convert a POL, CVD or MV3 model to glTF, with its textures as png",
+        export::run_gltf,
+    ),
+    (
+        "obj",
+        "<file> <output.obj>",
+        "Convert a POL or CVD model to OBJ and MTL, with its textures as png",
+        export::run_obj,
+    ),
+    (
+        "overrides",
+        "",
+        "List the files provided by more than one mount",
+        overrides::run,
+    ),
+    (
+        "verify",
+        "<manifest>",
+        "Check the mounted files against a manifest of a known-good install",
+        verify::run,
+    ),
+    (
+        "manifest",
+        "",
+        "Print a manifest of the mounted files for verify",
+        verify::run_manifest,
+    ),
+    (
+        "scenario",
+        "<file>",
+        "Run a smoke-test scenario against the mounted data",
+        scenario::run,
+    ),
+    (
+        "coverage",
+        "",
+        "Print which files of each scene the engine can load",
+        coverage::run,
+    ),
+];
+
+fn print_usage() {
+    println!("Usage: pal3tool [--data=<path>]... [--patch=<path>]... <command> [args]");
+    println!();
+    println!("Options:");
+    println!("    --data=<path>   Mount a game data directory or CPK archive.");
+    println!("    --patch=<path>  Mount a directory or CPK archive over the game data.");
+    println!("    --game=<dir>    Mount a PAL3 or PAL3A install directory.");
+    println!("    --index-dir=<dir>");
+    println!("                    Keep the tables of CPK archives mounted after it here.");
+    println!();
+    println!("Among mounts of the same kind, later mounts take priority.");
+    println!();
+    println!("Commands:");
+    for (name, args, description, _) in COMMANDS {
+        println!("    {} {}", name, args);
+        println!("        {}", description);
+    }
+}
+
+// Patches are mounted above the base data so that their files win
+const DATA_PRIORITY: i32 = 0;
+const PATCH_PRIORITY: i32 = 1;
+
+fn mount(vfs: &mut Vfs, path: &str, priority: i32) {
+    if path.to_lowercase().ends_with(".cpk") {
+        if let Err(e) = vfs.mount_archive(path, "", priority) {
+            println!("Unable to mount {}: {}", path, e);
+            std::process::exit(1);
+        }
+    } else {
+        vfs.mount_with_priority(path, priority);
+    }
+}
+
+fn main() {
+    let mut vfs = Vfs::new();
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.peek() {
+        if arg.starts_with("--data=") {
+            mount(&mut vfs, &arg["--data=".len()..], DATA_PRIORITY);
+            args.next();
+        } else if arg.starts_with("--patch=") {
+            mount(&mut vfs, &arg["--patch=".len()..], PATCH_PRIORITY);
+            args.next();
+        } else if arg.starts_with("--index-dir=") {
+            vfs.set_index_dir(&arg["--index-dir=".len()..]);
+            args.next();
+        } else if arg.starts_with("--game=") {
+            let root = &arg["--game=".len()..];
+            match GameProfile::detect(root) {
+                Some(profile) => {
+                    println!("Found {} in {}", profile.game.name(), root);
+                    profile.mount(&mut vfs);
+                }
+                None => {
+                    println!("No PAL3 or PAL3A install found in {}", root);
+                    std::process::exit(1);
+                }
+            }
+            args.next();
+        } else {
+            break;
+        }
+    }
+
+    vfs.build_index();
+    let command = args.next();
+    let command_args: Vec<String> = args.collect();
+    let entry = COMMANDS
+        .iter()
+        .find(|(name, _, _, _)| Some(*name) == command.as_deref())
+        .map(|(_, _, _, entry)| entry);
+
+    match entry {
+        Some(entry) => {
+            if let Err(e) = entry(&vfs, &command_args) {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        None => {
+            print_usage();
+            if command.is_some() && command.as_deref() != Some("help") {
+                std::process::exit(1);
+            }
+        }
+    }
+}
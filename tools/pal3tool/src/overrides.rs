@@ -0,0 +1,11 @@
+use opengb::vfs::Vfs;
+use std::error::Error;
+
+pub fn run(vfs: &Vfs, _args: &[String]) -> Result<(), Box<dyn Error>> {
+    for mount in vfs.mounts() {
+        println!("mount: {}", mount.display());
+    }
+
+    print!("{}", vfs.override_report());
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use opengb::plugins::PluginRegistry;
+use opengb::vfs::Vfs;
+use std::error::Error;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+
+// Commands that need the game runtime, which doesn't exist yet. They fail
+// like any other step, so a scenario using them can't pass by accident.
+const RUNTIME_COMMANDS: &[&str] = &["load_scene", "walk_to", "interact", "expect_flag"];
+
+// Runs a scenario file, one console-like command per line:
+//
+//     expect_file <path>    the file exists in the mounts
+//     load <path>           the model loads
+//
+// Blank lines and lines starting with '#' are ignored. Every step runs
+// even after a failure, so that one report lists all broken files.
+pub fn run(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let scenario_path = args.get(0).ok_or("scenario: no scenario file given")?;
+    let scenario = std::fs::read_to_string(scenario_path)?;
+    let plugins = PluginRegistry::with_builtin();
+
+    let (mut passed, mut failed) = (0, 0);
+    for (i, line) in scenario.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match run_step(vfs, &plugins, words[0], &words[1..]) {
+            Ok(()) => passed += 1,
+            Err(e) => {
+                failed += 1;
+                println!("{}:{}: FAILED {}: {}", scenario_path, i + 1, line, e);
+            }
+        }
+    }
+
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        return Err("scenario: some steps failed".into());
+    }
+
+    Ok(())
+}
+
+fn run_step(
+    vfs: &Vfs,
+    plugins: &PluginRegistry,
+    command: &str,
+    args: &[&str],
+) -> Result<(), Box<dyn Error>> {
+    match (command, args) {
+        ("expect_file", [path]) => {
+            vfs.read(path)?;
+            Ok(())
+        }
+        ("load", [path]) => {
+            let loader = plugins
+                .loader_for(Path::new(path))
+                .ok_or("unsupported file type")?;
+            let data = vfs.read(path)?;
+            // The loaders still panic on some malformed files
+            let meshes = std::panic::catch_unwind(AssertUnwindSafe(|| loader.load(&data)))
+                .map_err(|_| "the loader panicked")??;
+            if meshes.is_empty() {
+                return Err("no meshes".into());
+            }
+
+            Ok(())
+        }
+        _ if RUNTIME_COMMANDS.contains(&command) => {
+            Err("needs the game runtime, which doesn't exist yet".into())
+        }
+        _ => Err("unknown command or wrong arguments".into()),
+    }
+}
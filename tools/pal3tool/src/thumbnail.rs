@@ -0,0 +1,187 @@
+use crate::convert::output_path;
+use crate::cpk::wildcard_match;
+use crate::export::{extension, read_texture};
+use opengb::export::diffuse_textures;
+use opengb::loaders::cvdloader::cvd_load_from_bytes;
+use opengb::loaders::mv3loader::mv3_load_from_bytes;
+use opengb::loaders::polloader::pol_load_from_bytes;
+use opengb::model::{cvd_to_flat_meshes, mv3_to_flat_meshes, pol_to_flat_meshes};
+use opengb::thumbnail::{image_difference, ThumbnailRenderer};
+use opengb::vfs::Vfs;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+
+const MODEL_EXTENSIONS: &[&str] = &["pol", "cvd", "mv3"];
+// Per channel, so that rounding differences between compilers don't count
+const CHANNEL_TOLERANCE: u8 = 8;
+// Fraction of the pixels allowed to differ, for edges landing on the
+// other side of a pixel center
+const MAX_DIFFERENCE: f32 = 0.001;
+
+enum Outcome {
+    Rendered,
+    Matched,
+    NoGolden,
+    Mismatched(String),
+    Failed(String),
+}
+
+// thumbnail <output dir> [--size=<px>] [--golden=<dir>] [--jobs=<n>] [pattern]
+//
+// Renders every mounted model matching the pattern to a png, keeping the
+// directory layout. With --golden, each thumbnail is also compared to the
+// png at the same place in that directory, and the command fails if any
+// differs, for use in CI. A report is written to the output directory.
+pub fn run(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut output = None;
+    let mut size = 256;
+    let mut golden = None;
+    let mut jobs = 0;
+    let mut pattern = "*".to_owned();
+    for arg in args {
+        match arg.as_str() {
+            _ if arg.starts_with("--size=") => size = arg["--size=".len()..].parse()?,
+            _ if arg.starts_with("--golden=") => {
+                golden = Some(PathBuf::from(&arg["--golden=".len()..]))
+            }
+            _ if arg.starts_with("--jobs=") => jobs = arg["--jobs=".len()..].parse()?,
+            _ if arg.starts_with("--") => {
+                return Err(format!("thumbnail: unknown option {}", arg).into())
+            }
+            _ if output.is_none() => output = Some(PathBuf::from(arg)),
+            _ => pattern = arg.clone(),
+        }
+    }
+
+    let output = output.ok_or("thumbnail: expected <output dir>")?;
+    let names: Vec<String> = vfs
+        .file_names()
+        .into_iter()
+        .filter(|name| {
+            MODEL_EXTENSIONS.contains(&extension(name).as_str()) && wildcard_match(&pattern, name)
+        })
+        .collect();
+
+    let renderer = ThumbnailRenderer::new(size);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let outcomes: Vec<(String, Outcome)> = pool.install(|| {
+        names
+            .par_iter()
+            .map(|name| {
+                let outcome = thumbnail(vfs, name, &renderer, &output, golden.as_deref());
+                (name.clone(), outcome)
+            })
+            .collect()
+    });
+
+    let (mut matched, mut mismatched, mut no_golden, mut failed) = (0, 0, 0, 0);
+    let mut errors = String::new();
+    for (name, outcome) in &outcomes {
+        match outcome {
+            Outcome::Rendered => (),
+            Outcome::Matched => matched += 1,
+            Outcome::NoGolden => no_golden += 1,
+            Outcome::Mismatched(reason) => {
+                mismatched += 1;
+                errors.push_str(&format!("{}: {}\n", name, reason));
+            }
+            Outcome::Failed(error) => {
+                failed += 1;
+                errors.push_str(&format!("{}: {}\n", name, error));
+            }
+        }
+    }
+
+    let mut report = format!(
+        "rendered: {}\nfailed: {}\n",
+        outcomes.len() - failed,
+        failed
+    );
+    if golden.is_some() {
+        report.push_str(&format!(
+            "matched: {}\nmismatched: {}\nwithout golden image: {}\n",
+            matched, mismatched, no_golden
+        ));
+    }
+
+    report.push_str(&errors);
+    print!("{}", report);
+    std::fs::create_dir_all(&output)?;
+    std::fs::write(output.join("thumbnail-report.txt"), report)?;
+    if mismatched > 0 {
+        return Err("thumbnail: some thumbnails differ from the golden images".into());
+    }
+
+    Ok(())
+}
+
+fn thumbnail(
+    vfs: &Vfs,
+    name: &str,
+    renderer: &ThumbnailRenderer,
+    output: &Path,
+    golden: Option<&Path>,
+) -> Outcome {
+    let target = output_path(output, name).with_extension("png");
+    let render = || -> Result<Outcome, Box<dyn Error>> {
+        let data = vfs.read(name)?;
+        let meshes = match extension(name).as_str() {
+            "pol" => pol_to_flat_meshes(&pol_load_from_bytes(&data)?),
+            "cvd" => cvd_to_flat_meshes(&cvd_load_from_bytes(&data)?),
+            _ => mv3_to_flat_meshes(&mv3_load_from_bytes(&data)?),
+        };
+
+        // Missing textures are drawn plain grey rather than failing
+        let mut textures = HashMap::new();
+        for texture in diffuse_textures(&meshes) {
+            let image = read_texture(vfs, name, &texture)
+                .and_then(|data| Ok(image::load_from_memory(&data)?.to_rgba()));
+            if let Ok(image) = image {
+                textures.insert(texture, image);
+            }
+        }
+
+        let image = renderer.render(&meshes, &textures);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        image.save(&target)?;
+
+        let golden = match golden {
+            Some(golden) => output_path(golden, name).with_extension("png"),
+            None => return Ok(Outcome::Rendered),
+        };
+        if !golden.exists() {
+            return Ok(Outcome::NoGolden);
+        }
+
+        let expected = image::open(&golden)?.to_rgba();
+        Ok(
+            match image_difference(&image, &expected, CHANNEL_TOLERANCE) {
+                None => Outcome::Mismatched(format!(
+                    "the thumbnail is {:?}, but {:?} is {:?}",
+                    image.dimensions(),
+                    golden,
+                    expected.dimensions()
+                )),
+                Some(difference) if difference > MAX_DIFFERENCE => Outcome::Mismatched(format!(
+                    "{:.2}% of the pixels differ from {:?}",
+                    difference * 100.,
+                    golden
+                )),
+                Some(_) => Outcome::Matched,
+            },
+        )
+    };
+
+    // The loaders still panic on some malformed files
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| render().map_err(|e| e.to_string())));
+    match result {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(error)) => Outcome::Failed(error),
+        Err(_) => Outcome::Failed("the loader panicked".to_owned()),
+    }
+}
@@ -0,0 +1,91 @@
+use opengb::vfs::Vfs;
+use std::error::Error;
+
+// Manifests list one file per line as `<crc32> <size> <path>`, with the
+// crc in hex and the path as seen through the mounts. One is generated per
+// release and region from a known-good install with the manifest command.
+pub fn run(vfs: &Vfs, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let manifest_path = args.get(0).ok_or("verify: no manifest given")?;
+    let manifest = std::fs::read_to_string(manifest_path)?;
+
+    let mut checked = 0;
+    let mut mismatches = 0;
+    for (i, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.splitn(3, ' ');
+        let entry = match (fields.next(), fields.next(), fields.next()) {
+            (Some(crc), Some(size), Some(path)) => u32::from_str_radix(crc, 16)
+                .ok()
+                .and_then(|crc| size.parse::<usize>().ok().map(|size| (crc, size, path))),
+            _ => None,
+        };
+
+        let (expected_crc, expected_size, path) = match entry {
+            Some(entry) => entry,
+            None => {
+                println!("{}:{}: ignoring malformed line", manifest_path, i + 1);
+                continue;
+            }
+        };
+
+        checked += 1;
+        match vfs.read(path) {
+            Ok(data) => {
+                let crc = crc32(&data);
+                if data.len() != expected_size || crc != expected_crc {
+                    mismatches += 1;
+                    println!(
+                        "MISMATCH {}: {:08x} {} bytes, expected {:08x} {} bytes",
+                        path,
+                        crc,
+                        data.len(),
+                        expected_crc,
+                        expected_size
+                    );
+                }
+            }
+            Err(e) => {
+                mismatches += 1;
+                println!("MISSING {}: {}", path, e);
+            }
+        }
+    }
+
+    println!("{} files checked, {} mismatches", checked, mismatches);
+    if mismatches > 0 {
+        return Err("verify: the install differs from the manifest".into());
+    }
+
+    Ok(())
+}
+
+// Prints a manifest of every mounted file
+pub fn run_manifest(vfs: &Vfs, _args: &[String]) -> Result<(), Box<dyn Error>> {
+    for name in vfs.file_names() {
+        let data = vfs.read(&name)?;
+        println!("{:08x} {} {}", crc32(&data), data.len(), name);
+    }
+
+    Ok(())
+}
+
+// CRC-32 as used by zip and png
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
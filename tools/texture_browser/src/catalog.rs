@@ -0,0 +1,91 @@
+use crate::options::BrowserOptions;
+use opengb::vfs::Vfs;
+use rayon::prelude::*;
+use std::error::Error;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+
+const TEXTURE_EXTENSIONS: &[&str] = &["dds", "tga", "bmp"];
+
+#[derive(Debug, Clone)]
+pub struct TextureEntry {
+    pub name: String,
+    // The decoded texture written as png, for radiance to load
+    pub preview: Option<PathBuf>,
+    pub dimensions: (u32, u32),
+    pub error: Option<String>,
+}
+
+// Decodes every mounted texture matching the filter. Each is written as a
+// png preview, and to the export directory if there is one; textures that
+// fail to decode are kept with their error, so that decoder bugs show up
+// in the grid.
+pub fn load_catalog(vfs: &Vfs, options: &BrowserOptions) -> Vec<TextureEntry> {
+    let preview_dir = std::env::temp_dir().join("texture_browser");
+    let mut names: Vec<String> = vfs
+        .file_names()
+        .into_iter()
+        .filter(|name| {
+            let lower = name.to_lowercase();
+            let extension = lower.rsplit('.').next().unwrap_or("");
+            TEXTURE_EXTENSIONS.contains(&extension)
+                && options
+                    .filter
+                    .as_ref()
+                    .map(|filter| lower.contains(filter.as_str()))
+                    .unwrap_or(true)
+        })
+        .collect();
+    names.sort();
+
+    names
+        .par_iter()
+        .map(|name| {
+            let decode = || -> Result<(PathBuf, (u32, u32)), Box<dyn Error>> {
+                let image = image::load_from_memory(&vfs.read(name)?)?;
+                let preview = output_path(&preview_dir, name);
+                save_png(&image, &preview)?;
+                if let Some(export) = &options.export {
+                    save_png(&image, &output_path(export, name))?;
+                }
+
+                Ok((preview, (image.width(), image.height())))
+            };
+
+            // The decoders still panic on some malformed files
+            let result =
+                std::panic::catch_unwind(AssertUnwindSafe(|| decode().map_err(|e| e.to_string())))
+                    .unwrap_or_else(|_| Err("the decoder panicked".to_owned()));
+            match result {
+                Ok((preview, dimensions)) => TextureEntry {
+                    name: name.clone(),
+                    preview: Some(preview),
+                    dimensions,
+                    error: None,
+                },
+                Err(error) => TextureEntry {
+                    name: name.clone(),
+                    preview: None,
+                    dimensions: (0, 0),
+                    error: Some(error),
+                },
+            }
+        })
+        .collect()
+}
+
+// Keeps the directories of the game path
+fn output_path(dir: &Path, name: &str) -> PathBuf {
+    let relative = name.replace('\\', "/");
+    dir.join(relative.trim_start_matches('/'))
+        .with_extension("png")
+}
+
+fn save_png(image: &image::DynamicImage, path: &Path) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    image.save(path)?;
+    Ok(())
+}
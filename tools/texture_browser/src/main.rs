@@ -0,0 +1,137 @@
+mod catalog;
+mod options;
+mod pageentity;
+mod scene;
+
+use catalog::{load_catalog, TextureEntry};
+use opengb::vfs::Vfs;
+use options::BrowserOptions;
+use pageentity::page_size;
+use radiance::application;
+use radiance::application::utils::FpsCounter;
+use radiance::scene::CoreScene;
+
+struct ApplicationCallbacks {
+    entries: Vec<TextureEntry>,
+    options: BrowserOptions,
+    page: usize,
+    page_timer: f32,
+    fps_counter: FpsCounter,
+}
+
+impl application::ApplicationCallbacks for ApplicationCallbacks {
+    fn on_initialized<T: application::ApplicationCallbacks>(
+        &mut self,
+        app: &mut application::Application<T>,
+    ) {
+        self.load_page(app);
+    }
+
+    fn on_updated<T: application::ApplicationCallbacks>(
+        &mut self,
+        app: &mut application::Application<T>,
+        delta_sec: f32,
+    ) {
+        let fps = self.fps_counter.update_fps(delta_sec);
+        let title = format!(
+            "Texture Browser - OpenPAL3 Tools - Page {}/{} - FPS: {}",
+            self.page + 1,
+            self.page_count(),
+            fps
+        );
+        app.set_title(&title);
+
+        if let Some(page_sec) = self.options.page_sec {
+            self.page_timer += delta_sec;
+            if self.page_timer >= page_sec {
+                self.page_timer = 0.;
+                self.page = (self.page + 1) % self.page_count();
+                self.load_page(app);
+            }
+        }
+    }
+}
+
+impl ApplicationCallbacks {
+    pub fn new(entries: Vec<TextureEntry>, options: BrowserOptions) -> Self {
+        let mut callbacks = ApplicationCallbacks {
+            entries,
+            page: 0,
+            page_timer: 0.,
+            fps_counter: FpsCounter::new(),
+            options,
+        };
+        callbacks.page = callbacks.options.page.min(callbacks.page_count() - 1);
+        callbacks
+    }
+
+    fn page_count(&self) -> usize {
+        let size = page_size(self.options.columns);
+        ((self.entries.len() + size - 1) / size).max(1)
+    }
+
+    // The grid has no room for names, so the page is listed here
+    fn load_page<T: application::ApplicationCallbacks>(
+        &self,
+        app: &mut application::Application<T>,
+    ) {
+        let size = page_size(self.options.columns);
+        let start = self.page * size;
+        let entries: Vec<TextureEntry> = self
+            .entries
+            .iter()
+            .skip(start)
+            .take(size)
+            .cloned()
+            .collect();
+
+        println!("Page {}/{}", self.page + 1, self.page_count());
+        for (i, entry) in entries.iter().enumerate() {
+            match &entry.error {
+                Some(error) => println!("    {}: {} failed: {}", start + i + 1, entry.name, error),
+                None => println!(
+                    "    {}: {} {}x{}",
+                    start + i + 1,
+                    entry.name,
+                    entry.dimensions.0,
+                    entry.dimensions.1
+                ),
+            }
+        }
+
+        app.engine_mut()
+            .load_scene(CoreScene::new(scene::TextureBrowserScene {
+                entries,
+                columns: self.options.columns,
+            }));
+    }
+}
+
+fn main() {
+    let mut vfs = Vfs::new();
+    let options = BrowserOptions::from_args(&mut vfs);
+    let entries = load_catalog(&vfs, &options);
+    if entries.is_empty() {
+        println!("No textures found, mount some with --data=<dir or cpk> or --game=<dir>");
+        std::process::exit(1);
+    }
+
+    let failed: Vec<&TextureEntry> = entries.iter().filter(|e| e.error.is_some()).collect();
+    println!(
+        "Decoded {} of {} textures",
+        entries.len() - failed.len(),
+        entries.len()
+    );
+    for entry in failed {
+        println!("    {}: {}", entry.name, entry.error.as_ref().unwrap());
+    }
+
+    if let Some(export) = &options.export {
+        println!("Exported to {:?}", export);
+    }
+
+    let mut application =
+        application::Application::new(ApplicationCallbacks::new(entries, options));
+    application.initialize();
+    application.run();
+}
@@ -0,0 +1,76 @@
+use opengb::game::GameProfile;
+use opengb::vfs::Vfs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct BrowserOptions {
+    pub filter: Option<String>,
+    pub export: Option<PathBuf>,
+    pub columns: usize,
+    pub page: usize,
+    pub page_sec: Option<f32>,
+}
+
+impl BrowserOptions {
+    // Mounts given with --data and --game go into `vfs`
+    pub fn from_args(vfs: &mut Vfs) -> Self {
+        let mut options = BrowserOptions {
+            filter: None,
+            export: None,
+            columns: 6,
+            page: 0,
+            page_sec: Some(5.),
+        };
+
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                _ if arg.starts_with("--data=") => {
+                    let path = &arg["--data=".len()..];
+                    if path.to_lowercase().ends_with(".cpk") {
+                        if let Err(e) = vfs.mount_archive(path, "", 0) {
+                            println!("Unable to mount {}: {}", path, e);
+                        }
+                    } else {
+                        vfs.mount(path);
+                    }
+                }
+                _ if arg.starts_with("--game=") => {
+                    let root = &arg["--game=".len()..];
+                    match GameProfile::detect(root) {
+                        Some(profile) => profile.mount(vfs),
+                        None => println!("No PAL3 or PAL3A install found in {}", root),
+                    }
+                }
+                // Case is ignored, as for every game path
+                _ if arg.starts_with("--filter=") => {
+                    options.filter = Some(arg["--filter=".len()..].to_lowercase())
+                }
+                _ if arg.starts_with("--export=") => {
+                    options.export = Some(PathBuf::from(&arg["--export=".len()..]))
+                }
+                _ if arg.starts_with("--columns=") => {
+                    match arg["--columns=".len()..].parse::<usize>() {
+                        Ok(columns) if columns > 0 => options.columns = columns,
+                        _ => println!("Expected --columns=<count>"),
+                    }
+                }
+                // Pages are numbered from 1, as in the window title
+                _ if arg.starts_with("--page=") => match arg["--page=".len()..].parse::<usize>() {
+                    Ok(page) if page > 0 => options.page = page - 1,
+                    _ => println!("Expected --page=<number>"),
+                },
+                "--no-cycle" => options.page_sec = None,
+                _ if arg.starts_with("--page-sec=") => {
+                    match arg["--page-sec=".len()..].parse::<f32>() {
+                        Ok(sec) if sec > 0. => options.page_sec = Some(sec),
+                        _ => println!("Expected --page-sec=<seconds>"),
+                    }
+                }
+                _ => println!("Unknown argument {}", arg),
+            }
+        }
+
+        vfs.build_index();
+        options
+    }
+}
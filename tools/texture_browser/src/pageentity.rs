@@ -0,0 +1,79 @@
+use crate::catalog::TextureEntry;
+use opengb::material::create_screen_material;
+use opengb::ui::overlay::UiLayer;
+use opengb::ui::ScreenSpace;
+use radiance::math::{Vec2, Vec3};
+use radiance::rendering::RenderObject;
+use radiance::scene::{CoreEntity, Entity, EntityCallbacks};
+
+const SCREEN_WIDTH: f32 = 800.;
+const SCREEN_HEIGHT: f32 = 600.;
+const PADDING: f32 = 4.;
+
+// How many textures fit on a page of square cells
+pub fn page_size(columns: usize) -> usize {
+    let cell = SCREEN_WIDTH / columns as f32;
+    columns * ((SCREEN_HEIGHT / cell) as usize).max(1)
+}
+
+// One page of the grid, in reading order. Textures are scaled to fit their
+// cell, keeping their aspect; those that failed to decode are shown red.
+pub struct PageEntity {
+    entries: Vec<TextureEntry>,
+    columns: usize,
+}
+
+impl PageEntity {
+    pub fn new(entries: Vec<TextureEntry>, columns: usize) -> Self {
+        PageEntity { entries, columns }
+    }
+
+    fn build_layer(&self) -> UiLayer {
+        let mut layer = UiLayer::new(ScreenSpace::new(SCREEN_WIDTH, SCREEN_HEIGHT));
+        let cell = SCREEN_WIDTH / self.columns as f32;
+        let inner = cell - PADDING * 2.;
+        for (i, entry) in self.entries.iter().enumerate() {
+            let position = Vec2::new(
+                (i % self.columns) as f32 * cell + PADDING,
+                (i / self.columns) as f32 * cell + PADDING,
+            );
+            let background = if entry.error.is_some() {
+                Vec3::new(0.6, 0.1, 0.1)
+            } else {
+                Vec3::new(0.2, 0.2, 0.2)
+            };
+            layer.draw_rect(&position, &Vec2::new(inner, inner), &background);
+
+            if let Some(preview) = &entry.preview {
+                let (width, height) = entry.dimensions;
+                let scale = inner / width.max(height).max(1) as f32;
+                let size = Vec2::new(width as f32 * scale, height as f32 * scale);
+                layer.draw_image(
+                    preview,
+                    &Vec2::new(
+                        position.x + (inner - size.x) / 2.,
+                        position.y + (inner - size.y) / 2.,
+                    ),
+                    &size,
+                    (Vec2::new(0., 0.), Vec2::new(1., 1.)),
+                    &Vec3::new(1., 1., 1.),
+                );
+            }
+        }
+
+        layer
+    }
+}
+
+impl EntityCallbacks for PageEntity {
+    fn on_loading<T: EntityCallbacks>(&mut self, entity: &mut CoreEntity<T>) {
+        let layer = self.build_layer();
+        for batch in layer.batches() {
+            entity.add_component(RenderObject::new_with_data(
+                batch.to_vertex_buffer(layer.screen()),
+                batch.indices(),
+                Box::new(create_screen_material(&batch.texture_path)),
+            ));
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use super::catalog::TextureEntry;
+use super::pageentity::PageEntity;
+use radiance::scene::{CoreEntity, CoreScene, SceneCallbacks};
+
+pub struct TextureBrowserScene {
+    pub entries: Vec<TextureEntry>,
+    pub columns: usize,
+}
+
+impl SceneCallbacks for TextureBrowserScene {
+    fn on_loading<T: SceneCallbacks>(&mut self, scene: &mut CoreScene<T>) {
+        scene.add_entity(CoreEntity::new(PageEntity::new(
+            self.entries.clone(),
+            self.columns,
+        )));
+    }
+}